@@ -1,270 +1,414 @@
-use std::process::Command;
-use std::fs::File;
-use std::io::Write;
-use crate::settings::AdbStudioSettings;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::fs;
+use tokio_util::sync::CancellationToken;
+use crate::adb_client::{remote_basename, remote_join, AdbServerClient, PartialFile, ProgressReporter, ProgressSender, ShellOutput};
+use crate::error::AdbError;
+use crate::settings::{AdbBackend, AdbStudioSettings};
 
+// kill_on_drop lets a cancelled or abandoned operation take its adb process down with it.
 fn get_adb_command(settings: &AdbStudioSettings) -> Command {
-    Command::new(if settings.adb_path.is_empty() { "adb" } else { &settings.adb_path })
+    let mut command = Command::new(settings.adb_program());
+    command.kill_on_drop(true);
+    command
 }
 
-pub async fn adb_devices(settings: &AdbStudioSettings) -> Result<String, String> {
-    let output = get_adb_command(settings).arg("devices").output();
+pub fn server_client(settings: &AdbStudioSettings) -> AdbServerClient {
+    AdbServerClient::from_env().with_launcher(settings.adb_program())
+}
+
+async fn run_adb(settings: &AdbStudioSettings, args: &[&str]) -> Result<String, AdbError> {
+    let output = get_adb_command(settings).args(args).output().await;
 
     match output {
         Ok(output) => {
             if output.status.success() {
                 Ok(String::from_utf8_lossy(&output.stdout).to_string())
             } else {
-                Err(String::from_utf8_lossy(&output.stderr).to_string())
+                Err(failure(output.status.code(), &output.stdout, &output.stderr))
             }
         }
-        Err(e) => Err(e.to_string()),
+        Err(e) => Err(AdbError::from_spawn(settings.adb_program(), e)),
     }
 }
 
-pub async fn adb_reboot(settings: &AdbStudioSettings, serial: &str) -> Result<(), String> {
-    let output = get_adb_command(settings)
-        .arg("-s")
-        .arg(serial)
-        .arg("reboot")
-        .output();
+// Device-side tools often report errors on stdout, so fall back to it when stderr is empty.
+fn failure(code: Option<i32>, stdout: &[u8], stderr: &[u8]) -> AdbError {
+    let message = if stderr.iter().all(u8::is_ascii_whitespace) { stdout } else { stderr };
+    AdbError::classify(code, &String::from_utf8_lossy(message))
+}
 
-    match output {
-        Ok(output) => {
-            if output.status.success() {
-                Ok(())
-            } else {
-                Err(String::from_utf8_lossy(&output.stderr).to_string())
-            }
-        }
-        Err(e) => Err(e.to_string()),
+pub fn shell_result(output: ShellOutput) -> Result<String, AdbError> {
+    if output.exit_code == 0 {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        Err(failure(Some(output.exit_code), &output.stdout, &output.stderr))
     }
 }
 
-pub async fn adb_reboot_recovery(settings: &AdbStudioSettings, serial: &str) -> Result<(), String> {
-    let output = get_adb_command(settings)
-        .arg("-s")
-        .arg(serial)
-        .arg("reboot")
-        .arg("recovery")
-        .output();
+/// Runs `operation` until it finishes or `cancel` fires. Dropping the operation closes its adb
+/// socket or kills its `adb` process, which stops the work on the device as well.
+pub async fn cancellable<T>(
+    cancel: &CancellationToken,
+    operation: impl Future<Output = Result<T, AdbError>>,
+) -> Result<T, AdbError> {
+    tokio::select! {
+        result = operation => result,
+        _ = cancel.cancelled() => Err(AdbError::Cancelled),
+    }
+}
 
-    match output {
-        Ok(output) => {
-            if output.status.success() {
-                Ok(())
-            } else {
-                Err(String::from_utf8_lossy(&output.stderr).to_string())
-            }
-        }
-        Err(e) => Err(e.to_string()),
+pub fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+pub async fn adb_devices(settings: &AdbStudioSettings) -> Result<String, AdbError> {
+    match settings.backend {
+        AdbBackend::Server => server_client(settings)
+            .host_query("host:devices-l")
+            .await
+            .map(|list| format!("List of devices attached\n{}", list)),
+        AdbBackend::Executable => run_adb(settings, &["devices", "-l"]).await,
     }
 }
 
-pub async fn adb_reboot_bootloader(settings: &AdbStudioSettings, serial: &str) -> Result<(), String> {
-    let output = get_adb_command(settings)
-        .arg("-s")
-        .arg(serial)
-        .arg("reboot")
-        .arg("bootloader")
-        .output();
+pub async fn adb_server_version(settings: &AdbStudioSettings) -> Result<String, AdbError> {
+    match settings.backend {
+        AdbBackend::Server => server_client(settings).version().await.map(|v| format!("v{}", v)),
+        AdbBackend::Executable => run_adb(settings, &["version"])
+            .await
+            .map(|out| out.lines().next().unwrap_or_default().trim_start_matches("Android Debug Bridge version ").to_string()),
+    }
+}
 
-    match output {
-        Ok(output) => {
-            if output.status.success() {
-                Ok(())
-            } else {
-                Err(String::from_utf8_lossy(&output.stderr).to_string())
+async fn reboot(settings: &AdbStudioSettings, serial: &str, target: &str) -> Result<(), AdbError> {
+    match settings.backend {
+        AdbBackend::Server => {
+            let conn = server_client(settings).open(serial, &format!("reboot:{}", target)).await?;
+            conn.read_to_end().await.map(|_| ())
+        }
+        AdbBackend::Executable => {
+            let mut args = vec!["-s", serial, "reboot"];
+            if !target.is_empty() {
+                args.push(target);
             }
+            run_adb(settings, &args).await.map(|_| ())
         }
-        Err(e) => Err(e.to_string()),
     }
 }
 
-pub async fn adb_disconnect(settings: &AdbStudioSettings, serial: &str) -> Result<(), String> {
-    let output = get_adb_command(settings).arg("disconnect").arg(serial).output();
+pub async fn adb_reboot(settings: &AdbStudioSettings, serial: &str) -> Result<(), AdbError> {
+    reboot(settings, serial, "").await
+}
 
-    match output {
-        Ok(output) => {
-            if output.status.success() {
-                Ok(())
-            } else {
-                Err(String::from_utf8_lossy(&output.stderr).to_string())
-            }
-        }
-        Err(e) => Err(e.to_string()),
+pub async fn adb_reboot_recovery(settings: &AdbStudioSettings, serial: &str) -> Result<(), AdbError> {
+    reboot(settings, serial, "recovery").await
+}
+
+pub async fn adb_reboot_bootloader(settings: &AdbStudioSettings, serial: &str) -> Result<(), AdbError> {
+    reboot(settings, serial, "bootloader").await
+}
+
+pub async fn adb_disconnect(settings: &AdbStudioSettings, serial: &str) -> Result<(), AdbError> {
+    match settings.backend {
+        AdbBackend::Server => server_client(settings)
+            .host_query(&format!("host:disconnect:{}", serial))
+            .await
+            .map(|_| ()),
+        AdbBackend::Executable => run_adb(settings, &["disconnect", serial]).await.map(|_| ()),
     }
 }
 
-pub async fn adb_shell(settings: &AdbStudioSettings, command: &str) -> Result<String, String> {
-    let output = get_adb_command(settings).arg("shell").arg(command).output();
+/// Connects to a device listening for adb over TCP at `address` (`host:port`).
+pub async fn adb_connect(settings: &AdbStudioSettings, address: &str) -> Result<String, AdbError> {
+    let output = match settings.backend {
+        AdbBackend::Server => server_client(settings).host_query(&format!("host:connect:{}", address)).await?,
+        AdbBackend::Executable => run_adb(settings, &["connect", address]).await?,
+    };
+    // adb reports connection failures as a successful reply, so the message has to be checked.
+    let message = output.trim().to_string();
+    if message.contains("connected to") {
+        Ok(message)
+    } else {
+        Err(AdbError::classify(None, &message))
+    }
+}
 
-    match output {
-        Ok(output) => {
-            if output.status.success() {
-                Ok(String::from_utf8_lossy(&output.stdout).to_string())
-            } else {
-                Err(String::from_utf8_lossy(&output.stderr).to_string())
-            }
+/// Pairs with an Android 11+ device using the code shown in its wireless debugging settings.
+pub async fn adb_pair(settings: &AdbStudioSettings, address: &str, code: &str) -> Result<String, AdbError> {
+    let output = match settings.backend {
+        AdbBackend::Server => server_client(settings).host_query(&format!("host:pair:{}:{}", code, address)).await?,
+        AdbBackend::Executable => run_adb(settings, &["pair", address, code]).await?,
+    };
+    let message = output.trim().to_string();
+    if message.contains("Successfully paired") {
+        Ok(message)
+    } else {
+        Err(AdbError::classify(None, &message))
+    }
+}
+
+/// Restarts adbd on the device listening for TCP connections on `port`.
+pub async fn adb_tcpip(settings: &AdbStudioSettings, serial: &str, port: u16) -> Result<String, AdbError> {
+    match settings.backend {
+        AdbBackend::Server => {
+            let conn = server_client(settings).open(serial, &format!("tcpip:{}", port)).await?;
+            conn.read_to_end().await.map(|out| String::from_utf8_lossy(&out).trim().to_string())
         }
-        Err(e) => Err(e.to_string()),
+        AdbBackend::Executable => run_adb(settings, &["-s", serial, "tcpip", &port.to_string()]).await,
     }
 }
 
-pub async fn adb_push(settings: &AdbStudioSettings, serial: &str, from: &str, to: &str) -> Result<String, String> {
-    let output = get_adb_command(settings)
-        .arg("-s")
-        .arg(serial)
-        .arg("push")
-        .arg(from)
-        .arg(to)
-        .output();
+/// Lists the wireless debugging services the adb server has discovered over mDNS.
+pub async fn adb_mdns_services(settings: &AdbStudioSettings) -> Result<String, AdbError> {
+    match settings.backend {
+        AdbBackend::Server => server_client(settings).host_query("host:mdns:services").await,
+        AdbBackend::Executable => run_adb(settings, &["mdns", "services"]).await,
+    }
+}
 
-    match output {
-        Ok(output) => {
-            if output.status.success() {
-                Ok(String::from_utf8_lossy(&output.stdout).to_string())
-            } else {
-                Err(String::from_utf8_lossy(&output.stderr).to_string())
+pub async fn adb_shell(settings: &AdbStudioSettings, serial: &str, command: &str) -> Result<String, AdbError> {
+    match settings.backend {
+        AdbBackend::Server => shell_result(server_client(settings).shell(serial, command).await?),
+        AdbBackend::Executable => run_adb(settings, &["-s", serial, "shell", command]).await,
+    }
+}
+
+/// Runs `command` and returns its output whatever its exit status, for commands such as `ls`
+/// that still print something useful when part of the work fails.
+pub async fn adb_shell_output(settings: &AdbStudioSettings, serial: &str, command: &str) -> Result<ShellOutput, AdbError> {
+    match settings.backend {
+        AdbBackend::Server => server_client(settings).shell(serial, command).await,
+        AdbBackend::Executable => {
+            let output = get_adb_command(settings)
+                .args(["-s", serial, "shell", command])
+                .output()
+                .await
+                .map_err(|e| AdbError::from_spawn(settings.adb_program(), e))?;
+            match output.status.code() {
+                Some(exit_code) => Ok(ShellOutput { stdout: output.stdout, stderr: output.stderr, exit_code }),
+                None => Err(failure(None, &output.stdout, &output.stderr)),
             }
         }
-        Err(e) => Err(e.to_string()),
     }
 }
 
-pub async fn adb_pull(settings: &AdbStudioSettings, serial: &str, from: &str, to: &str) -> Result<String, String> {
-    let output = get_adb_command(settings)
-        .arg("-s")
-        .arg(serial)
-        .arg("pull")
-        .arg(from)
-        .arg(to)
-        .output();
+pub async fn adb_getprop(settings: &AdbStudioSettings, serial: &str) -> Result<String, AdbError> {
+    adb_shell(settings, serial, "getprop").await
+}
 
-    match output {
-        Ok(output) => {
-            if output.status.success() {
-                Ok(String::from_utf8_lossy(&output.stdout).to_string())
-            } else {
-                Err(String::from_utf8_lossy(&output.stderr).to_string())
+const PROGRESS_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// `adb push`/`adb pull` only print progress on a tty, so the executable backend estimates it by
+/// polling the size of the destination while the command runs.
+async fn run_adb_with_progress<F, Fut>(
+    settings: &AdbStudioSettings,
+    args: &[&str],
+    progress: Option<&ProgressSender>,
+    total: u64,
+    file: &str,
+    measure: F,
+) -> Result<String, AdbError>
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = Option<u64>>,
+{
+    if progress.is_none() {
+        return run_adb(settings, args).await;
+    }
+    let mut reporter = ProgressReporter::new(progress, total);
+    reporter.start_file(file);
+    let command = run_adb(settings, args);
+    tokio::pin!(command);
+    let mut done = 0;
+    loop {
+        tokio::select! {
+            result = &mut command => {
+                if result.is_ok() {
+                    reporter.advance(total.saturating_sub(done));
+                    reporter.finish();
+                }
+                return result;
+            }
+            _ = tokio::time::sleep(PROGRESS_POLL_INTERVAL) => {
+                if let Some(size) = measure().await.map(|s| s.min(total)) {
+                    if size > done {
+                        reporter.advance(size - done);
+                        done = size;
+                    }
+                }
             }
         }
-        Err(e) => Err(e.to_string()),
     }
 }
 
-pub async fn adb_install(settings: &AdbStudioSettings, serial: &str, apk_path: &str) -> Result<String, String> {
-    let output = get_adb_command(settings)
-        .arg("-s")
-        .arg(serial)
-        .arg("install")
-        .arg(apk_path)
-        .output();
-
-    match output {
-        Ok(output) => {
-            if output.status.success() {
-                Ok(String::from_utf8_lossy(&output.stdout).to_string())
-            } else {
-                Err(String::from_utf8_lossy(&output.stderr).to_string())
+async fn local_size(path: &Path) -> u64 {
+    let mut total = 0;
+    let mut pending = vec![path.to_path_buf()];
+    while let Some(path) = pending.pop() {
+        let Ok(metadata) = fs::metadata(&path).await else { continue };
+        if !metadata.is_dir() {
+            total += metadata.len();
+        } else if let Ok(mut dir) = fs::read_dir(&path).await {
+            while let Ok(Some(entry)) = dir.next_entry().await {
+                pending.push(entry.path());
             }
         }
-        Err(e) => Err(e.to_string()),
     }
+    total
 }
 
-pub async fn adb_uninstall(settings: &AdbStudioSettings, serial: &str, package_name: &str) -> Result<String, String> {
-    let output = get_adb_command(settings)
-        .arg("-s")
-        .arg(serial)
-        .arg("uninstall")
-        .arg(package_name)
-        .output();
+// `du -k` counts allocated blocks, which is close enough for a progress estimate.
+async fn remote_size(settings: &AdbStudioSettings, serial: &str, path: &str) -> Option<u64> {
+    let output = adb_shell(settings, serial, &format!("du -sk {}", shell_quote(path))).await.ok()?;
+    output.split_whitespace().next()?.parse::<u64>().ok().map(|kb| kb * 1024)
+}
 
-    match output {
-        Ok(output) => {
-            if output.status.success() {
-                Ok(String::from_utf8_lossy(&output.stdout).to_string())
-            } else {
-                Err(String::from_utf8_lossy(&output.stderr).to_string())
-            }
-        }
-        Err(e) => Err(e.to_string()),
+/// Where `adb push` puts `from`: inside `to` if that is an existing directory or ends with a
+/// slash, otherwise at `to` itself. The server backend resolves this the same way over sync.
+async fn push_target(settings: &AdbStudioSettings, serial: &str, from: &str, to: &str) -> String {
+    let Some(name) = Path::new(from).file_name() else {
+        return to.to_string();
+    };
+    if to.ends_with('/') || adb_shell(settings, serial, &format!("test -d {}", shell_quote(to))).await.is_ok() {
+        remote_join(to, &name.to_string_lossy())
+    } else {
+        to.to_string()
     }
 }
 
-pub async fn adb_list_packages(settings: &AdbStudioSettings, serial: &str) -> Result<String, String> {
-    let output = get_adb_command(settings)
-        .arg("-s")
-        .arg(serial)
-        .arg("shell")
-        .arg("pm list packages")
-        .output();
+pub async fn adb_push(
+    settings: &AdbStudioSettings,
+    serial: &str,
+    from: &str,
+    to: &str,
+    progress: Option<&ProgressSender>,
+) -> Result<String, AdbError> {
+    match settings.backend {
+        AdbBackend::Server => server_client(settings)
+            .push(serial, Path::new(from), to, progress)
+            .await
+            .map(|summary| summary.describe("pushed")),
+        AdbBackend::Executable => {
+            let target = if progress.is_some() { push_target(settings, serial, from, to).await } else { to.to_string() };
+            let total = local_size(Path::new(from)).await;
+            let args = ["-s", serial, "push", from, to];
+            run_adb_with_progress(settings, &args, progress, total, &target, || remote_size(settings, serial, &target)).await
+        }
+    }
+}
 
-    match output {
-        Ok(output) => {
-            if output.status.success() {
-                Ok(String::from_utf8_lossy(&output.stdout).to_string())
-            } else {
-                Err(String::from_utf8_lossy(&output.stderr).to_string())
+pub async fn adb_pull(
+    settings: &AdbStudioSettings,
+    serial: &str,
+    from: &str,
+    to: &str,
+    progress: Option<&ProgressSender>,
+) -> Result<String, AdbError> {
+    match settings.backend {
+        AdbBackend::Server => server_client(settings)
+            .pull(serial, from, Path::new(to), progress)
+            .await
+            .map(|summary| summary.describe("pulled")),
+        AdbBackend::Executable => {
+            let target = if Path::new(to).is_dir() { Path::new(to).join(remote_basename(from)) } else { PathBuf::from(to) };
+            // Only clean up on cancellation what this pull created, never a file it overwrote.
+            let partial = (!target.exists()).then(|| PartialFile::new(&target));
+            let total = if progress.is_some() { remote_size(settings, serial, from).await.unwrap_or(0) } else { 0 };
+            let args = ["-s", serial, "pull", from, to];
+            let result =
+                run_adb_with_progress(settings, &args, progress, total, from, || async { Some(local_size(&target).await) }).await;
+            if let (Ok(_), Some(partial)) = (&result, partial) {
+                partial.complete();
             }
+            result
         }
-        Err(e) => Err(e.to_string()),
     }
 }
 
-pub async fn adb_screenshot(settings: &AdbStudioSettings, serial: &str, path: &str) -> Result<String, String> {
-    let output = get_adb_command(settings)
-        .arg("-s")
-        .arg(serial)
-        .arg("exec-out")
-        .arg("screencap -p")
-        .output();
-
-    match output {
-        Ok(output) => {
-            if output.status.success() {
-                let mut file = File::create(path).map_err(|e| e.to_string())?;
-                file.write_all(&output.stdout).map_err(|e| e.to_string())?;
-                Ok(format!("Screenshot saved to {}", path))
-            } else {
-                Err(String::from_utf8_lossy(&output.stderr).to_string())
-            }
+/// Installs APKs already pushed to the device. Several APKs are written into one install
+/// session and committed together, which is what `adb install-multiple` does for split APKs.
+pub async fn adb_install(settings: &AdbStudioSettings, serial: &str, apks: &[String], flags: &[String]) -> Result<String, AdbError> {
+    let flags = flags.join(" ");
+    if let [apk] = apks {
+        return pm_result(adb_shell(settings, serial, &format!("pm install {} {}", flags, shell_quote(apk))).await);
+    }
+    // Success: created install session [1234]
+    let created = pm_result(adb_shell(settings, serial, &format!("pm install-create {}", flags)).await)?;
+    let session = created
+        .split_once('[')
+        .and_then(|(_, rest)| rest.split(']').next())
+        .ok_or_else(|| AdbError::Protocol(format!("unexpected install-create output: {}", created.trim())))?
+        .to_string();
+    for (i, apk) in apks.iter().enumerate() {
+        let write = format!("pm install-write {} {}.apk {}", session, i, shell_quote(apk));
+        if let Err(e) = pm_result(adb_shell(settings, serial, &write).await) {
+            let _ = adb_shell(settings, serial, &format!("pm install-abandon {}", session)).await;
+            return Err(e);
         }
-        Err(e) => Err(e.to_string()),
     }
+    pm_result(adb_shell(settings, serial, &format!("pm install-commit {}", session)).await)
 }
 
-pub async fn adb_start_screenrecord(settings: &AdbStudioSettings, serial: &str, path: &str) -> Result<String, String> {
-    let output = get_adb_command(settings)
-        .arg("-s")
-        .arg(serial)
-        .arg("shell")
-        .arg(format!("screenrecord {}", path))
-        .spawn(); // Use spawn to run in background
+// Older releases print `Failure [...]` but still exit 0.
+fn pm_result(result: Result<String, AdbError>) -> Result<String, AdbError> {
+    match result {
+        Ok(output) if output.contains("Failure [") || output.trim_start().starts_with("Error") => Err(AdbError::classify(None, &output)),
+        other => other,
+    }
+}
 
-    match output {
-        Ok(_) => Ok("Screen recording started.".to_string()),
-        Err(e) => Err(e.to_string()),
+pub async fn adb_uninstall(settings: &AdbStudioSettings, serial: &str, package_name: &str) -> Result<String, AdbError> {
+    match settings.backend {
+        AdbBackend::Server => shell_result(
+            server_client(settings)
+                .shell(serial, &format!("pm uninstall {}", shell_quote(package_name)))
+                .await?,
+        ),
+        AdbBackend::Executable => run_adb(settings, &["-s", serial, "uninstall", package_name]).await,
     }
 }
 
-pub async fn adb_stop_screenrecord(settings: &AdbStudioSettings, serial: &str) -> Result<String, String> {
-    let output = get_adb_command(settings)
-        .arg("-s")
-        .arg(serial)
-        .arg("shell")
-        .arg("pkill -INT screenrecord")
-        .output();
+pub async fn adb_list_packages(settings: &AdbStudioSettings, serial: &str) -> Result<String, AdbError> {
+    match settings.backend {
+        AdbBackend::Server => shell_result(server_client(settings).shell(serial, "pm list packages").await?),
+        AdbBackend::Executable => run_adb(settings, &["-s", serial, "shell", "pm list packages"]).await,
+    }
+}
 
-    match output {
-        Ok(output) => {
-            if output.status.success() {
-                Ok(String::from_utf8_lossy(&output.stdout).to_string())
-            } else {
-                Err(String::from_utf8_lossy(&output.stderr).to_string())
+pub async fn adb_dumpsys_package(settings: &AdbStudioSettings, serial: &str, package: &str) -> Result<String, AdbError> {
+    adb_shell(settings, serial, &format!("dumpsys package {}", shell_quote(package))).await
+}
+
+/// Lists the APK files a package is installed from, base first, one `package:<path>` per line.
+pub async fn adb_pm_path(settings: &AdbStudioSettings, serial: &str, package: &str) -> Result<String, AdbError> {
+    adb_shell(settings, serial, &format!("pm path {}", shell_quote(package))).await
+}
+
+/// Captures the screen as PNG bytes.
+pub async fn adb_screenshot(settings: &AdbStudioSettings, serial: &str) -> Result<Vec<u8>, AdbError> {
+    let png = match settings.backend {
+        AdbBackend::Server => server_client(settings).exec(serial, "screencap -p").await?,
+        AdbBackend::Executable => {
+            let output = get_adb_command(settings)
+                .arg("-s")
+                .arg(serial)
+                .arg("exec-out")
+                .arg("screencap -p")
+                .output()
+                .await
+                .map_err(|e| AdbError::from_spawn(settings.adb_program(), e))?;
+            if !output.status.success() {
+                return Err(failure(output.status.code(), &output.stdout, &output.stderr));
             }
+            output.stdout
         }
-        Err(e) => Err(e.to_string()),
+    };
+
+    if png.is_empty() {
+        return Err(AdbError::CommandFailed { code: None, message: "screencap returned no data".to_string() });
     }
+    Ok(png)
 }
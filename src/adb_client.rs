@@ -0,0 +1,993 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::fs::{self, File};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+use tokio::process::Command;
+use tokio::sync::mpsc::UnboundedSender;
+use crate::error::AdbError;
+
+pub const DEFAULT_SERVER_PORT: u16 = 5037;
+
+pub const SHELL_STDIN: u8 = 0;
+pub const SHELL_STDOUT: u8 = 1;
+pub const SHELL_STDERR: u8 = 2;
+pub const SHELL_EXIT: u8 = 3;
+pub const SHELL_WINDOW_SIZE: u8 = 5;
+
+const SYNC_DATA_MAX: usize = 64 * 1024;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+const S_IFMT: u32 = 0o170000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFREG: u32 = 0o100000;
+
+/// Client for the host side of the ADB server protocol (the daemon `adb start-server` runs on
+/// port 5037). Every request opens a fresh TCP connection, which is what the `adb` binary does too.
+#[derive(Clone, Debug)]
+pub struct AdbServerClient {
+    host: String,
+    port: u16,
+    launcher: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct ShellOutput {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    pub exit_code: i32,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RemoteStat {
+    pub mode: u32,
+    pub size: u32,
+    pub mtime: u32,
+}
+
+impl RemoteStat {
+    pub fn exists(&self) -> bool {
+        self.mode != 0
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.mode & S_IFMT == S_IFDIR
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.mode & S_IFMT == S_IFREG
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RemoteDirEntry {
+    pub name: String,
+    pub stat: RemoteStat,
+}
+
+#[derive(Debug, Default)]
+pub struct TransferSummary {
+    pub files: usize,
+    pub bytes: u64,
+    pub elapsed: Duration,
+}
+
+impl TransferSummary {
+    pub fn describe(&self, verb: &str) -> String {
+        let secs = self.elapsed.as_secs_f64();
+        let rate = if secs > 0.0 { self.bytes as f64 / secs / (1024.0 * 1024.0) } else { 0.0 };
+        format!(
+            "{} file{} {}. {:.1} MB/s ({} bytes in {:.3}s)",
+            self.files,
+            if self.files == 1 { "" } else { "s" },
+            verb,
+            rate,
+            self.bytes,
+            secs
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TransferProgress {
+    pub bytes_done: u64,
+    pub total_bytes: u64,
+    pub current_file: String,
+}
+
+pub type ProgressSender = UnboundedSender<TransferProgress>;
+
+/// Tracks bytes moved by a transfer and forwards them to an optional listener, at most once per
+/// `PROGRESS_INTERVAL` so a fast transfer doesn't flood the UI.
+pub struct ProgressReporter<'a> {
+    sender: Option<&'a ProgressSender>,
+    progress: TransferProgress,
+    last_sent: Option<Instant>,
+}
+
+impl<'a> ProgressReporter<'a> {
+    pub fn new(sender: Option<&'a ProgressSender>, total_bytes: u64) -> Self {
+        Self {
+            sender,
+            progress: TransferProgress { total_bytes, ..Default::default() },
+            last_sent: None,
+        }
+    }
+
+    pub fn start_file(&mut self, name: &str) {
+        self.progress.current_file = name.to_string();
+        self.send(true);
+    }
+
+    pub fn advance(&mut self, bytes: u64) {
+        self.progress.bytes_done += bytes;
+        self.send(false);
+    }
+
+    pub fn finish(&mut self) {
+        self.progress.total_bytes = self.progress.total_bytes.max(self.progress.bytes_done);
+        self.send(true);
+    }
+
+    fn send(&mut self, force: bool) {
+        let Some(sender) = self.sender else { return };
+        if force || !matches!(self.last_sent, Some(t) if t.elapsed() < PROGRESS_INTERVAL) {
+            let _ = sender.send(self.progress.clone());
+            self.last_sent = Some(Instant::now());
+        }
+    }
+}
+
+impl AdbServerClient {
+    pub fn new(host: impl Into<String>, port: u16) -> Self {
+        Self { host: host.into(), port, launcher: None }
+    }
+
+    /// Honours `ANDROID_ADB_SERVER_PORT` the same way the `adb` binary does.
+    pub fn from_env() -> Self {
+        let port = std::env::var("ANDROID_ADB_SERVER_PORT")
+            .ok()
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(DEFAULT_SERVER_PORT);
+        Self::new("127.0.0.1", port)
+    }
+
+    /// Binary used to run `adb start-server` when nothing is listening on the server port.
+    pub fn with_launcher(mut self, adb_program: impl Into<String>) -> Self {
+        self.launcher = Some(adb_program.into());
+        self
+    }
+
+    pub async fn connect(&self) -> Result<AdbConnection, AdbError> {
+        match TcpStream::connect((self.host.as_str(), self.port)).await {
+            Ok(stream) => Ok(AdbConnection { stream }),
+            Err(e) if e.kind() == io::ErrorKind::ConnectionRefused && self.launcher.is_some() => {
+                self.start_server().await?;
+                TcpStream::connect((self.host.as_str(), self.port))
+                    .await
+                    .map(|stream| AdbConnection { stream })
+                    .map_err(|e| self.unavailable(e))
+            }
+            Err(e) => Err(self.unavailable(e)),
+        }
+    }
+
+    fn unavailable(&self, error: io::Error) -> AdbError {
+        AdbError::ServerUnavailable(format!("{}:{}: {}", self.host, self.port, error))
+    }
+
+    async fn start_server(&self) -> Result<(), AdbError> {
+        let program = self.launcher.as_deref().unwrap_or("adb");
+        let output = Command::new(program)
+            .arg("start-server")
+            .output()
+            .await
+            .map_err(|e| AdbError::from_spawn(program, e))?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(AdbError::ServerUnavailable(String::from_utf8_lossy(&output.stderr).to_string()))
+        }
+    }
+
+    /// Runs a `host:` request that answers with a single length-prefixed payload.
+    pub async fn host_query(&self, request: &str) -> Result<String, AdbError> {
+        let mut conn = self.connect().await?;
+        conn.send_request(request).await?;
+        conn.read_length_prefixed().await
+    }
+
+    pub async fn version(&self) -> Result<u32, AdbError> {
+        let version = self.host_query("host:version").await?;
+        u32::from_str_radix(version.trim(), 16).map_err(|e| AdbError::Protocol(e.to_string()))
+    }
+
+    /// Opens `service` on the device identified by `serial`.
+    pub async fn open(&self, serial: &str, service: &str) -> Result<AdbConnection, AdbError> {
+        let mut conn = self.connect().await?;
+        conn.send_request(&format!("host:transport:{}", serial)).await?;
+        conn.send_request(service).await?;
+        Ok(conn)
+    }
+
+    /// Runs `command` through the shell v2 protocol so stderr and the exit status are kept,
+    /// falling back to the legacy `shell:` service on devices that don't support it.
+    pub async fn shell(&self, serial: &str, command: &str) -> Result<ShellOutput, AdbError> {
+        match self.open(serial, &format!("shell,v2,raw:{}", command)).await {
+            Ok(conn) => conn.read_shell_v2().await,
+            Err(AdbError::CommandFailed { .. }) => {
+                let conn = self.open(serial, &format!("shell:{}", command)).await?;
+                Ok(ShellOutput { stdout: conn.read_to_end().await?, ..Default::default() })
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Opens an interactive shell backed by a pseudo-terminal on the device. Returns the
+    /// connection and whether it speaks shell v2 (and therefore supports window resizes).
+    pub async fn open_pty_shell(&self, serial: &str, term: &str) -> Result<(AdbConnection, bool), AdbError> {
+        match self.open(serial, &format!("shell,v2,TERM={},pty:", term)).await {
+            Ok(conn) => Ok((conn, true)),
+            Err(AdbError::CommandFailed { .. }) => Ok((self.open(serial, "shell:").await?, false)),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Binary-safe equivalent of `adb exec-out`.
+    pub async fn exec(&self, serial: &str, command: &str) -> Result<Vec<u8>, AdbError> {
+        self.open(serial, &format!("exec:{}", command)).await?.read_to_end().await
+    }
+
+    pub async fn sync(&self, serial: &str) -> Result<SyncSession, AdbError> {
+        let conn = self.open(serial, "sync:").await?;
+        Ok(SyncSession { stream: conn.stream })
+    }
+
+    /// Same semantics as `adb push`: directories are copied recursively and a remote directory
+    /// target receives the local file name.
+    pub async fn push(
+        &self,
+        serial: &str,
+        local: &Path,
+        remote: &str,
+        progress: Option<&ProgressSender>,
+    ) -> Result<TransferSummary, AdbError> {
+        let started = Instant::now();
+        let mut sync = self.sync(serial).await?;
+        let local_name = local
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .ok_or_else(|| AdbError::NoSuchFile(format!("invalid local path: {}", local.display())))?;
+        let target = if remote.ends_with('/') || sync.stat(remote).await?.is_dir() {
+            remote_join(remote, &local_name)
+        } else {
+            remote.to_string()
+        };
+
+        let mut files = Vec::new();
+        let mut pending = vec![(local.to_path_buf(), target)];
+        while let Some((path, remote_path)) = pending.pop() {
+            let metadata = fs::metadata(&path).await?;
+            if metadata.is_dir() {
+                let mut dir = fs::read_dir(&path).await?;
+                while let Some(entry) = dir.next_entry().await? {
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    pending.push((entry.path(), remote_join(&remote_path, &name)));
+                }
+            } else {
+                files.push((path, remote_path, local_mode(&metadata), metadata.len()));
+            }
+        }
+
+        let mut reporter = ProgressReporter::new(progress, files.iter().map(|f| f.3).sum());
+        let mut summary = TransferSummary::default();
+        for (path, remote_path, mode, _) in files {
+            reporter.start_file(&remote_path);
+            summary.bytes += sync.send_file(&path, &remote_path, mode, &mut reporter).await?;
+            summary.files += 1;
+        }
+        reporter.finish();
+        sync.quit().await;
+        summary.elapsed = started.elapsed();
+        Ok(summary)
+    }
+
+    /// Same semantics as `adb pull`: remote directories are copied recursively and an existing
+    /// local directory target receives the remote file name.
+    pub async fn pull(
+        &self,
+        serial: &str,
+        remote: &str,
+        local: &Path,
+        progress: Option<&ProgressSender>,
+    ) -> Result<TransferSummary, AdbError> {
+        let started = Instant::now();
+        let mut sync = self.sync(serial).await?;
+        let stat = sync.stat(remote).await?;
+        if !stat.exists() {
+            return Err(AdbError::NoSuchFile(format!("remote object '{}' does not exist", remote)));
+        }
+        let target = if fs::metadata(local).await.map(|m| m.is_dir()).unwrap_or(false) {
+            local.join(remote_basename(remote))
+        } else {
+            local.to_path_buf()
+        };
+
+        // Walk the tree first so the total size is known before any data moves.
+        let mut files = Vec::new();
+        let mut pending = vec![(remote.trim_end_matches('/').to_string(), target, stat)];
+        while let Some((remote_path, local_path, stat)) = pending.pop() {
+            if stat.is_dir() {
+                fs::create_dir_all(&local_path).await?;
+                for entry in sync.list(&remote_path).await? {
+                    if entry.name == "." || entry.name == ".." {
+                        continue;
+                    }
+                    pending.push((remote_join(&remote_path, &entry.name), local_path.join(&entry.name), entry.stat));
+                }
+            } else if stat.is_file() {
+                files.push((remote_path, local_path, stat.size as u64));
+            }
+        }
+
+        let mut reporter = ProgressReporter::new(progress, files.iter().map(|f| f.2).sum());
+        let mut summary = TransferSummary::default();
+        for (remote_path, local_path, _) in files {
+            reporter.start_file(&remote_path);
+            summary.bytes += sync.recv_file(&remote_path, &local_path, &mut reporter).await?;
+            summary.files += 1;
+        }
+        reporter.finish();
+        sync.quit().await;
+        summary.elapsed = started.elapsed();
+        Ok(summary)
+    }
+}
+
+/// Deletes a local file or directory being written unless it is marked complete, so a failed or
+/// cancelled pull doesn't leave a truncated copy behind. Cancelling drops the transfer's future,
+/// so this has to happen on drop.
+pub struct PartialFile {
+    path: PathBuf,
+    complete: bool,
+}
+
+impl PartialFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), complete: false }
+    }
+
+    pub fn complete(mut self) {
+        self.complete = true;
+    }
+}
+
+impl Drop for PartialFile {
+    fn drop(&mut self) {
+        if self.complete {
+            return;
+        }
+        let _ = match std::fs::symlink_metadata(&self.path) {
+            Ok(metadata) if metadata.is_dir() => std::fs::remove_dir_all(&self.path),
+            Ok(_) => std::fs::remove_file(&self.path),
+            Err(_) => Ok(()),
+        };
+    }
+}
+
+pub struct AdbConnection {
+    stream: TcpStream,
+}
+
+impl AdbConnection {
+    pub async fn send_request(&mut self, request: &str) -> Result<(), AdbError> {
+        let message = format!("{:04x}{}", request.len(), request);
+        self.stream.write_all(message.as_bytes()).await?;
+        self.read_status().await
+    }
+
+    async fn read_status(&mut self) -> Result<(), AdbError> {
+        let mut status = [0u8; 4];
+        self.stream.read_exact(&mut status).await?;
+        match &status {
+            b"OKAY" => Ok(()),
+            b"FAIL" => Err(AdbError::classify(None, &self.read_length_prefixed().await?)),
+            other => Err(unexpected_response(other)),
+        }
+    }
+
+    pub async fn read_length_prefixed(&mut self) -> Result<String, AdbError> {
+        read_length_prefixed(&mut self.stream).await
+    }
+
+    pub async fn read_to_end(mut self) -> Result<Vec<u8>, AdbError> {
+        let mut buf = Vec::new();
+        self.stream.read_to_end(&mut buf).await?;
+        Ok(buf)
+    }
+
+    async fn read_shell_v2(mut self) -> Result<ShellOutput, AdbError> {
+        let mut output = ShellOutput::default();
+        while let Some((id, data)) = read_shell_packet(&mut self.stream).await? {
+            match id {
+                SHELL_STDOUT => output.stdout.extend_from_slice(&data),
+                SHELL_STDERR => output.stderr.extend_from_slice(&data),
+                SHELL_EXIT => {
+                    output.exit_code = data.first().copied().unwrap_or(0) as i32;
+                    break;
+                }
+                _ => {}
+            }
+        }
+        Ok(output)
+    }
+
+    pub fn into_split(self) -> (OwnedReadHalf, OwnedWriteHalf) {
+        self.stream.into_split()
+    }
+}
+
+/// A `sync:` service session, used for file transfer and remote stat/listing.
+pub struct SyncSession {
+    stream: TcpStream,
+}
+
+impl SyncSession {
+    async fn send_request(&mut self, id: &[u8; 4], payload: &[u8]) -> Result<(), AdbError> {
+        let mut message = Vec::with_capacity(8 + payload.len());
+        message.extend_from_slice(id);
+        message.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        message.extend_from_slice(payload);
+        Ok(self.stream.write_all(&message).await?)
+    }
+
+    async fn read_u32(&mut self) -> Result<u32, AdbError> {
+        let mut buf = [0u8; 4];
+        self.stream.read_exact(&mut buf).await?;
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    async fn read_id(&mut self) -> Result<[u8; 4], AdbError> {
+        let mut id = [0u8; 4];
+        self.stream.read_exact(&mut id).await?;
+        Ok(id)
+    }
+
+    async fn read_failure(&mut self) -> AdbError {
+        let Ok(len) = self.read_u32().await else {
+            return AdbError::Protocol("adb sync failure".to_string());
+        };
+        let mut message = vec![0u8; len as usize];
+        match self.stream.read_exact(&mut message).await {
+            Ok(_) => AdbError::classify(None, &String::from_utf8_lossy(&message)),
+            Err(e) => e.into(),
+        }
+    }
+
+    pub async fn stat(&mut self, path: &str) -> Result<RemoteStat, AdbError> {
+        self.send_request(b"STAT", path.as_bytes()).await?;
+        let id = self.read_id().await?;
+        if &id != b"STAT" {
+            return Err(unexpected_response(&id));
+        }
+        Ok(RemoteStat {
+            mode: self.read_u32().await?,
+            size: self.read_u32().await?,
+            mtime: self.read_u32().await?,
+        })
+    }
+
+    pub async fn list(&mut self, path: &str) -> Result<Vec<RemoteDirEntry>, AdbError> {
+        self.send_request(b"LIST", path.as_bytes()).await?;
+        let mut entries = Vec::new();
+        loop {
+            let id = self.read_id().await?;
+            match &id {
+                b"DENT" | b"DONE" => {
+                    let mode = self.read_u32().await?;
+                    let size = self.read_u32().await?;
+                    let mtime = self.read_u32().await?;
+                    let name_len = self.read_u32().await?;
+                    let mut name = vec![0u8; name_len as usize];
+                    self.stream.read_exact(&mut name).await?;
+                    if &id == b"DONE" {
+                        break;
+                    }
+                    entries.push(RemoteDirEntry {
+                        name: String::from_utf8_lossy(&name).to_string(),
+                        stat: RemoteStat { mode, size, mtime },
+                    });
+                }
+                b"FAIL" => return Err(self.read_failure().await),
+                other => return Err(unexpected_response(other)),
+            }
+        }
+        Ok(entries)
+    }
+
+    pub async fn send_file(
+        &mut self,
+        local: &Path,
+        remote: &str,
+        mode: u32,
+        progress: &mut ProgressReporter<'_>,
+    ) -> Result<u64, AdbError> {
+        let mut file = File::open(local).await?;
+        self.send_request(b"SEND", format!("{},{}", remote, mode).as_bytes()).await?;
+
+        let mut buf = vec![0u8; SYNC_DATA_MAX];
+        let mut sent = 0u64;
+        loop {
+            let n = file.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            self.send_request(b"DATA", &buf[..n]).await?;
+            sent += n as u64;
+            progress.advance(n as u64);
+        }
+
+        let mtime = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as u32).unwrap_or(0);
+        let mut done = Vec::with_capacity(8);
+        done.extend_from_slice(b"DONE");
+        done.extend_from_slice(&mtime.to_le_bytes());
+        self.stream.write_all(&done).await?;
+
+        let id = self.read_id().await?;
+        match &id {
+            b"OKAY" => {
+                self.read_u32().await?;
+                Ok(sent)
+            }
+            b"FAIL" => Err(self.read_failure().await),
+            other => Err(unexpected_response(other)),
+        }
+    }
+
+    pub async fn recv_file(
+        &mut self,
+        remote: &str,
+        local: &Path,
+        progress: &mut ProgressReporter<'_>,
+    ) -> Result<u64, AdbError> {
+        if let Some(parent) = local.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        self.send_request(b"RECV", remote.as_bytes()).await?;
+        // Declared before the file so the file is closed by the time it is removed.
+        let partial = PartialFile::new(local);
+        let mut file = File::create(local).await?;
+        let mut received = 0u64;
+        let mut buf = vec![0u8; SYNC_DATA_MAX];
+        loop {
+            let id = self.read_id().await?;
+            match &id {
+                b"DATA" => {
+                    let len = self.read_u32().await? as usize;
+                    if len > buf.len() {
+                        buf.resize(len, 0);
+                    }
+                    self.stream.read_exact(&mut buf[..len]).await?;
+                    file.write_all(&buf[..len]).await?;
+                    received += len as u64;
+                    progress.advance(len as u64);
+                }
+                b"DONE" => {
+                    self.read_u32().await?;
+                    break;
+                }
+                b"FAIL" => return Err(self.read_failure().await),
+                other => return Err(unexpected_response(other)),
+            }
+        }
+        file.flush().await?;
+        partial.complete();
+        Ok(received)
+    }
+
+    pub async fn quit(mut self) {
+        let _ = self.send_request(b"QUIT", &[]).await;
+    }
+}
+
+/// Reads a `<4 hex digits><payload>` message, the framing used by host services and by
+/// `adb track-devices` on stdout.
+pub async fn read_length_prefixed<R: AsyncRead + Unpin>(reader: &mut R) -> Result<String, AdbError> {
+    let mut len = [0u8; 4];
+    reader.read_exact(&mut len).await?;
+    let len = std::str::from_utf8(&len)
+        .ok()
+        .and_then(|l| usize::from_str_radix(l, 16).ok())
+        .ok_or_else(|| AdbError::Protocol("malformed length prefix from adb server".to_string()))?;
+    let mut payload = vec![0u8; len];
+    reader.read_exact(&mut payload).await?;
+    Ok(String::from_utf8_lossy(&payload).to_string())
+}
+
+/// Reads one shell v2 packet (`<id:u8><len:u32 le><data>`); `None` once the stream is closed.
+pub async fn read_shell_packet<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Option<(u8, Vec<u8>)>, AdbError> {
+    let mut header = [0u8; 5];
+    match reader.read_exact(&mut header).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let len = u32::from_le_bytes([header[1], header[2], header[3], header[4]]) as usize;
+    let mut data = vec![0u8; len];
+    reader.read_exact(&mut data).await?;
+    Ok(Some((header[0], data)))
+}
+
+pub async fn write_shell_packet<W: AsyncWrite + Unpin>(writer: &mut W, id: u8, data: &[u8]) -> Result<(), AdbError> {
+    let mut packet = Vec::with_capacity(5 + data.len());
+    packet.push(id);
+    packet.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    packet.extend_from_slice(data);
+    Ok(writer.write_all(&packet).await?)
+}
+
+fn unexpected_response(id: &[u8]) -> AdbError {
+    AdbError::Protocol(format!("unexpected response: {:?}", String::from_utf8_lossy(id)))
+}
+
+pub fn remote_join(dir: &str, name: &str) -> String {
+    format!("{}/{}", dir.trim_end_matches('/'), name)
+}
+
+pub fn remote_basename(path: &str) -> PathBuf {
+    PathBuf::from(path.trim_end_matches('/').rsplit('/').next().unwrap_or(path))
+}
+
+#[cfg(unix)]
+fn local_mode(metadata: &std::fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    S_IFREG | (metadata.permissions().mode() & 0o777)
+}
+
+#[cfg(not(unix))]
+fn local_mode(_metadata: &std::fs::Metadata) -> u32 {
+    S_IFREG | 0o644
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::Future;
+    use tokio::net::TcpListener;
+    use tokio::task::JoinHandle;
+
+    /// Starts a stand-in adb server on a free local port and runs `handler` against it.
+    async fn fake_server<F, Fut, T>(handler: F) -> (AdbServerClient, JoinHandle<T>)
+    where
+        F: FnOnce(TcpListener) -> Fut,
+        Fut: Future<Output = T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        (AdbServerClient::new("127.0.0.1", port), tokio::spawn(handler(listener)))
+    }
+
+    async fn read_request(stream: &mut TcpStream) -> String {
+        read_length_prefixed(stream).await.unwrap()
+    }
+
+    async fn reply_fail(stream: &mut TcpStream, message: &str) {
+        stream.write_all(format!("FAIL{:04x}{}", message.len(), message).as_bytes()).await.unwrap();
+    }
+
+    /// Accepts a connection and answers OKAY to its transport and service requests, returning
+    /// the service that was asked for.
+    async fn accept_service(listener: &TcpListener) -> (TcpStream, String) {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        assert_eq!(read_request(&mut stream).await, "host:transport:emulator-5554");
+        stream.write_all(b"OKAY").await.unwrap();
+        let service = read_request(&mut stream).await;
+        stream.write_all(b"OKAY").await.unwrap();
+        (stream, service)
+    }
+
+    /// Reads one sync request or `DONE` marker: a 4-byte id and a little-endian length, followed
+    /// by that many bytes of payload except for `DONE`, whose length is the file's mtime.
+    async fn read_sync(stream: &mut TcpStream) -> ([u8; 4], Vec<u8>) {
+        let mut header = [0u8; 8];
+        stream.read_exact(&mut header).await.unwrap();
+        let id = [header[0], header[1], header[2], header[3]];
+        let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        if &id == b"DONE" {
+            return (id, Vec::new());
+        }
+        let mut payload = vec![0u8; len as usize];
+        stream.read_exact(&mut payload).await.unwrap();
+        (id, payload)
+    }
+
+    fn sync_stat(id: &[u8; 4], mode: u32, size: u32, mtime: u32) -> Vec<u8> {
+        let mut message = id.to_vec();
+        for value in [mode, size, mtime] {
+            message.extend_from_slice(&value.to_le_bytes());
+        }
+        message
+    }
+
+    fn dent(name: &str, mode: u32, size: u32) -> Vec<u8> {
+        let mut message = sync_stat(b"DENT", mode, size, 1_700_000_000);
+        message.extend_from_slice(&(name.len() as u32).to_le_bytes());
+        message.extend_from_slice(name.as_bytes());
+        message
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("adb-studio-client-test-{}-{}", std::process::id(), name))
+    }
+
+    #[tokio::test]
+    async fn length_prefixed_framing() {
+        let mut reader: &[u8] = b"000bcompressionNEXT";
+        assert_eq!(read_length_prefixed(&mut reader).await.unwrap(), "compression");
+        assert_eq!(reader, b"NEXT");
+
+        let mut reader: &[u8] = b"0000";
+        assert_eq!(read_length_prefixed(&mut reader).await.unwrap(), "");
+
+        let mut reader: &[u8] = b"zz10payload";
+        assert!(matches!(read_length_prefixed(&mut reader).await, Err(AdbError::Protocol(_))));
+
+        let mut reader: &[u8] = b"0010short";
+        assert!(matches!(read_length_prefixed(&mut reader).await, Err(AdbError::Io(_))));
+    }
+
+    #[tokio::test]
+    async fn host_query_okay() {
+        let (client, server) = fake_server(|listener| async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut raw = [0u8; 16];
+            stream.read_exact(&mut raw).await.unwrap();
+            stream.write_all(b"OKAY00040029").await.unwrap();
+            raw
+        })
+        .await;
+        assert_eq!(client.version().await.unwrap(), 0x29);
+        assert_eq!(&server.await.unwrap(), b"000chost:version");
+    }
+
+    #[tokio::test]
+    async fn host_query_fail_is_classified() {
+        let (client, _server) = fake_server(|listener| async move {
+            for message in ["device unauthorized.\nThis adb server's $ADB_VENDOR_KEYS is not set", "device 'abc' not found"] {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                read_request(&mut stream).await;
+                reply_fail(&mut stream, message).await;
+            }
+        })
+        .await;
+        assert_eq!(client.host_query("host:devices-l").await, Err(AdbError::Unauthorized));
+        assert_eq!(client.host_query("host-serial:abc:get-state").await, Err(AdbError::DeviceNotFound("abc".to_string())));
+    }
+
+    #[tokio::test]
+    async fn unexpected_status_is_a_protocol_error() {
+        let (client, _server) = fake_server(|listener| async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            read_request(&mut stream).await;
+            stream.write_all(b"WHAT").await.unwrap();
+        })
+        .await;
+        assert!(matches!(client.host_query("host:version").await, Err(AdbError::Protocol(_))));
+    }
+
+    #[tokio::test]
+    async fn shell_v2_demuxes_streams_and_exit_code() {
+        let (client, server) = fake_server(|listener| async move {
+            let (mut stream, service) = accept_service(&listener).await;
+            write_shell_packet(&mut stream, SHELL_STDOUT, b"hello ").await.unwrap();
+            write_shell_packet(&mut stream, SHELL_STDERR, b"warning\n").await.unwrap();
+            write_shell_packet(&mut stream, SHELL_WINDOW_SIZE, b"ignored").await.unwrap();
+            write_shell_packet(&mut stream, SHELL_STDOUT, b"world\n").await.unwrap();
+            write_shell_packet(&mut stream, SHELL_EXIT, &[3]).await.unwrap();
+            service
+        })
+        .await;
+        let output = client.shell("emulator-5554", "echo hello world").await.unwrap();
+        assert_eq!(server.await.unwrap(), "shell,v2,raw:echo hello world");
+        assert_eq!(output.stdout, b"hello world\n");
+        assert_eq!(output.stderr, b"warning\n");
+        assert_eq!(output.exit_code, 3);
+    }
+
+    #[tokio::test]
+    async fn shell_falls_back_to_legacy_service() {
+        let (client, server) = fake_server(|listener| async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            read_request(&mut stream).await;
+            stream.write_all(b"OKAY").await.unwrap();
+            let v2 = read_request(&mut stream).await;
+            reply_fail(&mut stream, "closed").await;
+            let (mut stream, legacy) = accept_service(&listener).await;
+            stream.write_all(b"legacy output\n").await.unwrap();
+            (v2, legacy)
+        })
+        .await;
+        let output = client.shell("emulator-5554", "ls").await.unwrap();
+        assert_eq!(server.await.unwrap(), ("shell,v2,raw:ls".to_string(), "shell:ls".to_string()));
+        assert_eq!(output.stdout, b"legacy output\n");
+        assert!(output.stderr.is_empty());
+        assert_eq!(output.exit_code, 0);
+    }
+
+    #[tokio::test]
+    async fn shell_does_not_fall_back_on_device_errors() {
+        let (client, _server) = fake_server(|listener| async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            read_request(&mut stream).await;
+            reply_fail(&mut stream, "device offline").await;
+        })
+        .await;
+        assert_eq!(client.shell("emulator-5554", "ls").await.unwrap_err(), AdbError::Offline);
+    }
+
+    #[tokio::test]
+    async fn sync_stat_and_list() {
+        let (client, server) = fake_server(|listener| async move {
+            let (mut stream, service) = accept_service(&listener).await;
+            let stat = read_sync(&mut stream).await;
+            stream.write_all(&sync_stat(b"STAT", S_IFDIR | 0o771, 4096, 1_700_000_000)).await.unwrap();
+            let list = read_sync(&mut stream).await;
+            let mut reply = dent(".", S_IFDIR | 0o771, 4096);
+            reply.extend(dent("notes.txt", S_IFREG | 0o660, 12));
+            reply.extend(dent("DCIM", S_IFDIR | 0o771, 4096));
+            reply.extend(sync_stat(b"DONE", 0, 0, 0));
+            reply.extend_from_slice(&0u32.to_le_bytes());
+            stream.write_all(&reply).await.unwrap();
+            let missing = read_sync(&mut stream).await;
+            stream.write_all(&sync_stat(b"STAT", 0, 0, 0)).await.unwrap();
+            (service, stat, list, missing)
+        })
+        .await;
+        let mut sync = client.sync("emulator-5554").await.unwrap();
+        let stat = sync.stat("/sdcard").await.unwrap();
+        assert!(stat.exists() && stat.is_dir() && !stat.is_file());
+        let entries = sync.list("/sdcard").await.unwrap();
+        assert!(!sync.stat("/sdcard/missing").await.unwrap().exists());
+
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec![".", "notes.txt", "DCIM"]);
+        assert!(entries[1].stat.is_file());
+        assert_eq!(entries[1].stat.size, 12);
+        assert!(entries[2].stat.is_dir());
+
+        let (service, stat, list, missing) = server.await.unwrap();
+        assert_eq!(service, "sync:");
+        assert_eq!(stat, (*b"STAT", b"/sdcard".to_vec()));
+        assert_eq!(list, (*b"LIST", b"/sdcard".to_vec()));
+        assert_eq!(missing.1, b"/sdcard/missing");
+    }
+
+    #[tokio::test]
+    async fn sync_list_failure() {
+        let (client, _server) = fake_server(|listener| async move {
+            let (mut stream, _) = accept_service(&listener).await;
+            read_sync(&mut stream).await;
+            let message = "opendir failed: Permission denied";
+            let mut reply = b"FAIL".to_vec();
+            reply.extend_from_slice(&(message.len() as u32).to_le_bytes());
+            reply.extend_from_slice(message.as_bytes());
+            stream.write_all(&reply).await.unwrap();
+        })
+        .await;
+        let mut sync = client.sync("emulator-5554").await.unwrap();
+        assert!(matches!(sync.list("/data").await, Err(AdbError::PermissionDenied(_))));
+    }
+
+    #[tokio::test]
+    async fn sync_send_file() {
+        let local = temp_path("send.bin");
+        let contents: Vec<u8> = (0..SYNC_DATA_MAX + 100).map(|i| (i % 251) as u8).collect();
+        std::fs::write(&local, &contents).unwrap();
+
+        let (client, server) = fake_server(|listener| async move {
+            let (mut stream, _) = accept_service(&listener).await;
+            let (id, header) = read_sync(&mut stream).await;
+            assert_eq!(&id, b"SEND");
+            let mut chunks = Vec::new();
+            loop {
+                let (id, data) = read_sync(&mut stream).await;
+                if &id == b"DONE" {
+                    break;
+                }
+                assert_eq!(&id, b"DATA");
+                chunks.push(data);
+            }
+            stream.write_all(b"OKAY\0\0\0\0").await.unwrap();
+            (String::from_utf8(header).unwrap(), chunks)
+        })
+        .await;
+        let mut sync = client.sync("emulator-5554").await.unwrap();
+        let mut progress = ProgressReporter::new(None, contents.len() as u64);
+        let sent = sync.send_file(&local, "/sdcard/send.bin", S_IFREG | 0o644, &mut progress).await.unwrap();
+        let _ = std::fs::remove_file(&local);
+
+        let (header, chunks) = server.await.unwrap();
+        assert_eq!(sent, contents.len() as u64);
+        assert_eq!(header, format!("/sdcard/send.bin,{}", S_IFREG | 0o644));
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks.iter().all(|c| c.len() <= SYNC_DATA_MAX));
+        assert_eq!(chunks.concat(), contents);
+    }
+
+    #[tokio::test]
+    async fn sync_recv_file() {
+        let local = temp_path("recv.txt");
+        let (client, server) = fake_server(|listener| async move {
+            let (mut stream, _) = accept_service(&listener).await;
+            let request = read_sync(&mut stream).await;
+            let mut reply = Vec::new();
+            for chunk in [&b"first "[..], b"second"] {
+                reply.extend_from_slice(b"DATA");
+                reply.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+                reply.extend_from_slice(chunk);
+            }
+            reply.extend_from_slice(b"DONE\0\0\0\0");
+            stream.write_all(&reply).await.unwrap();
+            request
+        })
+        .await;
+        let mut sync = client.sync("emulator-5554").await.unwrap();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut progress = ProgressReporter::new(Some(&tx), 12);
+        let received = sync.recv_file("/sdcard/recv.txt", &local, &mut progress).await.unwrap();
+        progress.finish();
+
+        assert_eq!(server.await.unwrap(), (*b"RECV", b"/sdcard/recv.txt".to_vec()));
+        assert_eq!(received, 12);
+        assert_eq!(std::fs::read_to_string(&local).unwrap(), "first second");
+        let _ = std::fs::remove_file(&local);
+        let mut last = None;
+        while let Ok(update) = rx.try_recv() {
+            last = Some(update);
+        }
+        assert_eq!(last, Some(TransferProgress { bytes_done: 12, total_bytes: 12, current_file: String::new() }));
+    }
+
+    #[tokio::test]
+    async fn cancelled_recv_removes_local_file() {
+        let local = temp_path("recv-cancel.txt");
+        let (client, _server) = fake_server(|listener| async move {
+            let (mut stream, _) = accept_service(&listener).await;
+            read_sync(&mut stream).await;
+            let mut reply = b"DATA".to_vec();
+            reply.extend_from_slice(&100u32.to_le_bytes());
+            reply.extend_from_slice(b"only part of it");
+            stream.write_all(&reply).await.unwrap();
+            // Hold the connection open so the transfer stalls until it is cancelled.
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        })
+        .await;
+        let mut sync = client.sync("emulator-5554").await.unwrap();
+        let mut progress = ProgressReporter::new(None, 0);
+        let recv = sync.recv_file("/sdcard/big.bin", &local, &mut progress);
+        assert!(tokio::time::timeout(Duration::from_millis(200), recv).await.is_err());
+        assert!(!local.exists());
+    }
+
+    #[tokio::test]
+    async fn sync_recv_failure_removes_local_file() {
+        let local = temp_path("recv-fail.txt");
+        let (client, _server) = fake_server(|listener| async move {
+            let (mut stream, _) = accept_service(&listener).await;
+            read_sync(&mut stream).await;
+            let message = "open failed: No such file or directory";
+            let mut reply = b"FAIL".to_vec();
+            reply.extend_from_slice(&(message.len() as u32).to_le_bytes());
+            reply.extend_from_slice(message.as_bytes());
+            stream.write_all(&reply).await.unwrap();
+        })
+        .await;
+        let mut sync = client.sync("emulator-5554").await.unwrap();
+        let mut progress = ProgressReporter::new(None, 0);
+        let result = sync.recv_file("/sdcard/missing.txt", &local, &mut progress).await;
+        assert!(matches!(result, Err(AdbError::NoSuchFile(_))));
+        assert!(!local.exists());
+    }
+}
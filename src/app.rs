@@ -1,15 +1,27 @@
 use crate::adb::{
     adb_devices, adb_disconnect, adb_reboot, adb_reboot_bootloader, adb_reboot_recovery,
+    adb_server_version,
 };
 use crate::app_manager::AppManager;
-use crate::device::{parse_devices, Device};
+use crate::crash_detector::{CrashLog, CrashPanel};
+use crate::device::{parse_devices, Device, DevicePropsCache};
+use crate::error::AdbError;
+use crate::device_tracker::{apply_event, diff_devices, DeviceTracker};
 use crate::fileops::FileManager;
 use crate::log_viewer::LogViewer;
+use crate::mdns::NearbyDevices;
 use crate::media::MediaManager;
+use crate::screenshots::ScreenshotHistory;
 use crate::settings::{AdbStudioSettings, Settings};
-use crate::terminal::Terminal;
+use crate::terminal::{ShellTabs, Terminal};
+use crate::transfers::{TransferPanel, TransferQueue};
+use crate::wireless::WirelessPanel;
+use crate::installer::{install_groups, install_packages, is_installable, InstallOptions};
 use confy::load;
+use dioxus::html::HasFileData;
 use dioxus::prelude::*;
+use std::path::PathBuf;
+use tokio_util::sync::CancellationToken;
 
 #[derive(Clone, PartialEq)]
 enum View {
@@ -20,6 +32,8 @@ enum View {
     Apps,
     Media,
     Logs,
+    Crashes,
+    Transfers,
     Settings,
 }
 
@@ -29,6 +43,50 @@ pub struct AppState {
     pub selected_device: Option<Device>,
 }
 
+fn update_devices(mut app_state: Signal<AppState>, mut alert_message: Signal<String>, devices: Vec<Device>) {
+    let events = diff_devices(&app_state.read().devices, &devices);
+    let mut notices = Vec::new();
+    {
+        let mut state = app_state.write();
+        for event in events {
+            notices.extend(apply_event(&mut state, event));
+        }
+    }
+    if !notices.is_empty() {
+        alert_message.set(notices.join(", "));
+        spawn(async move {
+            tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
+            alert_message.set(String::new());
+        });
+    }
+}
+
+/// Follows the device list for as long as it runs, reconnecting to the tracker when the stream
+/// ends and polling when tracking isn't supported.
+async fn track_devices(settings: Signal<AdbStudioSettings>, app_state: Signal<AppState>, alert_message: Signal<String>) {
+    let mut props_cache = DevicePropsCache::default();
+    loop {
+        let settings_clone = settings.peek().clone();
+        match DeviceTracker::connect(&settings_clone).await {
+            Ok(mut tracker) => {
+                while let Ok(mut devices) = tracker.next_snapshot().await {
+                    props_cache.enrich(&settings_clone, &mut devices).await;
+                    update_devices(app_state, alert_message, devices);
+                }
+            }
+            // Servers or binaries without track-devices support fall back to polling.
+            Err(_) => {
+                if let Ok(output) = adb_devices(&settings_clone).await {
+                    let mut devices = parse_devices(&output);
+                    props_cache.enrich(&settings_clone, &mut devices).await;
+                    update_devices(app_state, alert_message, devices);
+                }
+            }
+        }
+        tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+    }
+}
+
 #[allow(non_snake_case)]
 pub fn App() -> Element {
     let mut app_state = use_context_provider(|| Signal::new(AppState::default()));
@@ -38,23 +96,178 @@ pub fn App() -> Element {
     let mut settings = use_context_provider(|| {
         Signal::new(load::<AdbStudioSettings>("adb-studio", None).unwrap_or_default())
     });
+    use_context_provider(|| Signal::new(ShellTabs::default()));
+    use_context_provider(|| Signal::new(ScreenshotHistory::default()));
+    let transfer_queue = use_context_provider(|| Signal::new(TransferQueue::default()));
+    let crash_log = use_context_provider(|| Signal::new(CrashLog::default()));
+    let mut last_crash_notice = use_signal(|| 0u64);
+    let mut drop_install = use_signal(|| None::<(String, CancellationToken)>);
+    let mut drop_broadcast = use_signal(|| None::<Vec<PathBuf>>);
 
-    use_future(move || async move {
-        loop {
-            if let Ok(output) = adb_devices(&settings.read()).await {
-                app_state.write().devices = parse_devices(&output);
-            }
-            tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+    use_effect(move || {
+        let log = crash_log.read();
+        let Some(latest) = log.incidents.iter().max_by_key(|i| i.id) else {
+            return;
+        };
+        if latest.id <= *last_crash_notice.peek() {
+            return;
         }
+        last_crash_notice.set(latest.id);
+        alert_message.set(format!("{} detected", latest.title()));
+        spawn(async move {
+            tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
+            alert_message.set(String::new());
+        });
+    });
+
+    let server_version = use_resource(move || async move { adb_server_version(&settings.read()).await });
+
+    // Tracking restarts whenever the backend or the adb binary changes, so it follows Settings.
+    let connection = use_memo(move || {
+        let settings = settings.read();
+        (settings.backend, settings.adb_path.clone())
+    });
+    let mut tracker_cancel = use_signal(|| None::<CancellationToken>);
+    use_effect(move || {
+        connection.read();
+        let cancel = CancellationToken::new();
+        if let Some(previous) = tracker_cancel.replace(Some(cancel.clone())) {
+            previous.cancel();
+        }
+        spawn(async move {
+            tokio::select! {
+                _ = track_devices(settings, app_state, alert_message) => {}
+                _ = cancel.cancelled() => {}
+            }
+        });
     });
 
+    let mut show_alert = move |message: String| {
+        alert_message.set(message);
+        spawn(async move {
+            tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
+            alert_message.set(String::new());
+        });
+    };
+
+    // Installs packages dropped on the window, one device after another, until done or cancelled.
+    let mut install_dropped = move |packages: Vec<PathBuf>, targets: Vec<Device>| {
+        let cancel = CancellationToken::new();
+        let names = targets.iter().map(|d| d.name()).collect::<Vec<_>>().join(", ");
+        drop_install.set(Some((format!("Installing on {}...", names), cancel.clone())));
+        spawn(async move {
+            let settings_clone = settings.read().clone();
+            let mut failures = Vec::new();
+            'devices: for device in &targets {
+                for group in install_groups(&packages) {
+                    let result = install_packages(
+                        transfer_queue,
+                        &settings_clone,
+                        &device.serial,
+                        &group,
+                        &InstallOptions::default(),
+                        &cancel,
+                        |_| {},
+                    )
+                    .await;
+                    match result {
+                        Ok(_) => {}
+                        Err(AdbError::Cancelled) => break 'devices,
+                        Err(e) => failures.push(format!("{}: {}", device.name(), e)),
+                    }
+                }
+            }
+            drop_install.set(None);
+            if cancel.is_cancelled() {
+                show_alert("Installation cancelled".to_string());
+            } else if failures.is_empty() {
+                show_alert(format!("Installed on {}", names));
+            } else {
+                show_alert(format!("Install failed on {}", failures.join("; ")));
+            }
+        });
+    };
+
     rsx! {
-        if !alert_message.read().is_empty() {
+        if let Some((status, cancel)) = drop_install() {
+            div {
+                class: "alert-message",
+                style: "background: var(--accent-color); display: flex; align-items: center; gap: 12px;",
+                "{status}"
+                button {
+                    class: "btn btn-secondary",
+                    style: "margin: 0; padding: 4px 10px;",
+                    onclick: move |_| cancel.cancel(),
+                    "Cancel"
+                }
+            }
+        } else if let Some(packages) = drop_broadcast() {
+            div {
+                class: "alert-message",
+                style: "background: var(--accent-color); display: flex; align-items: center; gap: 12px;",
+                {
+                    let online = app_state.read().devices.iter().filter(|d| d.status == "device").count();
+                    format!(
+                        "Install {} package{} on all {} connected device{}?",
+                        packages.len(),
+                        if packages.len() == 1 { "" } else { "s" },
+                        online,
+                        if online == 1 { "" } else { "s" }
+                    )
+                }
+                button {
+                    class: "btn",
+                    style: "margin: 0; padding: 4px 10px; background: var(--secondary-bg); color: var(--primary-text);",
+                    onclick: move |_| {
+                        drop_broadcast.set(None);
+                        let targets: Vec<Device> = app_state.read().devices.iter().filter(|d| d.status == "device").cloned().collect();
+                        if targets.is_empty() {
+                            show_alert("No connected devices".to_string());
+                        } else {
+                            install_dropped(packages.clone(), targets);
+                        }
+                    },
+                    "Install on All"
+                }
+                button {
+                    class: "btn btn-secondary",
+                    style: "margin: 0; padding: 4px 10px;",
+                    onclick: move |_| drop_broadcast.set(None),
+                    "Cancel"
+                }
+            }
+        } else if !alert_message.read().is_empty() {
             div { class: "alert-message", "{alert_message}" }
         }
         div {
             class: if settings.read().dark_mode { "app" } else { "app light-theme" },
             style: "height: 100vh;",
+            ondragover: move |e| e.prevent_default(),
+            // Packages dropped anywhere are installed; other files are pushed by the Files view.
+            ondrop: move |e| {
+                e.prevent_default();
+                let (packages, others): (Vec<PathBuf>, Vec<PathBuf>) = e.files().iter().map(|f| f.path()).partition(|p| is_installable(p));
+                if packages.is_empty() {
+                    if !others.is_empty() && current_view() != View::Files {
+                        show_alert("Open Files to push files to the device".to_string());
+                    }
+                    return;
+                }
+                if drop_install.peek().is_some() {
+                    show_alert("An install is already running".to_string());
+                    return;
+                }
+                // Holding Shift offers to install on every connected device instead of the selected one.
+                if e.modifiers().shift() {
+                    drop_broadcast.set(Some(packages));
+                    return;
+                }
+                let Some(device) = app_state.read().selected_device.clone() else {
+                    show_alert("Please select a device first".to_string());
+                    return;
+                };
+                install_dropped(packages, vec![device]);
+            },
             div { class: "sidebar",
                 h1 { "ADB Studio" }
                 nav {
@@ -130,6 +343,8 @@ pub fn App() -> Element {
                         },
                         i { class: "fas fa-file-alt" } "Logs" 
                     }
+                    button { onclick: move |_| current_view.set(View::Crashes), i { class: "fas fa-bug" } "Crashes" }
+                    button { onclick: move |_| current_view.set(View::Transfers), i { class: "fas fa-exchange-alt" } "Transfers" }
                     button { onclick: move |_| current_view.set(View::Settings), i { class: "fas fa-cog" } "Settings" }
                 }
             }
@@ -145,6 +360,20 @@ pub fn App() -> Element {
                         if settings.read().dark_mode { "Dark" } else { "Light" }
                     }
                     div { style: "margin-left: auto; display: flex; align-items: center; gap: 12px;",
+                        if crash_log.read().unseen() > 0 {
+                            button {
+                                onclick: move |_| current_view.set(View::Crashes),
+                                i { class: "fas fa-bug", style: "color: var(--error-color);" }
+                                "{crash_log.read().unseen()} new crashes"
+                            }
+                        }
+                        if transfer_queue.read().active_count() > 0 {
+                            button {
+                                onclick: move |_| current_view.set(View::Transfers),
+                                i { class: "fas fa-exchange-alt" }
+                                "{transfer_queue.read().active_count()} transferring"
+                            }
+                        }
                         if !app_state.read().devices.is_empty() {
                             div { 
                                 style: "position: relative;",
@@ -154,7 +383,7 @@ pub fn App() -> Element {
                                     onclick: move |_| dropdown_open.set(!dropdown_open()),
                                     span {
                                         if let Some(dev) = &app_state.read().selected_device {
-                                            "{dev.model} ({dev.serial})"
+                                            "{dev.label()}"
                                         } else {
                                             "Select Device"
                                         }
@@ -173,7 +402,7 @@ pub fn App() -> Element {
                                                     app_state.write().selected_device = Some(device.clone());
                                                     dropdown_open.set(false);
                                                 },
-                                                "{device.model} ({device.serial})"
+                                                "{device.label()}"
                                             }
                                         }
                                     }
@@ -210,7 +439,7 @@ pub fn App() -> Element {
                                     }
                                     div { class: "stat-label",
                                         if let Some(dev) = &app_state.read().selected_device {
-                                            "{dev.model}"
+                                            "{dev.name()}"
                                         } else {
                                             "None"
                                         }
@@ -218,8 +447,20 @@ pub fn App() -> Element {
                                 }
                                 div { class: "stat-card",
                                     h3 { "ADB Server" }
-                                    div { class: "stat-value", i { class: "fas fa-check-circle", style: "color: var(--success-color);" } }
-                                    div { class: "stat-label", "Running" }
+                                    match &*server_version.read() {
+                                        Some(Ok(version)) => rsx! {
+                                            div { class: "stat-value", i { class: "fas fa-check-circle", style: "color: var(--success-color);" } }
+                                            div { class: "stat-label", "Running ({version})" }
+                                        },
+                                        Some(Err(_)) => rsx! {
+                                            div { class: "stat-value", i { class: "fas fa-times-circle", style: "color: var(--error-color);" } }
+                                            div { class: "stat-label", "Unavailable" }
+                                        },
+                                        None => rsx! {
+                                            div { class: "stat-value", i { class: "fas fa-spinner fa-spin" } }
+                                            div { class: "stat-label", "Checking..." }
+                                        },
+                                    }
                                 }
                             }
                             if let Some(dev) = &app_state.read().selected_device {
@@ -235,7 +476,31 @@ pub fn App() -> Element {
                                     }
                                     div { class: "device-info-item",
                                         span { class: "label", "Model" }
-                                        span { class: "value", "{dev.model}" }
+                                        span { class: "value", "{dev.name()}" }
+                                    }
+                                    if !dev.connection().is_empty() {
+                                        div { class: "device-info-item",
+                                            span { class: "label", "Connection" }
+                                            span { class: "value", "{dev.connection()}" }
+                                        }
+                                    }
+                                    if let Some(props) = &dev.props {
+                                        div { class: "device-info-item",
+                                            span { class: "label", "Manufacturer" }
+                                            span { class: "value", "{props.manufacturer}" }
+                                        }
+                                        div { class: "device-info-item",
+                                            span { class: "label", "Android Version" }
+                                            span { class: "value", "{props.android_version} (SDK {props.sdk_level})" }
+                                        }
+                                        div { class: "device-info-item",
+                                            span { class: "label", "ABI" }
+                                            span { class: "value", "{props.abi}" }
+                                        }
+                                        div { class: "device-info-item",
+                                            span { class: "label", "Build Fingerprint" }
+                                            span { class: "value", style: "word-break: break-all; text-align: right; margin-left: 16px;", "{props.fingerprint}" }
+                                        }
                                     }
                                 }
                             }
@@ -309,6 +574,8 @@ pub fn App() -> Element {
                                         th { "Serial" }
                                         th { "Status" }
                                         th { "Model" }
+                                        th { "Android" }
+                                        th { "Connection" }
                                         th { "Actions" }
                                     }
                                 }
@@ -327,6 +594,12 @@ pub fn App() -> Element {
                                                     td { {dev_item.serial.clone()} }
                                                     td { {dev_item.status.clone()} }
                                                     td { {dev_item.model.clone()} }
+                                                    td {
+                                                        if let Some(props) = &dev_item.props {
+                                                            "{props.android_version} (SDK {props.sdk_level})"
+                                                        }
+                                                    }
+                                                    td { {dev_item.connection()} }
                                                     td {
                                                         button { onclick: move |_| {
                                                             let serial = item_clone1.serial.clone();
@@ -363,12 +636,16 @@ pub fn App() -> Element {
                                     }
                                 }
                             }
+                            NearbyDevices {}
+                            WirelessPanel {}
                         },
                         View::Files => rsx!{ FileManager {} },
                         View::Terminal => rsx!{ Terminal {} },
                         View::Apps => rsx!{ AppManager {} },
                         View::Media => rsx!{ MediaManager {} },
                         View::Logs => rsx!{ LogViewer {} },
+                        View::Crashes => rsx!{ CrashPanel {} },
+                        View::Transfers => rsx!{ TransferPanel {} },
                         View::Settings => rsx!{ Settings {} },
                     }
                 }
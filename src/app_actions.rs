@@ -0,0 +1,214 @@
+use dioxus::prelude::*;
+use crate::adb::{adb_shell, shell_quote};
+use crate::app_details::{EnabledState, UserState};
+use crate::error::AdbError;
+use crate::settings::AdbStudioSettings;
+use crate::ui::ErrorNotice;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AppAction {
+    Launch,
+    ForceStop,
+    ClearData,
+    Disable,
+    Enable,
+    Hide,
+    Unhide,
+}
+
+/// What an action did, as reported back by the device.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ActionOutcome {
+    Launched { activity: Option<String> },
+    Stopped,
+    DataCleared,
+    StateChanged(EnabledState),
+    HiddenChanged(bool),
+}
+
+impl ActionOutcome {
+    pub fn message(&self, package: &str) -> String {
+        match self {
+            ActionOutcome::Launched { activity: Some(activity) } => format!("Launched {}", activity),
+            ActionOutcome::Launched { activity: None } => format!("Launched {}", package),
+            ActionOutcome::Stopped => format!("Force-stopped {}", package),
+            ActionOutcome::DataCleared => format!("Cleared data for {}", package),
+            ActionOutcome::StateChanged(state) => format!("{} is now {}", package, state.label().to_lowercase()),
+            ActionOutcome::HiddenChanged(true) => format!("{} is now hidden", package),
+            ActionOutcome::HiddenChanged(false) => format!("{} is no longer hidden", package),
+        }
+    }
+}
+
+impl AppAction {
+    pub fn label(&self) -> &'static str {
+        match self {
+            AppAction::Launch => "Launch",
+            AppAction::ForceStop => "Force Stop",
+            AppAction::ClearData => "Clear Data",
+            AppAction::Disable => "Disable",
+            AppAction::Enable => "Enable",
+            AppAction::Hide => "Hide",
+            AppAction::Unhide => "Unhide",
+        }
+    }
+
+    pub fn icon(&self) -> &'static str {
+        match self {
+            AppAction::Launch => "fas fa-play",
+            AppAction::ForceStop => "fas fa-stop",
+            AppAction::ClearData => "fas fa-eraser",
+            AppAction::Disable => "fas fa-ban",
+            AppAction::Enable => "fas fa-check",
+            AppAction::Hide => "fas fa-eye-slash",
+            AppAction::Unhide => "fas fa-eye",
+        }
+    }
+
+    /// The question to ask first, for actions that lose data or take the app away from the user.
+    pub fn confirmation(&self) -> Option<&'static str> {
+        match self {
+            AppAction::ClearData => Some("Delete all of this app's data, accounts and settings?"),
+            AppAction::Disable => Some("Disable this app for the current user? It disappears from the launcher until enabled again."),
+            AppAction::Hide => Some("Hide this app? It can't be used until it is unhidden."),
+            _ => None,
+        }
+    }
+
+    pub async fn run(&self, settings: &AdbStudioSettings, serial: &str, package: &str) -> Result<ActionOutcome, AdbError> {
+        let quoted = shell_quote(package);
+        match self {
+            AppAction::Launch => launch(settings, serial, package).await,
+            AppAction::ForceStop => {
+                adb_shell(settings, serial, &format!("am force-stop {}", quoted)).await?;
+                Ok(ActionOutcome::Stopped)
+            }
+            AppAction::ClearData => {
+                // pm clear reports failure on stdout and still exits 0 on older releases.
+                let output = adb_shell(settings, serial, &format!("pm clear {}", quoted)).await?;
+                expect(&output, "Success").map(|_| ActionOutcome::DataCleared)
+            }
+            AppAction::Disable | AppAction::Enable => {
+                let verb = if *self == AppAction::Disable { "disable-user" } else { "enable" };
+                let output = adb_shell(settings, serial, &format!("pm {} {}", verb, quoted)).await?;
+                // Package com.example new state: disabled-user
+                let state = expect(&output, "new state:")?.rsplit(':').next().unwrap_or_default().trim().to_string();
+                Ok(ActionOutcome::StateChanged(match state.as_str() {
+                    "disabled-user" => EnabledState::DisabledUser,
+                    "disabled" => EnabledState::Disabled,
+                    "disabled-until-used" => EnabledState::DisabledUntilUsed,
+                    "default" => EnabledState::Default,
+                    _ => EnabledState::Enabled,
+                }))
+            }
+            AppAction::Hide | AppAction::Unhide => {
+                let verb = if *self == AppAction::Hide { "hide" } else { "unhide" };
+                let output = adb_shell(settings, serial, &format!("pm {} {}", verb, quoted)).await?;
+                // Package com.example new hidden state: true
+                let hidden = expect(&output, "new hidden state:")?.trim_end().ends_with("true");
+                Ok(ActionOutcome::HiddenChanged(hidden))
+            }
+        }
+    }
+}
+
+/// Returns the line of `output` containing `marker`, or the output itself as the error.
+fn expect<'a>(output: &'a str, marker: &str) -> Result<&'a str, AdbError> {
+    output
+        .lines()
+        .find(|line| line.contains(marker))
+        .ok_or_else(|| AdbError::classify(None, if output.trim().is_empty() { "no output" } else { output }))
+}
+
+/// Starts the launcher activity, resolved through the package manager so the exact activity can
+/// be reported. `monkey` is the fallback for releases without `cmd package resolve-activity`.
+async fn launch(settings: &AdbStudioSettings, serial: &str, package: &str) -> Result<ActionOutcome, AdbError> {
+    let resolve = format!("cmd package resolve-activity --brief -c android.intent.category.LAUNCHER {}", shell_quote(package));
+    let activity = adb_shell(settings, serial, &resolve)
+        .await
+        .ok()
+        .and_then(|output| output.lines().last().map(str::trim).filter(|line| line.contains('/')).map(str::to_string));
+    if let Some(activity) = activity {
+        let output = adb_shell(settings, serial, &format!("am start -n {}", shell_quote(&activity))).await?;
+        if let Some(error) = output.lines().find(|line| line.starts_with("Error")) {
+            return Err(AdbError::classify(None, error));
+        }
+        return Ok(ActionOutcome::Launched { activity: Some(activity) });
+    }
+    let monkey = format!("monkey -p {} -c android.intent.category.LAUNCHER 1", shell_quote(package));
+    let output = adb_shell(settings, serial, &monkey).await?;
+    expect(&output, "Events injected").map(|_| ActionOutcome::Launched { activity: None })
+}
+
+#[component]
+pub fn AppActions(serial: String, package: String, user: Option<UserState>, on_change: EventHandler<()>) -> Element {
+    let settings = use_context::<Signal<AdbStudioSettings>>();
+    let mut pending = use_signal(|| None::<AppAction>);
+    let mut busy = use_signal(|| false);
+    let mut status = use_signal(String::new);
+    let mut error = use_signal(|| None::<AdbError>);
+
+    let run = use_callback(move |action: AppAction| {
+        let serial = serial.clone();
+        let package = package.clone();
+        pending.set(None);
+        spawn(async move {
+            busy.set(true);
+            error.set(None);
+            status.set(String::new());
+            let settings_clone = settings.read().clone();
+            match action.run(&settings_clone, &serial, &package).await {
+                Ok(outcome) => status.set(outcome.message(&package)),
+                Err(e) => error.set(Some(e)),
+            }
+            busy.set(false);
+            on_change.call(());
+        });
+    });
+
+    let enabled = user.as_ref().map(|u| u.enabled.is_enabled()).unwrap_or(true);
+    let hidden = user.as_ref().map(|u| u.hidden).unwrap_or(false);
+    let actions = [
+        AppAction::Launch,
+        AppAction::ForceStop,
+        AppAction::ClearData,
+        if enabled { AppAction::Disable } else { AppAction::Enable },
+        if hidden { AppAction::Unhide } else { AppAction::Hide },
+    ];
+
+    rsx! {
+        div {
+            div { style: "display: flex; flex-wrap: wrap;",
+                for action in actions {
+                    button {
+                        class: "btn btn-secondary",
+                        disabled: busy(),
+                        onclick: move |_| {
+                            if action.confirmation().is_some() {
+                                pending.set(Some(action));
+                            } else {
+                                run.call(action);
+                            }
+                        },
+                        i { class: action.icon() }
+                        "{action.label()}"
+                    }
+                }
+            }
+            if let Some(action) = pending() {
+                div {
+                    style: "display: flex; align-items: center; gap: 8px; padding: 8px 12px; margin-bottom: 8px; border: 1px solid var(--warning-color); border-radius: 6px;",
+                    span { style: "flex: 1; font-size: 0.875rem;", {action.confirmation().unwrap_or_default()} }
+                    button { class: "btn", style: "margin: 0;", onclick: move |_| run.call(action), "{action.label()}" }
+                    button { class: "btn btn-secondary", style: "margin: 0;", onclick: move |_| pending.set(None), "Cancel" }
+                }
+            }
+            if !status.read().is_empty() {
+                p { style: "font-size: 0.875rem; color: var(--success-color); margin-bottom: 8px;", "{status}" }
+            }
+            if let Some(e) = error() {
+                ErrorNotice { error: e }
+            }
+        }
+    }
+}
@@ -0,0 +1,552 @@
+use dioxus::prelude::*;
+use crate::adb::{adb_dumpsys_package, adb_pm_path};
+use crate::app_actions::AppActions;
+use crate::error::AdbError;
+use crate::permissions::PermissionsTab;
+use crate::settings::AdbStudioSettings;
+use crate::ui::ErrorNotice;
+
+/// `PackageManager.COMPONENT_ENABLED_STATE_*`, as printed in the `enabled=` field.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EnabledState {
+    Default,
+    Enabled,
+    Disabled,
+    DisabledUser,
+    DisabledUntilUsed,
+}
+
+impl EnabledState {
+    fn from_code(code: &str) -> Self {
+        match code {
+            "1" => EnabledState::Enabled,
+            "2" => EnabledState::Disabled,
+            "3" => EnabledState::DisabledUser,
+            "4" => EnabledState::DisabledUntilUsed,
+            _ => EnabledState::Default,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        matches!(self, EnabledState::Default | EnabledState::Enabled)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            EnabledState::Default => "Enabled (default)",
+            EnabledState::Enabled => "Enabled",
+            EnabledState::Disabled => "Disabled",
+            EnabledState::DisabledUser => "Disabled by user",
+            EnabledState::DisabledUntilUsed => "Disabled until used",
+        }
+    }
+}
+
+/// A package's state for one user, from its `User N:` line and the block under it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserState {
+    pub user_id: u32,
+    pub installed: bool,
+    pub hidden: bool,
+    pub suspended: bool,
+    pub stopped: bool,
+    pub enabled: EnabledState,
+    /// Runtime permissions are granted per user, so a work profile has its own list.
+    pub runtime_permissions: Vec<PermissionGrant>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PermissionGrant {
+    pub name: String,
+    pub granted: bool,
+    pub flags: Vec<String>,
+}
+
+/// What `dumpsys package` and `pm path` report about one installed package.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PackageInfo {
+    pub package: String,
+    pub version_name: String,
+    pub version_code: String,
+    pub min_sdk: Option<u32>,
+    pub target_sdk: Option<u32>,
+    pub first_install_time: String,
+    pub last_update_time: String,
+    pub installer: Option<String>,
+    pub base_apk: Option<String>,
+    pub split_apks: Vec<String>,
+    pub data_dir: String,
+    pub uid: Option<u32>,
+    pub requested_permissions: Vec<String>,
+    pub install_permissions: Vec<PermissionGrant>,
+    pub users: Vec<UserState>,
+}
+
+impl PackageInfo {
+    pub async fn query(settings: &AdbStudioSettings, serial: &str, package: &str) -> Result<Self, AdbError> {
+        let dumpsys = adb_dumpsys_package(settings, serial, package).await?;
+        let paths = adb_pm_path(settings, serial, package).await.unwrap_or_default();
+        PackageInfo::parse(package, &dumpsys, &paths)
+            .ok_or_else(|| AdbError::NoSuchFile(format!("package {} is not installed", package)))
+    }
+
+    /// Reads the `Package [<package>]` block of `dumpsys package`; the rest of the dump
+    /// (resolver tables, hidden system packages) is ignored.
+    pub fn parse(package: &str, dumpsys: &str, pm_path: &str) -> Option<Self> {
+        let header = format!("Package [{}]", package);
+        let mut lines = dumpsys.lines().skip_while(|line| !line.trim_start().starts_with(&header));
+        let block_indent = indent(lines.next()?);
+
+        let mut info = PackageInfo { package: package.to_string(), ..Default::default() };
+        // The subsection being read and the indentation of its heading.
+        let mut section: Option<(&str, usize)> = None;
+        for line in lines.take_while(|line| line.trim().is_empty() || indent(line) > block_indent) {
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            if let Some((_, heading_indent)) = section {
+                if indent(line) <= heading_indent {
+                    section = None;
+                }
+            }
+            match section {
+                Some(("requested permissions:", _)) => {
+                    // Newer releases append restrictions, e.g. `android.permission.X: restricted=true`.
+                    let name = trimmed.split(':').next().unwrap_or(trimmed);
+                    info.requested_permissions.push(name.to_string());
+                    continue;
+                }
+                Some(("install permissions:", _)) => {
+                    info.install_permissions.extend(parse_grant(trimmed));
+                    continue;
+                }
+                Some(("runtime permissions:", _)) => {
+                    if let Some(user) = info.users.last_mut() {
+                        user.runtime_permissions.extend(parse_grant(trimmed));
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+            if matches!(trimmed, "requested permissions:" | "install permissions:" | "runtime permissions:") {
+                section = Some((trimmed, indent(line)));
+            } else if let Some(user) = trimmed.strip_prefix("User ") {
+                info.users.extend(parse_user(user));
+            } else if let Some(value) = trimmed.strip_prefix("versionName=") {
+                info.version_name = value.to_string();
+            } else if let Some(value) = trimmed.strip_prefix("firstInstallTime=") {
+                info.first_install_time = value.to_string();
+            } else if let Some(value) = trimmed.strip_prefix("lastUpdateTime=") {
+                info.last_update_time = value.to_string();
+            } else if let Some(value) = trimmed.strip_prefix("installerPackageName=") {
+                info.installer = (value != "null").then(|| value.to_string());
+            } else if let Some(value) = trimmed.strip_prefix("dataDir=") {
+                info.data_dir = value.to_string();
+            } else if let Some(value) = trimmed.strip_prefix("userId=") {
+                info.uid = value.parse().ok();
+            } else if trimmed.starts_with("versionCode=") {
+                // versionCode=42 minSdk=24 targetSdk=34
+                for (key, value) in trimmed.split_whitespace().filter_map(|field| field.split_once('=')) {
+                    match key {
+                        "versionCode" => info.version_code = value.to_string(),
+                        "minSdk" => info.min_sdk = value.parse().ok(),
+                        "targetSdk" => info.target_sdk = value.parse().ok(),
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        let apks: Vec<String> = pm_path.lines().filter_map(|line| line.trim().strip_prefix("package:")).map(str::to_string).collect();
+        let base = apks.iter().position(|path| path.ends_with("/base.apk")).unwrap_or(0);
+        for (i, path) in apks.into_iter().enumerate() {
+            if i == base {
+                info.base_apk = Some(path);
+            } else {
+                info.split_apks.push(path);
+            }
+        }
+        Some(info)
+    }
+
+    /// Whether a permission is currently granted, at install time or at runtime for the primary
+    /// user.
+    pub fn is_granted(&self, permission: &str) -> bool {
+        self.install_permissions.iter().chain(self.runtime_permissions()).any(|p| p.name == permission && p.granted)
+    }
+
+    /// The primary user's runtime permissions.
+    pub fn runtime_permissions(&self) -> &[PermissionGrant] {
+        self.primary_user().map(|user| user.runtime_permissions.as_slice()).unwrap_or_default()
+    }
+
+    /// State for the primary user, which is what the shell commands act on by default.
+    pub fn primary_user(&self) -> Option<&UserState> {
+        self.users.iter().find(|u| u.user_id == 0).or(self.users.first())
+    }
+}
+
+fn indent(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Parses `android.permission.CAMERA: granted=false, flags=[ USER_SET|USER_SENSITIVE_WHEN_GRANTED ]`.
+fn parse_grant(line: &str) -> Option<PermissionGrant> {
+    let (name, rest) = line.split_once(':')?;
+    let flags = rest
+        .split_once("flags=[")
+        .and_then(|(_, flags)| flags.split(']').next())
+        .map(|flags| flags.split('|').map(|f| f.trim().to_string()).filter(|f| !f.is_empty()).collect())
+        .unwrap_or_default();
+    Some(PermissionGrant { name: name.trim().to_string(), granted: rest.contains("granted=true"), flags })
+}
+
+/// Parses the rest of `User 0: ceDataInode=123 installed=true hidden=false ... enabled=0 ...`.
+fn parse_user(line: &str) -> Option<UserState> {
+    let (id, fields) = line.split_once(':')?;
+    let mut user = UserState {
+        user_id: id.trim().parse().ok()?,
+        installed: true,
+        hidden: false,
+        suspended: false,
+        stopped: false,
+        enabled: EnabledState::Default,
+        runtime_permissions: Vec::new(),
+    };
+    for (key, value) in fields.split_whitespace().filter_map(|field| field.split_once('=')) {
+        match key {
+            "installed" => user.installed = value == "true",
+            "hidden" => user.hidden = value == "true",
+            "suspended" => user.suspended = value == "true",
+            "stopped" => user.stopped = value == "true",
+            "enabled" => user.enabled = EnabledState::from_code(value),
+            _ => {}
+        }
+    }
+    Some(user)
+}
+
+#[component]
+fn DetailRow(label: String, value: String) -> Element {
+    rsx! {
+        div { class: "device-info-item",
+            span { class: "label", "{label}" }
+            span { class: "value", style: "text-align: right; word-break: break-all; margin-left: 16px;", "{value}" }
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum DetailsTab {
+    Overview,
+    Permissions,
+}
+
+#[component]
+pub fn AppDetails(serial: String, package: String) -> Element {
+    let settings = use_context::<Signal<AdbStudioSettings>>();
+    let mut info = use_signal(|| None::<PackageInfo>);
+    let mut is_loading = use_signal(|| false);
+    let mut error = use_signal(|| None::<AdbError>);
+    let mut tab = use_signal(|| DetailsTab::Overview);
+    let mut reload = use_signal(|| 0u32);
+
+    use_effect(use_reactive!(|serial, package| {
+        reload();
+        let settings_clone = settings.peek().clone();
+        spawn(async move {
+            is_loading.set(true);
+            error.set(None);
+            match PackageInfo::query(&settings_clone, &serial, &package).await {
+                Ok(details) => info.set(Some(details)),
+                Err(e) => {
+                    info.set(None);
+                    error.set(Some(e));
+                }
+            }
+            is_loading.set(false);
+        });
+    }));
+
+    rsx! {
+        div {
+            class: "device-info",
+            style: "margin-top: 0;",
+            h3 { "{package}" }
+            AppActions {
+                serial: serial.clone(),
+                package: package.clone(),
+                user: info.read().as_ref().and_then(|details| details.primary_user().cloned()),
+                on_change: move |_| reload += 1,
+            }
+            div {
+                style: "display: flex; gap: 4px; margin-bottom: 8px;",
+                button {
+                    class: if tab() == DetailsTab::Overview { "btn" } else { "btn btn-secondary" },
+                    onclick: move |_| tab.set(DetailsTab::Overview),
+                    i { class: "fas fa-info-circle" }
+                    "Overview"
+                }
+                button {
+                    class: if tab() == DetailsTab::Permissions { "btn" } else { "btn btn-secondary" },
+                    onclick: move |_| tab.set(DetailsTab::Permissions),
+                    i { class: "fas fa-shield-alt" }
+                    "Permissions"
+                }
+            }
+            if let Some(e) = error() {
+                ErrorNotice { error: e }
+            }
+            if is_loading() && info.read().is_none() {
+                p { style: "color: var(--secondary-text); font-size: 0.875rem;", "Loading package details..." }
+            }
+            if let (Some(details), DetailsTab::Permissions) = (info(), tab()) {
+                PermissionsTab {
+                    serial: serial.clone(),
+                    package: package.clone(),
+                    user_id: details.primary_user().map(|user| user.user_id).unwrap_or(0),
+                    permissions: details.runtime_permissions().to_vec(),
+                    on_change: move |_| reload += 1,
+                }
+            } else if let Some(details) = info() {
+                {
+                    let sdk = |sdk: Option<u32>| sdk.map(|s| s.to_string()).unwrap_or_else(|| "-".to_string());
+                    let state = details.primary_user().map(|user| {
+                        let mut state = user.enabled.label().to_string();
+                        if user.hidden {
+                            state.push_str(", hidden");
+                        }
+                        if user.suspended {
+                            state.push_str(", suspended");
+                        }
+                        if user.stopped {
+                            state.push_str(", stopped");
+                        }
+                        if !user.installed {
+                            state = "Not installed for this user".to_string();
+                        }
+                        state
+                    });
+                    rsx! {
+                        DetailRow { label: "Version", value: format!("{} ({})", details.version_name, details.version_code) }
+                        DetailRow { label: "Target SDK", value: sdk(details.target_sdk) }
+                        DetailRow { label: "Min SDK", value: sdk(details.min_sdk) }
+                        DetailRow { label: "Installed", value: details.first_install_time.clone() }
+                        DetailRow { label: "Updated", value: details.last_update_time.clone() }
+                        DetailRow { label: "Installer", value: details.installer.clone().unwrap_or_else(|| "Unknown".to_string()) }
+                        DetailRow { label: "State", value: state.unwrap_or_else(|| "Unknown".to_string()) }
+                        DetailRow { label: "UID", value: details.uid.map(|u| u.to_string()).unwrap_or_default() }
+                        DetailRow { label: "Data directory", value: details.data_dir.clone() }
+                        DetailRow { label: "APK", value: details.base_apk.clone().unwrap_or_default() }
+                        for split in details.split_apks.iter() {
+                            DetailRow { label: "Split APK", value: split.clone() }
+                        }
+                        h3 { style: "margin-top: 16px;",
+                            {format!(
+                                "Permissions ({} of {} granted)",
+                                details.requested_permissions.iter().filter(|p| details.is_granted(p)).count(),
+                                details.requested_permissions.len()
+                            )}
+                        }
+                        for permission in details.requested_permissions.iter() {
+                            div { class: "device-info-item",
+                                span { class: "label", style: "word-break: break-all;", "{permission}" }
+                                if details.is_granted(permission) {
+                                    span { style: "color: var(--success-color); margin-left: 16px;", "Granted" }
+                                } else {
+                                    span { style: "color: var(--secondary-text); margin-left: 16px;", "Not granted" }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Trimmed `dumpsys package com.example.app` from an Android 13 device with a work profile.
+    const DUMPSYS: &str = "\
+Activity Resolver Table:
+  Non-Data Actions:
+      android.intent.action.MAIN:
+        5d0e7c4 com.example.app/.MainActivity filter 9a1b2c3
+          Action: \"android.intent.action.MAIN\"
+          Category: \"android.intent.category.LAUNCHER\"
+
+Key Set Manager:
+  [com.example.app]
+      Signing KeySets: 58
+
+Packages:
+  Package [com.example.app] (b7e3a1f):
+    userId=10234
+    pkg=Package{3c1d2e0 com.example.app}
+    codePath=/data/app/~~Xk3pQ9w==/com.example.app-Fq1bA2c==
+    resourcePath=/data/app/~~Xk3pQ9w==/com.example.app-Fq1bA2c==
+    legacyNativeLibraryDir=/data/app/~~Xk3pQ9w==/com.example.app-Fq1bA2c==/lib
+    primaryCpuAbi=arm64-v8a
+    versionCode=4021 minSdk=24 targetSdk=33
+    versionName=4.2.1 (beta)
+    usesNonSdkApi=false
+    splits=[base, config.arm64_v8a, config.xxhdpi]
+    apkSigningVersion=3
+    flags=[ HAS_CODE ALLOW_CLEAR_USER_DATA ALLOW_BACKUP ]
+    dataDir=/data/user/0/com.example.app
+    timeStamp=2024-05-02 09:14:27
+    firstInstallTime=2024-03-11 17:02:45
+    lastUpdateTime=2024-05-02 09:14:28
+    installerPackageName=com.android.vending
+    signatures=PackageSignatures{6a2b9f1 version:3, signatures:[4e8c2a10], past signatures:[]}
+    requested permissions:
+      android.permission.INTERNET
+      android.permission.CAMERA
+      android.permission.POST_NOTIFICATIONS
+      android.permission.ACCESS_FINE_LOCATION: restricted=true
+    install permissions:
+      android.permission.INTERNET: granted=true
+      android.permission.WAKE_LOCK: granted=false
+    User 0: ceDataInode=180263 installed=true hidden=false suspended=false distractionFlags=0 stopped=false notLaunched=false enabled=0 instant=false virtual=false
+      gids=[3003]
+      runtime permissions:
+        android.permission.POST_NOTIFICATIONS: granted=true, flags=[ USER_SET|USER_SENSITIVE_WHEN_GRANTED|USER_SENSITIVE_WHEN_DENIED]
+        android.permission.CAMERA: granted=false, flags=[ USER_SENSITIVE_WHEN_GRANTED|USER_SENSITIVE_WHEN_DENIED]
+        android.permission.ACCESS_FINE_LOCATION: granted=false, flags=[ RESTRICTION_INSTALLER_EXEMPT ]
+      disabledComponents:
+        com.example.app.DebugActivity
+    User 10: ceDataInode=204117 installed=true hidden=false suspended=false distractionFlags=0 stopped=true notLaunched=false enabled=3 instant=false virtual=false
+      gids=[3003]
+      runtime permissions:
+        android.permission.POST_NOTIFICATIONS: granted=false, flags=[ USER_SENSITIVE_WHEN_GRANTED|USER_SENSITIVE_WHEN_DENIED]
+        android.permission.CAMERA: granted=true, flags=[ USER_SET|USER_SENSITIVE_WHEN_GRANTED|USER_SENSITIVE_WHEN_DENIED]
+
+Queries:
+  system apps queryable: false
+
+Hidden system packages:
+  Package [com.example.app] (4c2d1e8):
+    userId=10234
+    versionCode=1 minSdk=24 targetSdk=33
+    versionName=1.0
+";
+
+    const PM_PATH: &str = "\
+package:/data/app/~~Xk3pQ9w==/com.example.app-Fq1bA2c==/split_config.arm64_v8a.apk
+package:/data/app/~~Xk3pQ9w==/com.example.app-Fq1bA2c==/base.apk
+package:/data/app/~~Xk3pQ9w==/com.example.app-Fq1bA2c==/split_config.xxhdpi.apk
+";
+
+    #[test]
+    fn parses_package_block() {
+        let info = PackageInfo::parse("com.example.app", DUMPSYS, PM_PATH).unwrap();
+        assert_eq!(info.package, "com.example.app");
+        // The hidden system package further down must not overwrite the installed version.
+        assert_eq!(info.version_name, "4.2.1 (beta)");
+        assert_eq!(info.version_code, "4021");
+        assert_eq!(info.min_sdk, Some(24));
+        assert_eq!(info.target_sdk, Some(33));
+        assert_eq!(info.uid, Some(10234));
+        assert_eq!(info.data_dir, "/data/user/0/com.example.app");
+        assert_eq!(info.first_install_time, "2024-03-11 17:02:45");
+        assert_eq!(info.last_update_time, "2024-05-02 09:14:28");
+        assert_eq!(info.installer.as_deref(), Some("com.android.vending"));
+    }
+
+    #[test]
+    fn parses_permissions() {
+        let info = PackageInfo::parse("com.example.app", DUMPSYS, "").unwrap();
+        assert_eq!(
+            info.requested_permissions,
+            [
+                "android.permission.INTERNET",
+                "android.permission.CAMERA",
+                "android.permission.POST_NOTIFICATIONS",
+                "android.permission.ACCESS_FINE_LOCATION",
+            ]
+        );
+        assert_eq!(info.install_permissions.len(), 2);
+        let runtime = info.runtime_permissions();
+        assert_eq!(runtime.len(), 3);
+        assert_eq!(runtime[0].flags, ["USER_SET", "USER_SENSITIVE_WHEN_GRANTED", "USER_SENSITIVE_WHEN_DENIED"]);
+        assert_eq!(runtime[2].flags, ["RESTRICTION_INSTALLER_EXEMPT"]);
+        assert!(info.is_granted("android.permission.INTERNET"));
+        assert!(info.is_granted("android.permission.POST_NOTIFICATIONS"));
+        assert!(!info.is_granted("android.permission.WAKE_LOCK"));
+        // Granted in the work profile only, which doesn't count for the primary user.
+        assert!(!info.is_granted("android.permission.CAMERA"));
+        assert!(!info.is_granted("android.permission.READ_CONTACTS"));
+    }
+
+    #[test]
+    fn keeps_runtime_permissions_per_user() {
+        let info = PackageInfo::parse("com.example.app", DUMPSYS, "").unwrap();
+        fn grants(user: &UserState) -> Vec<(&str, bool)> {
+            user.runtime_permissions.iter().map(|p| (p.name.as_str(), p.granted)).collect()
+        }
+        assert_eq!(
+            grants(&info.users[0]),
+            [
+                ("android.permission.POST_NOTIFICATIONS", true),
+                ("android.permission.CAMERA", false),
+                ("android.permission.ACCESS_FINE_LOCATION", false),
+            ]
+        );
+        assert_eq!(grants(&info.users[1]), [("android.permission.POST_NOTIFICATIONS", false), ("android.permission.CAMERA", true)]);
+        assert_eq!(info.runtime_permissions(), info.users[0].runtime_permissions.as_slice());
+    }
+
+    #[test]
+    fn parses_users() {
+        let info = PackageInfo::parse("com.example.app", DUMPSYS, "").unwrap();
+        let states = info.users.iter().map(|u| (u.user_id, u.installed, u.hidden, u.suspended, u.stopped, u.enabled)).collect::<Vec<_>>();
+        assert_eq!(
+            states,
+            [(0, true, false, false, false, EnabledState::Default), (10, true, false, false, true, EnabledState::DisabledUser)]
+        );
+        assert_eq!(info.primary_user().map(|u| u.user_id), Some(0));
+        assert!(info.primary_user().unwrap().enabled.is_enabled());
+    }
+
+    #[test]
+    fn finds_base_apk_among_splits() {
+        let info = PackageInfo::parse("com.example.app", DUMPSYS, PM_PATH).unwrap();
+        assert_eq!(info.base_apk.as_deref(), Some("/data/app/~~Xk3pQ9w==/com.example.app-Fq1bA2c==/base.apk"));
+        assert_eq!(info.split_apks.len(), 2);
+        assert!(info.split_apks[0].ends_with("/split_config.arm64_v8a.apk"));
+
+        // Older releases keep single-APK apps at /data/app/<package>-1.apk.
+        let info = PackageInfo::parse("com.example.app", DUMPSYS, "package:/data/app/com.example.app-1.apk\n").unwrap();
+        assert_eq!(info.base_apk.as_deref(), Some("/data/app/com.example.app-1.apk"));
+        assert!(info.split_apks.is_empty());
+    }
+
+    #[test]
+    fn handles_older_and_missing_packages() {
+        // Android 7: no minSdk, no installer, and a single user.
+        let dumpsys = "\
+Packages:
+  Package [com.example.legacy] (2b4f6a1):
+    userId=10087
+    versionCode=12 targetSdk=23
+    versionName=1.2
+    installerPackageName=null
+    User 0: ceDataInode=40961 installed=true hidden=false suspended=false stopped=true notLaunched=false enabled=2
+";
+        let info = PackageInfo::parse("com.example.legacy", dumpsys, "").unwrap();
+        assert_eq!(info.version_code, "12");
+        assert_eq!(info.min_sdk, None);
+        assert_eq!(info.target_sdk, Some(23));
+        assert_eq!(info.installer, None);
+        assert_eq!(info.base_apk, None);
+        assert_eq!(info.primary_user().unwrap().enabled, EnabledState::Disabled);
+
+        // A package whose name is a prefix of another one's isn't confused with it.
+        assert!(PackageInfo::parse("com.example", DUMPSYS, "").is_none());
+        assert!(PackageInfo::parse("com.example.app", "Unable to find package: com.example.app\n", "").is_none());
+    }
+}
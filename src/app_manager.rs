@@ -1,8 +1,15 @@
+use std::path::PathBuf;
 use dioxus::prelude::*;
 use rfd::AsyncFileDialog;
-use crate::adb::{adb_push, adb_list_packages};
+use crate::adb::adb_list_packages;
 use crate::app::AppState;
+use crate::app_details::AppDetails;
+use crate::installer::{install_packages, InstallEvent, InstallOptions, INSTALLABLE_EXTENSIONS};
+use crate::transfers::{TransferProgressBar, TransferQueue};
 use crate::settings::AdbStudioSettings;
+use crate::error::AdbError;
+use crate::ui::ErrorNotice;
+use tokio_util::sync::CancellationToken;
 
 #[component]
 pub fn AppManager() -> Element {
@@ -11,10 +18,14 @@ pub fn AppManager() -> Element {
     let mut selected_package = use_signal(|| None::<String>);
     let mut search_query = use_signal(String::new);
     let mut is_loading = use_signal(|| false);
-    let mut install_progress = use_signal(|| 0);
+    let mut install_transfer = use_signal(|| None::<u64>);
+    let mut install_cancel = use_signal(|| None::<CancellationToken>);
+    let mut install_options = use_signal(InstallOptions::default);
     let mut alert_message = use_signal(|| String::new());
+    let mut error = use_signal(|| None::<AdbError>);
     let app_state = use_context::<Signal<AppState>>();
     let settings = use_context::<Signal<AdbStudioSettings>>();
+    let transfers = use_context::<Signal<TransferQueue>>();
 
     let load_packages = move || {
         spawn(async move {
@@ -27,7 +38,7 @@ pub fn AppManager() -> Element {
                             line.strip_prefix("package:").map(|s| s.to_string())
                         }).collect());
                     },
-                    Err(e) => logs.set(e),
+                    Err(e) => error.set(Some(e)),
                 }
             }
         });
@@ -52,20 +63,14 @@ pub fn AppManager() -> Element {
         }
         div {
             h2 { "App Manager" }
-            if install_progress() > 0 {
+            if let Some(transfer) = install_transfer().and_then(|id| transfers.read().get(id).cloned()) {
                 div {
                     style: "margin: 16px 0; padding: 16px; background: var(--card-bg); border: 1px solid var(--border-color); border-radius: 8px; max-width: 100%;",
-                    h3 { style: "margin: 0 0 12px 0; font-size: 1rem;", "Installing APK" }
-                    div {
-                        style: "background: var(--tertiary-bg); border-radius: 6px; height: 24px; overflow: hidden;",
-                        div {
-                            style: format!("background: var(--accent-color); height: 100%; width: {}%; transition: width 0.3s ease;", install_progress()),
-                        }
-                    }
-                    div {
-                        style: "text-align: center; margin-top: 8px; font-size: 0.875rem; color: var(--secondary-text); font-weight: 500;",
-                        "{install_progress()}%"
+                    h3 {
+                        style: "margin: 0 0 12px 0; font-size: 1rem;",
+                        if transfer.is_running() { "Pushing APK" } else { "Installing APK" }
                     }
+                    TransferProgressBar { transfer }
                 }
             }
             div {
@@ -77,68 +82,64 @@ pub fn AppManager() -> Element {
                             logs.set("Please select a device first".to_string());
                             return;
                         }
-                        let app_state = app_state.clone();
-                        let settings = settings.clone();
-                        let mut logs = logs.clone();
-                        let mut is_loading = is_loading.clone();
-                        let mut install_progress = install_progress.clone();
                         let load_packages_fn = load_packages.clone();
                         spawn(async move {
                             let device = app_state.read().selected_device.clone();
-                            if let Some(device) = device {
-                                if let Some(file) = AsyncFileDialog::new().add_filter("apk", &["apk"]).pick_file().await {
-                                    is_loading.set(true);
-                                    install_progress.set(10);
-                                    logs.set("Pushing APK to device...".to_string());
-                                    let settings_clone = settings.read().clone();
-                                    let apk_path = file.path().to_str().unwrap();
-                                    let remote_path = "/data/local/tmp/temp.apk";
-                                    
-                                    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-                                    install_progress.set(30);
-                                    match adb_push(&settings_clone, &device.serial, apk_path, remote_path).await {
-                                        Ok(_) => {
-                                            install_progress.set(60);
-                                            logs.set("Installing APK...".to_string());
-                                            let install_cmd = format!("pm install -r {}", remote_path);
-                                            match crate::adb::adb_shell(&settings_clone, &install_cmd).await {
-                                                Ok(output) => {
-                                                    install_progress.set(100);
-                                                    if output.contains("Success") {
-                                                        logs.set("APK installed successfully!".to_string());
-                                                        load_packages_fn();
-                                                        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-                                                        install_progress.set(0);
-                                                        alert_message.set("APK installed successfully!".to_string());
-                                                        let mut alert_clone = alert_message.clone();
-                                                        spawn(async move {
-                                                            tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
-                                                            alert_clone.set(String::new());
-                                                        });
-                                                    } else {
-                                                        logs.set(format!("Installation failed: {}", output));
-                                                        install_progress.set(0);
-                                                    }
-                                                },
-                                                Err(e) => {
-                                                    install_progress.set(0);
-                                                    logs.set(format!("Installation failed: {}", e));
-                                                }
-                                            }
-                                        },
-                                        Err(e) => {
-                                            install_progress.set(0);
-                                            logs.set(format!("Push failed: {}", e));
-                                        }
-                                    }
-                                    is_loading.set(false);
+                            let Some(device) = device else { return };
+                            let Some(files) = AsyncFileDialog::new()
+                                .add_filter("Android package", &INSTALLABLE_EXTENSIONS)
+                                .pick_files()
+                                .await
+                            else {
+                                return;
+                            };
+                            let paths: Vec<PathBuf> = files.iter().map(|f| f.path().to_path_buf()).collect();
+                            error.set(None);
+                            is_loading.set(true);
+                            let settings_clone = settings.read().clone();
+                            let cancel = CancellationToken::new();
+                            install_cancel.set(Some(cancel.clone()));
+                            let result = install_packages(transfers, &settings_clone, &device.serial, &paths, &install_options(), &cancel, |event| match event {
+                                InstallEvent::Preparing => logs.set("Preparing packages...".to_string()),
+                                InstallEvent::Pushing { transfer, index, count } => {
+                                    install_transfer.set(Some(transfer));
+                                    logs.set(format!("Pushing file {} of {} to device...", index + 1, count));
+                                }
+                                InstallEvent::Installing => logs.set("Installing...".to_string()),
+                            })
+                            .await;
+                            install_transfer.set(None);
+                            match result {
+                                Ok(_) => {
+                                    logs.set("APK installed successfully!".to_string());
+                                    load_packages_fn();
+                                    alert_message.set("APK installed successfully!".to_string());
+                                    spawn(async move {
+                                        tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
+                                        alert_message.set(String::new());
+                                    });
+                                }
+                                Err(AdbError::Cancelled) => logs.set("Installation cancelled".to_string()),
+                                Err(e) => {
+                                    logs.set("Installation failed".to_string());
+                                    error.set(Some(e));
                                 }
                             }
+                            install_cancel.set(None);
+                            is_loading.set(false);
                         });
                     },
                     i { class: "fas fa-download" }
                     if is_loading() { "Installing..." } else { "Install APK" }
                 }
+                if let Some(cancel) = install_cancel() {
+                    button {
+                        class: "btn btn-secondary",
+                        onclick: move |_| cancel.cancel(),
+                        i { class: "fas fa-times" }
+                        "Cancel Install"
+                    }
+                }
                 button {
                     class: "btn btn-secondary",
                     disabled: !has_device || selected_package().is_none(),
@@ -156,6 +157,7 @@ pub fn AppManager() -> Element {
                             spawn(async move {
                                 let device = app_state.read().selected_device.clone();
                                 if let Some(device) = device {
+                                    error.set(None);
                                     logs.set(format!("Uninstalling {}...", pkg_name));
                                     let settings_clone = settings.read().clone();
                                     match crate::adb::adb_uninstall(&settings_clone, &device.serial, &pkg_name).await {
@@ -171,7 +173,10 @@ pub fn AppManager() -> Element {
                                                 alert_clone.set(String::new());
                                             });
                                         },
-                                        Err(e) => logs.set(format!("Error: {}", e)),
+                                        Err(e) => {
+                                            logs.set(format!("Failed to uninstall {}", pkg_name));
+                                            error.set(Some(e));
+                                        },
                                     }
                                 }
                             });
@@ -196,6 +201,55 @@ pub fn AppManager() -> Element {
                     "Refresh" 
                 }
             }
+            div {
+                style: "display: flex; flex-wrap: wrap; align-items: center; gap: 16px; margin-bottom: 12px; font-size: 0.875rem;",
+                label { style: "display: flex; align-items: center;",
+                    "Replace existing"
+                    input {
+                        r#type: "checkbox",
+                        checked: install_options.read().replace,
+                        onchange: move |e| install_options.write().replace = e.checked(),
+                    }
+                }
+                label { style: "display: flex; align-items: center;",
+                    "Allow downgrade"
+                    input {
+                        r#type: "checkbox",
+                        checked: install_options.read().downgrade,
+                        onchange: move |e| install_options.write().downgrade = e.checked(),
+                    }
+                }
+                label { style: "display: flex; align-items: center;",
+                    "Grant all permissions"
+                    input {
+                        r#type: "checkbox",
+                        checked: install_options.read().grant_all,
+                        onchange: move |e| install_options.write().grant_all = e.checked(),
+                    }
+                }
+                label { style: "display: flex; align-items: center;",
+                    "Allow test packages"
+                    input {
+                        r#type: "checkbox",
+                        checked: install_options.read().allow_test,
+                        onchange: move |e| install_options.write().allow_test = e.checked(),
+                    }
+                }
+                label { style: "display: flex; align-items: center; gap: 8px;",
+                    "User"
+                    input {
+                        r#type: "number",
+                        min: "0",
+                        placeholder: "Current",
+                        style: "width: 100px;",
+                        value: install_options.read().user.map(|u| u.to_string()).unwrap_or_default(),
+                        oninput: move |e| install_options.write().user = e.value().trim().parse().ok(),
+                    }
+                }
+            }
+            if let Some(e) = error() {
+                ErrorNotice { error: e }
+            }
             div {
                 style: "margin-bottom: 12px;",
                 input {
@@ -209,25 +263,35 @@ pub fn AppManager() -> Element {
                 }
             }
             div {
-                h3 { "Installed Packages ({filtered_packages.len()})" }
-                if filtered_packages.is_empty() && !packages().is_empty() {
-                    div {
-                        style: "padding: 20px; text-align: center; color: var(--secondary-text); font-size: 0.875rem;",
-                        "No packages found matching \"{search_query}\""
+                style: "display: flex; gap: 16px; align-items: flex-start;",
+                div {
+                    style: "flex: 1; min-width: 0;",
+                    h3 { "Installed Packages ({filtered_packages.len()})" }
+                    if filtered_packages.is_empty() && !packages().is_empty() {
+                        div {
+                            style: "padding: 20px; text-align: center; color: var(--secondary-text); font-size: 0.875rem;",
+                            "No packages found matching \"{search_query}\""
+                        }
                     }
-                }
-                ul {
-                    style: "list-style: none; padding: 0; padding-bottom: 50px; max-height: calc(100vh - 280px); overflow-y: auto;",
-                    for package in filtered_packages {
-                        li { 
-                            class: if selected_package() == Some(package.clone()) { "package-item selected" } else { "package-item" },
-                            onclick: move |_| {
-                                selected_package.set(Some(package.clone()));
-                            },
-                            "{package}" 
+                    ul {
+                        style: "list-style: none; padding: 0; padding-bottom: 50px; max-height: calc(100vh - 280px); overflow-y: auto;",
+                        for package in filtered_packages {
+                            li { 
+                                class: if selected_package() == Some(package.clone()) { "package-item selected" } else { "package-item" },
+                                onclick: move |_| {
+                                    selected_package.set(Some(package.clone()));
+                                },
+                                "{package}" 
+                            }
                         }
                     }
                 }
+                if let (Some(device), Some(package)) = (app_state.read().selected_device.clone(), selected_package()) {
+                    div {
+                        style: "flex: 1.4; min-width: 0; max-height: calc(100vh - 220px); overflow-y: auto;",
+                        AppDetails { key: "{package}", serial: device.serial, package }
+                    }
+                }
             }
         }
     }
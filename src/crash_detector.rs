@@ -0,0 +1,558 @@
+use std::collections::HashSet;
+use dioxus::prelude::*;
+use crate::logcat::{LogEntry, LogLevel};
+
+/// Stack traces longer than this are cut off; a runaway trace shouldn't grow without bound.
+const MAX_TRACE_LINES: usize = 500;
+const MAX_INCIDENTS: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IncidentKind {
+    JavaCrash,
+    NativeCrash,
+    Anr,
+}
+
+impl IncidentKind {
+    /// Recognizes the first line of a crash or ANR report.
+    fn detect(entry: &LogEntry) -> Option<Self> {
+        if entry.level < LogLevel::Error {
+            return None;
+        }
+        match entry.tag.as_str() {
+            "AndroidRuntime" if entry.message.starts_with("FATAL EXCEPTION") => Some(IncidentKind::JavaCrash),
+            "libc" if entry.message.starts_with("Fatal signal") => Some(IncidentKind::NativeCrash),
+            "DEBUG" if is_tombstone_header(&entry.message) => Some(IncidentKind::NativeCrash),
+            "ActivityManager" if entry.message.starts_with("ANR in ") => Some(IncidentKind::Anr),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            IncidentKind::JavaCrash => "Java crash",
+            IncidentKind::NativeCrash => "Native crash",
+            IncidentKind::Anr => "ANR",
+        }
+    }
+
+    pub fn icon(&self) -> &'static str {
+        match self {
+            IncidentKind::JavaCrash => "fas fa-bug",
+            IncidentKind::NativeCrash => "fas fa-skull-crossbones",
+            IncidentKind::Anr => "fas fa-hourglass-half",
+        }
+    }
+}
+
+fn is_tombstone_header(message: &str) -> bool {
+    message.starts_with("*** *** ***")
+}
+
+/// One crash or ANR, with every line of its report.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Incident {
+    pub id: u64,
+    pub kind: IncidentKind,
+    pub source: String,
+    pub package: Option<String>,
+    pub pid: u32,
+    pub timestamp: String,
+    pub summary: String,
+    pub lines: Vec<String>,
+    tombstone: bool,
+}
+
+impl Incident {
+    fn new(id: u64, kind: IncidentKind, source: &str, entry: &LogEntry) -> Self {
+        let mut incident = Incident {
+            id,
+            kind,
+            source: source.to_string(),
+            package: None,
+            pid: entry.pid,
+            timestamp: entry.timestamp.clone(),
+            summary: String::new(),
+            lines: Vec::new(),
+            tombstone: false,
+        };
+        incident.append(&entry.message);
+        incident
+    }
+
+    /// Adds a line to the report, picking the package, pid and a one-line summary out of the
+    /// lines that carry them. Returns `false` once the report is full.
+    fn append(&mut self, message: &str) -> bool {
+        if self.lines.len() >= MAX_TRACE_LINES {
+            return false;
+        }
+        let line = message.trim_end();
+        match self.kind {
+            IncidentKind::JavaCrash => {
+                // Process: com.example.app, PID: 1234
+                if let Some(rest) = line.strip_prefix("Process: ") {
+                    let (package, pid) = rest.split_once(", PID: ").unwrap_or((rest, ""));
+                    self.package = Some(package.trim().to_string());
+                    if let Ok(pid) = pid.trim().parse() {
+                        self.pid = pid;
+                    }
+                } else if self.summary.is_empty() && !line.starts_with("FATAL EXCEPTION") && !line.trim().is_empty() {
+                    self.summary = line.trim().to_string();
+                }
+            }
+            IncidentKind::NativeCrash => {
+                if let Some(rest) = line.strip_prefix("Fatal signal ") {
+                    // Fatal signal 11 (SIGSEGV), ... in tid 1234 (RenderThread), pid 1234 (com.example.app)
+                    self.summary = format!("signal {}", rest.split(" in tid ").next().unwrap_or(rest));
+                    if let Some((_, process)) = rest.rsplit_once(", pid ") {
+                        let (pid, name) = process.split_once(' ').unwrap_or((process, ""));
+                        if let Ok(pid) = pid.parse() {
+                            self.pid = pid;
+                        }
+                        let name = name.trim().trim_start_matches('(').trim_end_matches(')');
+                        if !name.is_empty() {
+                            self.package = Some(name.to_string());
+                        }
+                    }
+                } else if line.starts_with("signal ") {
+                    self.summary = line.to_string();
+                } else if let Some(rest) = line.strip_prefix("pid: ") {
+                    // pid: 1234, tid: 1234, name: RenderThread  >>> com.example.app <<<
+                    if let Ok(pid) = rest.split(',').next().unwrap_or_default().trim().parse() {
+                        self.pid = pid;
+                    }
+                    if let Some((_, name)) = line.split_once(">>> ") {
+                        self.package = Some(name.trim_end_matches("<<<").trim().to_string());
+                    }
+                }
+            }
+            IncidentKind::Anr => {
+                if let Some(rest) = line.strip_prefix("ANR in ") {
+                    self.package = rest.split_whitespace().next().map(str::to_string);
+                } else if let Some(pid) = line.strip_prefix("PID: ") {
+                    if let Ok(pid) = pid.trim().parse() {
+                        self.pid = pid;
+                    }
+                } else if let Some(reason) = line.strip_prefix("Reason: ") {
+                    self.summary = reason.to_string();
+                }
+            }
+        }
+        self.lines.push(line.to_string());
+        true
+    }
+
+    pub fn title(&self) -> String {
+        match &self.package {
+            Some(package) => format!("{} in {}", self.kind.label(), package),
+            None => format!("{} (pid {})", self.kind.label(), self.pid),
+        }
+    }
+
+    pub fn trace(&self) -> String {
+        self.lines.join("\n")
+    }
+}
+
+/// A report still being written: further lines from the same thread under the same tag
+/// belong to it.
+struct OpenReport {
+    pid: u32,
+    tid: u32,
+    tag: String,
+    incident: u64,
+    /// A tombstone stays hidden until its `pid:` line says which crash it belongs to.
+    awaiting_pid: bool,
+}
+
+/// Watches a stream of log entries for crash and ANR reports and assembles each one into an
+/// [`Incident`].
+pub struct CrashDetector {
+    source: String,
+    open: Vec<OpenReport>,
+    building: Vec<Incident>,
+    closed: Vec<Incident>,
+    dirty: HashSet<u64>,
+    next_id: u64,
+}
+
+impl CrashDetector {
+    /// `first_id` keeps incident ids unique across detectors feeding the same [`CrashLog`].
+    pub fn new(source: &str, first_id: u64) -> Self {
+        CrashDetector {
+            source: source.to_string(),
+            open: Vec::new(),
+            building: Vec::new(),
+            closed: Vec::new(),
+            dirty: HashSet::new(),
+            next_id: first_id,
+        }
+    }
+
+    pub fn observe(&mut self, entry: &LogEntry) {
+        let kind = IncidentKind::detect(entry);
+        if let Some(pos) = self.open.iter().position(|r| r.pid == entry.pid && r.tid == entry.tid) {
+            let report = &self.open[pos];
+            // The thread moving on to another tag, or starting a new report, ends the current one.
+            if report.tag == entry.tag && kind.is_none() {
+                let (id, awaiting_pid) = (report.incident, report.awaiting_pid);
+                if let Some(incident) = self.building.iter_mut().find(|i| i.id == id) {
+                    if incident.append(&entry.message) {
+                        if !awaiting_pid {
+                            self.dirty.insert(id);
+                        } else if entry.message.starts_with("pid: ") {
+                            self.match_tombstone(pos);
+                        }
+                        return;
+                    }
+                }
+            }
+            self.close(pos);
+        }
+        let Some(kind) = kind else {
+            return;
+        };
+
+        let id = self.next_id;
+        self.next_id += 1;
+        let tombstone = entry.tag == "DEBUG";
+        let mut incident = Incident::new(id, kind, &self.source, entry);
+        incident.tombstone = tombstone;
+        self.building.push(incident);
+        self.open.push(OpenReport { pid: entry.pid, tid: entry.tid, tag: entry.tag.clone(), incident: id, awaiting_pid: tombstone });
+        if !tombstone {
+            self.dirty.insert(id);
+        }
+    }
+
+    /// The tombstone that debuggerd writes under DEBUG usually follows libc's one-line report of
+    /// the same crash. Once the tombstone's `pid:` line names the crashed process, it completes
+    /// libc's incident for that pid, or becomes an incident of its own if there is none.
+    fn match_tombstone(&mut self, pos: usize) {
+        let report = &mut self.open[pos];
+        report.awaiting_pid = false;
+        let id = report.incident;
+        let Some(index) = self.building.iter().position(|i| i.id == id) else {
+            return;
+        };
+        let pid = self.building[index].pid;
+        let crash = self.building.iter().position(|i| i.kind == IncidentKind::NativeCrash && !i.tombstone && i.pid == pid);
+        let Some(crash) = crash else {
+            self.dirty.insert(id);
+            return;
+        };
+        let tombstone = self.building.remove(index);
+        let incident = &mut self.building[if crash > index { crash - 1 } else { crash }];
+        incident.tombstone = true;
+        for line in &tombstone.lines {
+            incident.append(line);
+        }
+        self.open[pos].incident = incident.id;
+        self.dirty.insert(incident.id);
+    }
+
+    fn close(&mut self, pos: usize) {
+        let report = self.open.remove(pos);
+        if self.open.iter().any(|r| r.incident == report.incident) {
+            return;
+        }
+        if let Some(index) = self.building.iter().position(|i| i.id == report.incident) {
+            self.closed.push(self.building.remove(index));
+        }
+    }
+
+    /// Returns every incident that was started or extended since the last call.
+    pub fn take_updates(&mut self) -> Vec<Incident> {
+        let dirty = std::mem::take(&mut self.dirty);
+        let mut updates = self.closed.drain(..).filter(|i| dirty.contains(&i.id)).collect::<Vec<_>>();
+        updates.extend(self.building.iter().filter(|i| dirty.contains(&i.id)).cloned());
+        updates.sort_by_key(|i| i.id);
+        updates
+    }
+}
+
+/// Every incident seen so far, shared through context so the toolbar and the Crashes panel can
+/// show them while the log viewer records them.
+#[derive(Clone, Default)]
+pub struct CrashLog {
+    pub incidents: Vec<Incident>,
+    next_id: u64,
+    seen: u64,
+}
+
+impl CrashLog {
+    /// Reserves a block of ids for a new detector.
+    pub fn detector(&mut self, source: &str) -> CrashDetector {
+        // Live logcats rarely produce more than a handful of incidents; leave plenty of room.
+        let first_id = self.next_id + 1;
+        self.next_id += 1_000_000;
+        CrashDetector::new(source, first_id)
+    }
+
+    /// Adds new incidents and replaces ones that gained more lines.
+    pub fn record(&mut self, updates: Vec<Incident>) {
+        for incident in updates {
+            match self.incidents.iter_mut().find(|i| i.id == incident.id) {
+                Some(existing) => *existing = incident,
+                None => self.incidents.push(incident),
+            }
+        }
+        if self.incidents.len() > MAX_INCIDENTS {
+            let excess = self.incidents.len() - MAX_INCIDENTS;
+            self.incidents.drain(..excess);
+        }
+    }
+
+    /// Incidents that arrived after the Crashes panel was last opened.
+    pub fn unseen(&self) -> usize {
+        self.incidents.iter().filter(|i| i.id > self.seen).count()
+    }
+
+    pub fn mark_seen(&mut self) {
+        self.seen = self.incidents.iter().map(|i| i.id).max().unwrap_or(self.seen);
+    }
+
+    pub fn clear(&mut self) {
+        self.mark_seen();
+        self.incidents.clear();
+    }
+}
+
+#[component]
+pub fn CrashPanel() -> Element {
+    let mut crash_log = use_context::<Signal<CrashLog>>();
+    let mut expanded = use_signal(|| None::<u64>);
+    let incidents = crash_log.read().incidents.iter().rev().cloned().collect::<Vec<_>>();
+    let count = incidents.len();
+    let is_empty = incidents.is_empty();
+
+    // Opening the panel (or new incidents arriving while it is open) counts as seeing them.
+    use_effect(use_reactive!(|count| {
+        let _ = count;
+        if crash_log.peek().unseen() > 0 {
+            crash_log.write().mark_seen();
+        }
+    }));
+
+    rsx! {
+        div {
+            h2 { "Crashes" }
+            button {
+                class: "btn btn-secondary",
+                disabled: is_empty,
+                onclick: move |_| crash_log.write().clear(),
+                i { class: "fas fa-broom" }
+                "Clear"
+            }
+            if is_empty {
+                p { style: "color: var(--secondary-text);",
+                    "No crashes or ANRs detected. Incidents appear here while the log viewer is reading logcat or a saved log."
+                }
+            }
+            for incident in incidents {
+                div {
+                    key: "{incident.id}",
+                    class: "device-info",
+                    style: "margin-top: 12px; cursor: pointer;",
+                    onclick: move |_| {
+                        let id = incident.id;
+                        expanded.set(if expanded() == Some(id) { None } else { Some(id) });
+                    },
+                    div {
+                        style: "display: flex; align-items: center; gap: 8px;",
+                        i { class: incident.kind.icon(), style: "color: var(--error-color);" }
+                        span { style: "font-weight: 600;", "{incident.title()}" }
+                        span { style: "margin-left: auto; color: var(--secondary-text); font-size: 0.8rem;",
+                            "{incident.timestamp} · {incident.source}"
+                        }
+                    }
+                    if !incident.summary.is_empty() {
+                        div { style: "margin-top: 4px; font-size: 0.85rem; color: var(--secondary-text); overflow: hidden; text-overflow: ellipsis; white-space: nowrap;",
+                            "{incident.summary}"
+                        }
+                    }
+                    if expanded() == Some(incident.id) {
+                        div {
+                            class: "log-output",
+                            style: "height: auto; max-height: 480px;",
+                            onclick: move |e| e.stop_propagation(),
+                            pre { style: "color: var(--primary-text);", "{incident.trace()}" }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logcat::parse_threadtime;
+
+    const JAVA_CRASH: &str = "\
+10-18 10:00:00.512  8243  8243 E AndroidRuntime: FATAL EXCEPTION: main
+10-18 10:00:00.512  8243  8243 E AndroidRuntime: Process: com.example.app, PID: 8243
+10-18 10:00:00.512  8243  8243 E AndroidRuntime: java.lang.RuntimeException: Unable to start activity ComponentInfo{com.example.app/com.example.app.MainActivity}: java.lang.NullPointerException
+10-18 10:00:00.512  8243  8243 E AndroidRuntime: \tat android.app.ActivityThread.performLaunchActivity(ActivityThread.java:3449)
+10-18 10:00:00.513  1502  1520 W ActivityTaskManager:   Force finishing activity com.example.app/.MainActivity
+10-18 10:00:00.512  8243  8243 E AndroidRuntime: \tat android.app.ActivityThread.handleLaunchActivity(ActivityThread.java:3601)
+10-18 10:00:00.530  8243  8243 I Process : Sending signal. PID: 8243 SIG: 9
+";
+
+    const LIBC_REPORT: &str = "\
+10-18 10:00:02.100  9120  9151 F libc    : Fatal signal 11 (SIGSEGV), code 1 (SEGV_MAPERR), fault addr 0x0 in tid 9151 (RenderThread), pid 9120 (com.example.game)
+";
+
+    const TOMBSTONE: &str = "\
+10-18 10:00:02.180  9160  9160 F DEBUG   : *** *** *** *** *** *** *** *** *** *** *** *** *** *** *** ***
+10-18 10:00:02.180  9160  9160 F DEBUG   : Build fingerprint: 'google/sunfish/sunfish:12/SP1A.210812.015/7679548:user/release-keys'
+10-18 10:00:02.180  9160  9160 F DEBUG   : Revision: '0'
+10-18 10:00:02.180  9160  9160 F DEBUG   : ABI: 'arm64'
+10-18 10:00:02.180  9160  9160 F DEBUG   : pid: 9120, tid: 9151, name: RenderThread  >>> com.example.game <<<
+10-18 10:00:02.180  9160  9160 F DEBUG   : uid: 10234
+10-18 10:00:02.180  9160  9160 F DEBUG   : signal 11 (SIGSEGV), code 1 (SEGV_MAPERR), fault addr 0x0000000000000000
+10-18 10:00:02.180  9160  9160 F DEBUG   : backtrace:
+10-18 10:00:02.180  9160  9160 F DEBUG   :       #00 pc 000000000004f8a4  /data/app/com.example.game/lib/arm64/libgame.so (Engine::tick()+52)
+";
+
+    const ANR: &str = "\
+10-18 10:00:05.004  1502  8810 E ActivityManager: ANR in com.example.app (com.example.app/.MainActivity)
+10-18 10:00:05.004  1502  8810 E ActivityManager: PID: 8243
+10-18 10:00:05.004  1502  8810 E ActivityManager: Reason: Input dispatching timed out (Waiting to send non-key event because the touched window has not finished processing certain input events)
+10-18 10:00:05.004  1502  8810 E ActivityManager: Load: 8.44 / 7.91 / 6.02
+";
+
+    fn entries(log: &str) -> Vec<LogEntry> {
+        log.lines().map(|line| parse_threadtime(line).expect(line)).collect()
+    }
+
+    fn detect(log: &str) -> Vec<Incident> {
+        let mut detector = CrashDetector::new("emulator-5554", 1);
+        let mut crash_log = CrashLog::default();
+        for entry in entries(log) {
+            detector.observe(&entry);
+            crash_log.record(detector.take_updates());
+        }
+        crash_log.incidents
+    }
+
+    #[test]
+    fn detects_report_headers() {
+        let kinds = |log: &str| entries(log).iter().map(IncidentKind::detect).collect::<Vec<_>>();
+        assert_eq!(kinds(JAVA_CRASH)[..2], [Some(IncidentKind::JavaCrash), None]);
+        assert_eq!(kinds(LIBC_REPORT), [Some(IncidentKind::NativeCrash)]);
+        assert_eq!(kinds(TOMBSTONE)[..2], [Some(IncidentKind::NativeCrash), None]);
+        assert_eq!(kinds(ANR)[..2], [Some(IncidentKind::Anr), None]);
+
+        // Headers below error level are the app logging about a crash, not the crash itself.
+        let quoted = "10-18 10:00:00.512  8243  8243 I AndroidRuntime: FATAL EXCEPTION: main";
+        assert_eq!(IncidentKind::detect(&parse_threadtime(quoted).unwrap()), None);
+        let other_tag = "10-18 10:00:00.512  8243  8243 E MyApp   : ANR in com.example.app";
+        assert_eq!(IncidentKind::detect(&parse_threadtime(other_tag).unwrap()), None);
+    }
+
+    #[test]
+    fn appends_java_crash_lines() {
+        let lines = entries(JAVA_CRASH);
+        let mut incident = Incident::new(1, IncidentKind::JavaCrash, "emulator-5554", &lines[0]);
+        assert!(incident.append(&lines[1].message));
+        assert!(incident.append(&lines[2].message));
+        assert_eq!(incident.package.as_deref(), Some("com.example.app"));
+        assert_eq!(incident.pid, 8243);
+        assert!(incident.summary.starts_with("java.lang.RuntimeException: Unable to start activity"));
+        assert_eq!(incident.title(), "Java crash in com.example.app");
+        assert_eq!(incident.lines.len(), 3);
+    }
+
+    #[test]
+    fn appends_native_crash_lines() {
+        let libc = entries(LIBC_REPORT);
+        let incident = Incident::new(1, IncidentKind::NativeCrash, "emulator-5554", &libc[0]);
+        assert_eq!(incident.pid, 9120);
+        assert_eq!(incident.package.as_deref(), Some("com.example.game"));
+        assert_eq!(incident.summary, "signal 11 (SIGSEGV), code 1 (SEGV_MAPERR), fault addr 0x0");
+
+        let tombstone = entries(TOMBSTONE);
+        let mut incident = Incident::new(2, IncidentKind::NativeCrash, "emulator-5554", &tombstone[0]);
+        assert_eq!(incident.pid, 9160);
+        for entry in &tombstone[1..] {
+            assert!(incident.append(&entry.message));
+        }
+        assert_eq!(incident.pid, 9120);
+        assert_eq!(incident.package.as_deref(), Some("com.example.game"));
+        assert_eq!(incident.summary, "signal 11 (SIGSEGV), code 1 (SEGV_MAPERR), fault addr 0x0000000000000000");
+    }
+
+    #[test]
+    fn appends_anr_lines_and_stops_when_full() {
+        let lines = entries(ANR);
+        let mut incident = Incident::new(1, IncidentKind::Anr, "emulator-5554", &lines[0]);
+        for entry in &lines[1..] {
+            incident.append(&entry.message);
+        }
+        assert_eq!(incident.package.as_deref(), Some("com.example.app"));
+        assert_eq!(incident.pid, 8243);
+        assert!(incident.summary.starts_with("Input dispatching timed out"));
+
+        while incident.lines.len() < MAX_TRACE_LINES {
+            assert!(incident.append("  at frame"));
+        }
+        assert!(!incident.append("  at one frame too many"));
+        assert_eq!(incident.lines.len(), MAX_TRACE_LINES);
+    }
+
+    #[test]
+    fn assembles_interleaved_java_crash() {
+        let incidents = detect(JAVA_CRASH);
+        assert_eq!(incidents.len(), 1);
+        assert_eq!(incidents[0].kind, IncidentKind::JavaCrash);
+        // The ActivityTaskManager line from another process doesn't interrupt the trace.
+        assert_eq!(incidents[0].lines.len(), 5);
+        assert!(incidents[0].trace().ends_with("(ActivityThread.java:3601)"));
+    }
+
+    #[test]
+    fn tombstone_completes_the_libc_report() {
+        let incidents = detect(&format!("{}{}", LIBC_REPORT, TOMBSTONE));
+        assert_eq!(incidents.len(), 1, "{:#?}", incidents);
+        assert_eq!(incidents[0].pid, 9120);
+        assert_eq!(incidents[0].lines.len(), 1 + TOMBSTONE.lines().count());
+        assert!(incidents[0].trace().ends_with("(Engine::tick()+52)"));
+    }
+
+    #[test]
+    fn tombstone_matches_the_crash_with_its_pid() {
+        let second = "10-18 10:00:02.120  9300  9300 F libc    : Fatal signal 6 (SIGABRT), code -1 (SI_QUEUE) in tid 9300 (com.example.other), pid 9300 (com.example.other)\n";
+        let incidents = detect(&format!("{}{}{}", LIBC_REPORT, second, TOMBSTONE));
+        assert_eq!(incidents.len(), 2, "{:#?}", incidents);
+        assert_eq!(incidents[0].package.as_deref(), Some("com.example.game"));
+        assert_eq!(incidents[0].lines.len(), 1 + TOMBSTONE.lines().count());
+        assert_eq!(incidents[1].package.as_deref(), Some("com.example.other"));
+        assert_eq!(incidents[1].lines.len(), 1);
+    }
+
+    #[test]
+    fn tombstone_without_libc_report_stands_alone() {
+        let mut detector = CrashDetector::new("emulator-5554", 1);
+        let tombstone = entries(TOMBSTONE);
+        detector.observe(&tombstone[0]);
+        assert!(detector.take_updates().is_empty());
+        for entry in &tombstone[1..] {
+            detector.observe(entry);
+        }
+        let incidents = detector.take_updates();
+        assert_eq!(incidents.len(), 1);
+        assert_eq!(incidents[0].pid, 9120);
+        assert_eq!(incidents[0].title(), "Native crash in com.example.game");
+    }
+
+    #[test]
+    fn assembles_anr() {
+        let incidents = detect(&format!("{}{}", JAVA_CRASH, ANR));
+        assert_eq!(incidents.len(), 2);
+        assert_eq!(incidents[1].kind, IncidentKind::Anr);
+        assert_eq!(incidents[1].pid, 8243);
+        assert_eq!(incidents[1].lines.len(), 4);
+    }
+}
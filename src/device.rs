@@ -1,23 +1,280 @@
+use std::collections::HashMap;
 use serde::Deserialize;
+use crate::adb::adb_getprop;
+use crate::settings::AdbStudioSettings;
 
-#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
 pub struct Device {
     pub serial: String,
     pub status: String,
     pub model: String,
+    pub product: String,
+    pub device: String,
+    pub transport_id: Option<u32>,
+    pub usb: Option<String>,
+    pub props: Option<DeviceProps>,
 }
 
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+pub struct DeviceProps {
+    pub manufacturer: String,
+    pub model: String,
+    pub android_version: String,
+    pub sdk_level: String,
+    pub abi: String,
+    pub fingerprint: String,
+}
+
+impl Device {
+    /// The model when known; unauthorized and offline devices only report their serial.
+    pub fn name(&self) -> &str {
+        if self.model.is_empty() {
+            &self.serial
+        } else {
+            &self.model
+        }
+    }
+
+    /// `model (serial)` for lists where devices of the same model must be told apart; just the
+    /// serial when the model is unknown.
+    pub fn label(&self) -> String {
+        if self.model.is_empty() {
+            self.serial.clone()
+        } else {
+            format!("{} ({})", self.model, self.serial)
+        }
+    }
+
+    pub fn connection(&self) -> String {
+        if let Some(usb) = &self.usb {
+            format!("USB {}", usb)
+        } else if self.serial.contains(':') || self.serial.contains("._adb-tls-connect.") {
+            "Wi-Fi".to_string()
+        } else if self.serial.starts_with("emulator-") {
+            "Emulator".to_string()
+        } else {
+            String::new()
+        }
+    }
+}
+
+impl DeviceProps {
+    pub fn from_getprop(output: &str) -> Self {
+        let props = parse_getprop(output);
+        let get = |key: &str| props.get(key).cloned().unwrap_or_default();
+        DeviceProps {
+            manufacturer: get("ro.product.manufacturer"),
+            model: get("ro.product.model"),
+            android_version: get("ro.build.version.release"),
+            sdk_level: get("ro.build.version.sdk"),
+            abi: get("ro.product.cpu.abi"),
+            fingerprint: get("ro.build.fingerprint"),
+        }
+    }
+}
+
+/// Parses `adb devices -l`. The state column can contain spaces
+/// (`no permissions (missing udev rules? ...)`), so only `key:value` tokens are treated as attributes.
 pub fn parse_devices(output: &str) -> Vec<Device> {
+    parse_device_lines(output.lines().skip(1))
+}
+
+/// Same as `parse_devices` for payloads without the `List of devices attached` header,
+/// such as the snapshots sent by `host:track-devices-l`.
+pub fn parse_device_list(payload: &str) -> Vec<Device> {
+    parse_device_lines(payload.lines())
+}
+
+fn parse_device_lines<'a>(lines: impl Iterator<Item = &'a str>) -> Vec<Device> {
     let mut devices = Vec::new();
-    for line in output.lines().skip(1) {
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() >= 2 {
-            devices.push(Device {
-                serial: parts[0].to_string(),
-                status: parts[1].to_string(),
-                model: "".to_string(), // Model detection requires more commands
-            });
+    for line in lines {
+        let mut parts = line.split_whitespace();
+        let Some(serial) = parts.next() else {
+            continue;
+        };
+        let mut device = Device { serial: serial.to_string(), ..Default::default() };
+        let mut status = Vec::new();
+        for part in parts {
+            match part.split_once(':') {
+                Some(("usb", value)) => device.usb = Some(value.to_string()),
+                Some(("product", value)) => device.product = value.to_string(),
+                Some(("model", value)) => device.model = value.replace('_', " "),
+                Some(("device", value)) => device.device = value.to_string(),
+                Some(("transport_id", value)) => device.transport_id = value.parse().ok(),
+                _ => status.push(part),
+            }
         }
+        if status.is_empty() {
+            continue;
+        }
+        device.status = status.join(" ");
+        devices.push(device);
     }
     devices
 }
+
+/// Parses `getprop` output lines of the form `[key]: [value]`.
+pub fn parse_getprop(output: &str) -> HashMap<String, String> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once("]: [")?;
+            Some((key.trim_start_matches('[').to_string(), value.trim_end().trim_end_matches(']').to_string()))
+        })
+        .collect()
+}
+
+/// Parses `wm size`, preferring the override size when one is set.
+pub fn parse_wm_size(output: &str) -> Option<(u32, u32)> {
+    let value = |prefix: &str| {
+        output.lines().find_map(|line| line.trim().strip_prefix(prefix)).and_then(|size| {
+            let (w, h) = size.trim().split_once('x')?;
+            Some((w.trim().parse().ok()?, h.trim().parse().ok()?))
+        })
+    };
+    value("Override size:").or_else(|| value("Physical size:"))
+}
+
+/// Parses `wm density`, preferring the override density when one is set.
+pub fn parse_wm_density(output: &str) -> Option<u32> {
+    let value = |prefix: &str| output.lines().find_map(|line| line.trim().strip_prefix(prefix)?.trim().parse().ok());
+    value("Override density:").or_else(|| value("Physical density:"))
+}
+
+/// Build properties per serial, so repeated polls only query devices that are new or just came online.
+#[derive(Default)]
+pub struct DevicePropsCache {
+    props: HashMap<String, DeviceProps>,
+}
+
+impl DevicePropsCache {
+    pub async fn enrich(&mut self, settings: &AdbStudioSettings, devices: &mut [Device]) {
+        self.props.retain(|serial, _| devices.iter().any(|d| &d.serial == serial && d.status == "device"));
+        for device in devices.iter_mut().filter(|d| d.status == "device") {
+            if !self.props.contains_key(&device.serial) {
+                if let Ok(output) = adb_getprop(settings, &device.serial).await {
+                    self.props.insert(device.serial.clone(), DeviceProps::from_getprop(&output));
+                }
+            }
+            if let Some(props) = self.props.get(&device.serial) {
+                if !props.model.is_empty() {
+                    device.model = props.model.clone();
+                }
+                device.props = Some(props.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_adb_devices_l() {
+        let output = "\
+List of devices attached
+0A201JECB02458         device usb:1-4 product:sunfish model:Pixel_4a device:sunfish transport_id:3
+emulator-5554          device product:sdk_gphone64_x86_64 model:sdk_gphone64_x86_64 device:emu64xa transport_id:1
+192.168.1.20:41235     offline transport_id:5
+R58M42ABCDE            unauthorized usb:1-2 transport_id:4
+9B111FFAZ001           no permissions (missing udev rules? user is in the plugdev group); see [http://developer.android.com/tools/device.html] usb:1-3
+
+";
+        let devices = parse_devices(output);
+        assert_eq!(devices.len(), 5);
+
+        assert_eq!(devices[0].serial, "0A201JECB02458");
+        assert_eq!(devices[0].status, "device");
+        assert_eq!(devices[0].model, "Pixel 4a");
+        assert_eq!(devices[0].product, "sunfish");
+        assert_eq!(devices[0].device, "sunfish");
+        assert_eq!(devices[0].usb.as_deref(), Some("1-4"));
+        assert_eq!(devices[0].transport_id, Some(3));
+        assert_eq!(devices[0].connection(), "USB 1-4");
+
+        assert_eq!(devices[1].connection(), "Emulator");
+        assert_eq!(devices[2].status, "offline");
+        assert_eq!(devices[2].connection(), "Wi-Fi");
+
+        assert_eq!(devices[3].status, "unauthorized");
+        assert_eq!(devices[3].name(), "R58M42ABCDE");
+        assert_eq!(devices[3].label(), "R58M42ABCDE");
+        assert_eq!(devices[0].name(), "Pixel 4a");
+        assert_eq!(devices[0].label(), "Pixel 4a (0A201JECB02458)");
+
+        assert_eq!(
+            devices[4].status,
+            "no permissions (missing udev rules? user is in the plugdev group); see [http://developer.android.com/tools/device.html]"
+        );
+        assert_eq!(devices[4].usb.as_deref(), Some("1-3"));
+    }
+
+    #[test]
+    fn parses_empty_device_list() {
+        assert!(parse_devices("List of devices attached\n\n").is_empty());
+        assert!(parse_devices("").is_empty());
+        assert!(parse_device_list("").is_empty());
+    }
+
+    #[test]
+    fn parses_track_devices_snapshot() {
+        // host:track-devices-l sends the list without a header line.
+        let payload = "\
+emulator-5554          device product:sdk_gphone64_x86_64 model:sdk_gphone64_x86_64 device:emu64xa transport_id:1
+adb-0A201JECB02458-vWgJpq._adb-tls-connect._tcp device product:sunfish model:Pixel_4a device:sunfish transport_id:7
+";
+        let devices = parse_device_list(payload);
+        assert_eq!(devices.len(), 2);
+        assert_eq!(devices[0].serial, "emulator-5554");
+        assert_eq!(devices[1].model, "Pixel 4a");
+        assert_eq!(devices[1].transport_id, Some(7));
+        assert_eq!(devices[1].connection(), "Wi-Fi");
+        // The same lines under a header lose only the header.
+        assert_eq!(parse_devices(&format!("List of devices attached\n{}", payload)), devices);
+    }
+
+    #[test]
+    fn parses_getprop() {
+        let output = "\
+[dalvik.vm.heapsize]: [512m]
+[ro.build.fingerprint]: [google/sunfish/sunfish:13/TQ3A.230805.001/10316531:user/release-keys]
+[ro.build.version.release]: [13]
+[ro.build.version.sdk]: [33]
+[ro.product.cpu.abi]: [arm64-v8a]
+[ro.product.cpu.abilist]: [arm64-v8a,armeabi-v7a,armeabi]
+[ro.product.manufacturer]: [Google]
+[ro.product.model]: [Pixel 4a]
+[persist.sys.timezone]: []\r
+";
+        let props = parse_getprop(output);
+        assert_eq!(props.len(), 9);
+        assert_eq!(props["ro.product.cpu.abilist"], "arm64-v8a,armeabi-v7a,armeabi");
+        assert_eq!(props["persist.sys.timezone"], "");
+
+        let device = DeviceProps::from_getprop(output);
+        assert_eq!(device.manufacturer, "Google");
+        assert_eq!(device.model, "Pixel 4a");
+        assert_eq!(device.android_version, "13");
+        assert_eq!(device.sdk_level, "33");
+        assert_eq!(device.abi, "arm64-v8a");
+        assert!(device.fingerprint.starts_with("google/sunfish/sunfish:13/"));
+    }
+
+    #[test]
+    fn parses_wm_size() {
+        assert_eq!(parse_wm_size("Physical size: 1080x2400\n"), Some((1080, 2400)));
+        // `wm size 720x1600` adds an override line, which is what apps and `input` see.
+        assert_eq!(parse_wm_size("Physical size: 1080x2400\nOverride size: 720x1600\n"), Some((720, 1600)));
+        assert_eq!(parse_wm_size("Override size: 720x1600\r\nPhysical size: 1080x2400\r\n"), Some((720, 1600)));
+        assert_eq!(parse_wm_size(""), None);
+        assert_eq!(parse_wm_size("Physical size: unknown\n"), None);
+    }
+
+    #[test]
+    fn parses_wm_density() {
+        assert_eq!(parse_wm_density("Physical density: 420\n"), Some(420));
+        assert_eq!(parse_wm_density("Physical density: 420\nOverride density: 480\n"), Some(480));
+        assert_eq!(parse_wm_density("cmd: Can't find service: window\n"), None);
+    }
+}
@@ -0,0 +1,224 @@
+use std::process::Stdio;
+use tokio::process::{Child, ChildStdout, Command};
+use crate::adb::server_client;
+use crate::adb_client::{read_length_prefixed, AdbConnection};
+use crate::app::AppState;
+use crate::device::{parse_device_list, Device};
+use crate::error::AdbError;
+use crate::settings::{AdbBackend, AdbStudioSettings};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeviceEvent {
+    Connected(Device),
+    Disconnected(String),
+    StateChanged { device: Device, previous: String },
+    Updated(Device),
+}
+
+/// Subscription to the adb server's `track-devices` stream. The server sends the full device
+/// list once on connect and again every time anything about it changes.
+pub struct DeviceTracker {
+    source: TrackerSource,
+}
+
+enum TrackerSource {
+    Server(AdbConnection),
+    // The child is held so `adb track-devices` is killed when the tracker is dropped.
+    Executable { _child: Child, stdout: ChildStdout },
+}
+
+impl DeviceTracker {
+    pub async fn connect(settings: &AdbStudioSettings) -> Result<Self, AdbError> {
+        let source = match settings.backend {
+            AdbBackend::Server => {
+                let mut conn = server_client(settings).connect().await?;
+                conn.send_request("host:track-devices-l").await?;
+                TrackerSource::Server(conn)
+            }
+            AdbBackend::Executable => {
+                let mut child = Command::new(settings.adb_program())
+                    .arg("track-devices")
+                    .arg("-l")
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::null())
+                    .kill_on_drop(true)
+                    .spawn()
+                    .map_err(|e| AdbError::from_spawn(settings.adb_program(), e))?;
+                let stdout = child.stdout.take().ok_or_else(|| AdbError::Spawn("no stdout".to_string()))?;
+                TrackerSource::Executable { _child: child, stdout }
+            }
+        };
+        Ok(Self { source })
+    }
+
+    pub async fn next_snapshot(&mut self) -> Result<Vec<Device>, AdbError> {
+        let payload = match &mut self.source {
+            TrackerSource::Server(conn) => conn.read_length_prefixed().await?,
+            TrackerSource::Executable { stdout, .. } => read_length_prefixed(stdout).await?,
+        };
+        Ok(parse_device_list(&payload))
+    }
+}
+
+pub fn diff_devices(old: &[Device], new: &[Device]) -> Vec<DeviceEvent> {
+    let mut events = Vec::new();
+    for device in old {
+        if !new.iter().any(|d| d.serial == device.serial) {
+            events.push(DeviceEvent::Disconnected(device.serial.clone()));
+        }
+    }
+    for device in new {
+        match old.iter().find(|d| d.serial == device.serial) {
+            None => events.push(DeviceEvent::Connected(device.clone())),
+            Some(previous) if previous.status != device.status => events.push(DeviceEvent::StateChanged {
+                device: device.clone(),
+                previous: previous.status.clone(),
+            }),
+            Some(previous) if previous != device => events.push(DeviceEvent::Updated(device.clone())),
+            Some(_) => {}
+        }
+    }
+    events
+}
+
+/// Applies `event` to the device list and keeps `selected_device` pointing at a usable device.
+/// Returns a message for the user when the selection had to be dropped.
+pub fn apply_event(state: &mut AppState, event: DeviceEvent) -> Option<String> {
+    let is_selected = |serial: &str| state.selected_device.as_ref().is_some_and(|d| d.serial == serial);
+    match event {
+        DeviceEvent::Connected(device) => {
+            state.devices.push(device);
+            None
+        }
+        DeviceEvent::Disconnected(serial) => {
+            state.devices.retain(|d| d.serial != serial);
+            if is_selected(&serial) {
+                state.selected_device = None;
+                return Some(format!("{} was disconnected", serial));
+            }
+            None
+        }
+        DeviceEvent::StateChanged { device, .. } | DeviceEvent::Updated(device) => {
+            let selected = is_selected(&device.serial);
+            if let Some(existing) = state.devices.iter_mut().find(|d| d.serial == device.serial) {
+                *existing = device.clone();
+            }
+            if !selected {
+                None
+            } else if device.status == "unauthorized" || device.status == "offline" {
+                state.selected_device = None;
+                Some(format!("{} is {}", device.serial, device.status))
+            } else {
+                state.selected_device = Some(device);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn devices(payload: &str) -> Vec<Device> {
+        parse_device_list(payload)
+    }
+
+    const PIXEL: &str = "0A201JECB02458         device usb:1-4 product:sunfish model:Pixel_4a device:sunfish transport_id:3\n";
+    const EMULATOR: &str = "emulator-5554          device product:sdk_gphone64_x86_64 model:sdk_gphone64_x86_64 device:emu64xa transport_id:1\n";
+
+    fn state(payload: &str, selected: Option<&str>) -> AppState {
+        let devices = devices(payload);
+        let selected_device = selected.and_then(|serial| devices.iter().find(|d| d.serial == serial).cloned());
+        AppState { devices, selected_device }
+    }
+
+    #[test]
+    fn diffs_connected_and_disconnected_devices() {
+        let old = devices(PIXEL);
+        let new = devices(EMULATOR);
+        assert_eq!(
+            diff_devices(&old, &new),
+            [DeviceEvent::Disconnected("0A201JECB02458".to_string()), DeviceEvent::Connected(new[0].clone())]
+        );
+        assert!(diff_devices(&old, &old).is_empty());
+        assert!(diff_devices(&[], &[]).is_empty());
+    }
+
+    #[test]
+    fn diffs_state_changes_and_updates() {
+        // A device that was just plugged in shows up unauthorized, without its model.
+        let unauthorized = devices("0A201JECB02458         unauthorized usb:1-4 transport_id:3\n");
+        let authorized = devices(PIXEL);
+        assert_eq!(
+            diff_devices(&unauthorized, &authorized),
+            [DeviceEvent::StateChanged { device: authorized[0].clone(), previous: "unauthorized".to_string() }]
+        );
+
+        // Same state, but it moved to another USB port and got a new transport.
+        let moved = devices("0A201JECB02458         device usb:2-1 product:sunfish model:Pixel_4a device:sunfish transport_id:9\n");
+        assert_eq!(diff_devices(&authorized, &moved), [DeviceEvent::Updated(moved[0].clone())]);
+    }
+
+    #[test]
+    fn connect_adds_the_device_without_selecting_it() {
+        let mut app = state(PIXEL, Some("0A201JECB02458"));
+        let emulator = devices(EMULATOR).remove(0);
+        assert_eq!(apply_event(&mut app, DeviceEvent::Connected(emulator.clone())), None);
+        assert_eq!(app.devices.len(), 2);
+        assert_eq!(app.devices[1], emulator);
+        assert_eq!(app.selected_device.as_ref().map(|d| d.serial.as_str()), Some("0A201JECB02458"));
+    }
+
+    #[test]
+    fn disconnect_clears_only_the_selected_device() {
+        let both = format!("{}{}", PIXEL, EMULATOR);
+        let mut app = state(&both, Some("0A201JECB02458"));
+        assert_eq!(apply_event(&mut app, DeviceEvent::Disconnected("emulator-5554".to_string())), None);
+        assert!(app.selected_device.is_some());
+
+        let message = apply_event(&mut app, DeviceEvent::Disconnected("0A201JECB02458".to_string()));
+        assert_eq!(message.as_deref(), Some("0A201JECB02458 was disconnected"));
+        assert!(app.devices.is_empty());
+        assert_eq!(app.selected_device, None);
+    }
+
+    #[test]
+    fn unauthorized_or_offline_clears_the_selection() {
+        for status in ["unauthorized", "offline"] {
+            let mut app = state(PIXEL, Some("0A201JECB02458"));
+            let device = devices(&format!("0A201JECB02458         {} usb:1-4 transport_id:3\n", status)).remove(0);
+            let message = apply_event(&mut app, DeviceEvent::StateChanged { device: device.clone(), previous: "device".to_string() });
+            assert_eq!(message, Some(format!("0A201JECB02458 is {}", status)));
+            assert_eq!(app.selected_device, None);
+            // The device stays listed with its new state.
+            assert_eq!(app.devices, [device]);
+        }
+    }
+
+    #[test]
+    fn state_changes_of_other_devices_leave_the_selection_alone() {
+        let both = format!("{}{}", PIXEL, EMULATOR);
+        let mut app = state(&both, Some("emulator-5554"));
+        let offline = devices("0A201JECB02458         offline usb:1-4 transport_id:3\n").remove(0);
+        assert_eq!(apply_event(&mut app, DeviceEvent::StateChanged { device: offline, previous: "device".to_string() }), None);
+        assert_eq!(app.devices[0].status, "offline");
+        assert_eq!(app.selected_device.as_ref().map(|d| d.serial.as_str()), Some("emulator-5554"));
+    }
+
+    #[test]
+    fn updates_refresh_the_selected_device() {
+        let mut app = state(PIXEL, Some("0A201JECB02458"));
+        let moved = devices("0A201JECB02458         device usb:2-1 product:sunfish model:Pixel_4a device:sunfish transport_id:9\n").remove(0);
+        assert_eq!(apply_event(&mut app, DeviceEvent::Updated(moved.clone())), None);
+        assert_eq!(app.selected_device.as_ref(), Some(&moved));
+        assert_eq!(app.devices, [moved]);
+
+        // Coming back online after being authorized keeps it selected with the new details.
+        let mut app = state(PIXEL, Some("0A201JECB02458"));
+        let online = devices(PIXEL).remove(0);
+        let event = DeviceEvent::StateChanged { device: online.clone(), previous: "offline".to_string() };
+        assert_eq!(apply_event(&mut app, event), None);
+        assert_eq!(app.selected_device, Some(online));
+    }
+}
@@ -0,0 +1,228 @@
+use std::fmt;
+use std::io;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AdbError {
+    /// The `adb` binary could not be found at the configured path or in `PATH`.
+    AdbNotFound(String),
+    /// The `adb` binary exists but could not be started.
+    Spawn(String),
+    /// Nothing is listening on the adb server port and it could not be started.
+    ServerUnavailable(String),
+    NoDevices,
+    MultipleDevices,
+    DeviceNotFound(String),
+    Unauthorized,
+    Offline,
+    PermissionDenied(String),
+    NoSpace(String),
+    NoSuchFile(String),
+    /// The package manager rejected an install, with its `INSTALL_FAILED_*` or
+    /// `INSTALL_PARSE_FAILED_*` reason.
+    InstallFailed { reason: String, message: String },
+    /// A command ran but exited with a failure that has no more specific classification.
+    CommandFailed { code: Option<i32>, message: String },
+    Protocol(String),
+    Io(String),
+    /// The user cancelled the operation before it finished.
+    Cancelled,
+}
+
+impl AdbError {
+    /// Maps the error text printed by adb, adbd or a device-side tool onto a variant.
+    ///
+    /// Device states are matched on adb's own wording and errno descriptions only at the end of
+    /// a line, where `strerror` text goes, so command output that merely mentions them (an HTTP
+    /// `401 Unauthorized`, a file named `does not exist.txt`) stays a plain command failure.
+    pub fn classify(code: Option<i32>, message: &str) -> Self {
+        let message = message.trim();
+        let lower = message.to_lowercase();
+        let line_ends_with = |suffix: &str| lower.lines().any(|line| line.trim_end().trim_end_matches('.').ends_with(suffix));
+        if let Some(failure) = install_failure(message) {
+            failure
+        } else if lower.contains("device unauthorized") || lower.contains("device still authorizing") {
+            AdbError::Unauthorized
+        } else if lower.contains("device offline") {
+            AdbError::Offline
+        } else if lower.contains("no devices/emulators found") || lower.contains("no devices found") {
+            AdbError::NoDevices
+        } else if lower.contains("more than one device") {
+            AdbError::MultipleDevices
+        } else if lower.contains("device '") && lower.contains("not found") {
+            let serial = message.split('\'').nth(1).unwrap_or_default();
+            AdbError::DeviceNotFound(serial.to_string())
+        } else if line_ends_with("no space left on device") || lower.contains("insufficient_storage") {
+            AdbError::NoSpace(message.to_string())
+        } else if line_ends_with("permission denied") || line_ends_with("operation not permitted") {
+            AdbError::PermissionDenied(message.to_string())
+        } else if line_ends_with("no such file or directory") || line_ends_with("does not exist") {
+            AdbError::NoSuchFile(message.to_string())
+        } else {
+            AdbError::CommandFailed { code, message: message.to_string() }
+        }
+    }
+
+    pub fn from_spawn(program: &str, error: io::Error) -> Self {
+        if error.kind() == io::ErrorKind::NotFound {
+            AdbError::AdbNotFound(program.to_string())
+        } else {
+            AdbError::Spawn(error.to_string())
+        }
+    }
+
+    pub fn remediation(&self) -> &'static str {
+        match self {
+            AdbError::AdbNotFound(_) => "Install the Android platform-tools or set the ADB binary path in Settings.",
+            AdbError::Spawn(_) => "Check that the configured ADB binary is executable.",
+            AdbError::ServerUnavailable(_) => "Run `adb start-server` or switch the ADB connection to the adb binary in Settings.",
+            AdbError::NoDevices => "Connect a device with USB debugging enabled.",
+            AdbError::MultipleDevices => "Select the device to use from the device dropdown.",
+            AdbError::DeviceNotFound(_) => "The device was disconnected. Reconnect it and select it again.",
+            AdbError::Unauthorized => "Unlock the device and accept the \"Allow USB debugging\" prompt.",
+            AdbError::Offline => "Reconnect the cable or restart the adb server; the device is not responding.",
+            AdbError::PermissionDenied(_) => "The shell user can't access this path. Use a location under /sdcard or /data/local/tmp.",
+            AdbError::NoSpace(_) => "Free up storage on the device and try again.",
+            AdbError::NoSuchFile(_) => "Check that the path exists on the device.",
+            AdbError::InstallFailed { reason, .. } => install_remediation(reason),
+            AdbError::CommandFailed { .. } => "See the command output for details.",
+            AdbError::Protocol(_) | AdbError::Io(_) => "Restart the adb server and try again.",
+            AdbError::Cancelled => "Start the operation again when you're ready.",
+        }
+    }
+}
+
+/// Parses `Failure [INSTALL_FAILED_VERSION_DOWNGRADE: Downgrade detected: ...]` as printed by
+/// `pm install` and `adb install`.
+fn install_failure(message: &str) -> Option<AdbError> {
+    let (_, rest) = message.split_once("Failure [")?;
+    let inner = rest.split(']').next().unwrap_or(rest);
+    let (reason, detail) = inner.split_once(':').unwrap_or((inner, ""));
+    Some(AdbError::InstallFailed { reason: reason.trim().to_string(), message: detail.trim().to_string() })
+}
+
+fn install_remediation(reason: &str) -> &'static str {
+    match reason {
+        "INSTALL_FAILED_VERSION_DOWNGRADE" => "Enable \"Allow downgrade\" to install an older version over a newer one.",
+        "INSTALL_FAILED_UPDATE_INCOMPATIBLE" | "INSTALL_FAILED_SHARED_USER_INCOMPATIBLE" => {
+            "The installed app is signed with a different key. Uninstall it first."
+        }
+        "INSTALL_FAILED_ALREADY_EXISTS" => "Enable \"Replace existing\" to reinstall the app.",
+        "INSTALL_FAILED_TEST_ONLY" => "Enable \"Allow test packages\" to install a test-only APK.",
+        "INSTALL_FAILED_INSUFFICIENT_STORAGE" => "Free up storage on the device and try again.",
+        "INSTALL_FAILED_NO_MATCHING_ABIS" => "The app has no native code for this device's CPU architecture.",
+        "INSTALL_FAILED_OLDER_SDK" => "The device runs an older Android version than the app's minimum SDK.",
+        "INSTALL_FAILED_DEPRECATED_SDK_VERSION" => "The app targets an SDK too old for this Android version.",
+        "INSTALL_FAILED_MISSING_SPLIT" => "Select every split APK of the app, or install the .apks/.xapk bundle.",
+        "INSTALL_FAILED_USER_RESTRICTED" => "Allow installing apps over USB in the device's developer options.",
+        reason if reason.starts_with("INSTALL_PARSE_FAILED") => "The APK is damaged or unsigned. Rebuild or re-download it.",
+        _ => "See the reason reported by the package manager.",
+    }
+}
+
+impl fmt::Display for AdbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AdbError::AdbNotFound(program) => write!(f, "adb binary not found: {}", program),
+            AdbError::Spawn(e) => write!(f, "failed to start adb: {}", e),
+            AdbError::ServerUnavailable(e) => write!(f, "adb server unavailable: {}", e),
+            AdbError::NoDevices => write!(f, "no devices/emulators found"),
+            AdbError::MultipleDevices => write!(f, "more than one device/emulator"),
+            AdbError::DeviceNotFound(serial) => write!(f, "device '{}' not found", serial),
+            AdbError::Unauthorized => write!(f, "device unauthorized"),
+            AdbError::Offline => write!(f, "device offline"),
+            AdbError::PermissionDenied(msg) | AdbError::NoSpace(msg) | AdbError::NoSuchFile(msg) => write!(f, "{}", msg),
+            AdbError::InstallFailed { reason, message } if message.is_empty() => write!(f, "install failed: {}", reason),
+            AdbError::InstallFailed { reason, message } => write!(f, "install failed: {}: {}", reason, message),
+            AdbError::CommandFailed { code: Some(code), message } => write!(f, "command failed with exit code {}: {}", code, message),
+            AdbError::CommandFailed { code: None, message } => write!(f, "command failed: {}", message),
+            AdbError::Protocol(msg) => write!(f, "adb protocol error: {}", msg),
+            AdbError::Io(msg) => write!(f, "{}", msg),
+            AdbError::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for AdbError {}
+
+impl From<io::Error> for AdbError {
+    fn from(error: io::Error) -> Self {
+        AdbError::Io(error.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_adb_and_device_errors() {
+        let cases = [
+            ("error: device unauthorized.\nThis adb server's $ADB_VENDOR_KEYS is not set\nTry 'adb kill-server' if that seems wrong.\nOtherwise check for a confirmation dialog on your device.", AdbError::Unauthorized),
+            ("error: device still authorizing", AdbError::Unauthorized),
+            ("error: device offline", AdbError::Offline),
+            ("adb: no devices/emulators found", AdbError::NoDevices),
+            ("error: more than one device/emulator", AdbError::MultipleDevices),
+            ("error: device '0A201JECB02458' not found", AdbError::DeviceNotFound("0A201JECB02458".to_string())),
+            (
+                "adb: error: failed to stat remote object '/sdcard/missing.txt': No such file or directory",
+                AdbError::NoSuchFile("adb: error: failed to stat remote object '/sdcard/missing.txt': No such file or directory".to_string()),
+            ),
+            ("remote object '/sdcard/missing.txt' does not exist", AdbError::NoSuchFile("remote object '/sdcard/missing.txt' does not exist".to_string())),
+            ("ls: /data/data: Permission denied", AdbError::PermissionDenied("ls: /data/data: Permission denied".to_string())),
+            ("rm: /system/bin/sh: Operation not permitted", AdbError::PermissionDenied("rm: /system/bin/sh: Operation not permitted".to_string())),
+            (
+                "adb: error: failed to copy 'big.bin' to '/sdcard/big.bin': remote write failed: No space left on device",
+                AdbError::NoSpace("adb: error: failed to copy 'big.bin' to '/sdcard/big.bin': remote write failed: No space left on device".to_string()),
+            ),
+        ];
+        for (message, expected) in cases {
+            assert_eq!(AdbError::classify(Some(1), message), expected, "{:?}", message);
+        }
+    }
+
+    #[test]
+    fn classifies_install_failures() {
+        let error = AdbError::classify(
+            Some(1),
+            "Performing Streamed Install\nadb: failed to install app.apk: Failure [INSTALL_FAILED_VERSION_DOWNGRADE: Downgrade detected: Update version code 41 is older than current 42]",
+        );
+        assert_eq!(
+            error,
+            AdbError::InstallFailed {
+                reason: "INSTALL_FAILED_VERSION_DOWNGRADE".to_string(),
+                message: "Downgrade detected: Update version code 41 is older than current 42".to_string(),
+            }
+        );
+        assert_eq!(error.remediation(), install_remediation("INSTALL_FAILED_VERSION_DOWNGRADE"));
+
+        let error = AdbError::classify(None, "Failure [INSTALL_FAILED_INSUFFICIENT_STORAGE]");
+        assert_eq!(error, AdbError::InstallFailed { reason: "INSTALL_FAILED_INSUFFICIENT_STORAGE".to_string(), message: String::new() });
+        assert_eq!(error.to_string(), "install failed: INSTALL_FAILED_INSUFFICIENT_STORAGE");
+    }
+
+    #[test]
+    fn output_that_only_mentions_an_error_is_a_command_failure() {
+        for message in [
+            "curl: (22) The requested URL returned error: 401 Unauthorized",
+            "/sdcard/Download/does not exist.txt",
+            "grep: pattern 'Permission denied' matched 3 lines in /sdcard/log.txt",
+            "echo No such file or directory is not an error here",
+        ] {
+            assert_eq!(
+                AdbError::classify(Some(2), message),
+                AdbError::CommandFailed { code: Some(2), message: message.to_string() },
+                "{:?}",
+                message
+            );
+        }
+        assert_eq!(AdbError::classify(None, "  \n"), AdbError::CommandFailed { code: None, message: String::new() });
+    }
+
+    #[test]
+    fn spawn_errors() {
+        let missing = io::Error::new(io::ErrorKind::NotFound, "No such file or directory (os error 2)");
+        assert_eq!(AdbError::from_spawn("/opt/platform-tools/adb", missing), AdbError::AdbNotFound("/opt/platform-tools/adb".to_string()));
+        let denied = io::Error::new(io::ErrorKind::PermissionDenied, "Permission denied (os error 13)");
+        assert_eq!(AdbError::from_spawn("adb", denied), AdbError::Spawn("Permission denied (os error 13)".to_string()));
+    }
+}
@@ -1,58 +1,393 @@
-use crate::adb::{adb_push, adb_pull};
+use std::path::PathBuf;
+use dioxus::html::HasFileData;
 use dioxus::prelude::*;
-use rfd::AsyncFileDialog;
+use rfd::{AsyncFileDialog, AsyncMessageDialog, MessageButtons, MessageDialogResult, MessageLevel};
 use crate::app::AppState;
 use crate::settings::AdbStudioSettings;
+use crate::error::AdbError;
+use crate::installer::is_installable;
+use crate::transfers::{run_transfer, TransferDirection, TransferQueue};
+use tokio_util::sync::CancellationToken;
+use crate::remote_fs::{self, child_path, format_size, parent_path, sort_entries, EntryKind, RemoteEntry, SortKey};
+use crate::ui::ErrorNotice;
+
+#[derive(Clone, PartialEq)]
+enum PendingAction {
+    NewFolder,
+    Rename(String),
+}
+
+fn breadcrumbs(path: &str) -> Vec<(String, String)> {
+    let mut crumbs = vec![("/".to_string(), "/".to_string())];
+    let mut current = String::new();
+    for segment in path.split('/').filter(|s| !s.is_empty()) {
+        current = format!("{}/{}", current, segment);
+        crumbs.push((segment.to_string(), current.clone()));
+    }
+    crumbs
+}
 
 #[component]
 pub fn FileManager() -> Element {
     let mut logs = use_signal(String::new);
+    let mut error = use_signal(|| None::<AdbError>);
+    let mut current_path = use_signal(|| "/sdcard".to_string());
+    let mut entries = use_signal(Vec::<RemoteEntry>::new);
+    let mut sort_key = use_signal(|| SortKey::Name);
+    let mut ascending = use_signal(|| true);
+    let mut selected = use_signal(|| None::<String>);
+    let mut pending = use_signal(|| None::<PendingAction>);
+    let mut pending_name = use_signal(String::new);
+    let mut reload = use_signal(|| 0u32);
+    let mut is_loading = use_signal(|| false);
     let app_state = use_context::<Signal<AppState>>();
     let settings = use_context::<Signal<AdbStudioSettings>>();
+    let mut transfers = use_context::<Signal<TransferQueue>>();
+
+    // Memoized so unrelated device list updates do not reload the listing.
+    let selected_serial = use_memo(move || app_state.read().selected_device.as_ref().map(|d| d.serial.clone()));
+
+    use_effect(move || {
+        let path = current_path();
+        let _ = reload();
+        let serial = selected_serial();
+        spawn(async move {
+            let Some(serial) = serial else {
+                entries.set(Vec::new());
+                return;
+            };
+            is_loading.set(true);
+            let settings_clone = settings.read().clone();
+            match remote_fs::list_dir(&settings_clone, &serial, &path).await {
+                Ok(list) => {
+                    error.set(None);
+                    entries.set(list);
+                }
+                Err(e) => {
+                    entries.set(Vec::new());
+                    error.set(Some(e));
+                }
+            }
+            selected.set(None);
+            is_loading.set(false);
+        });
+    });
+
+    let mut report = move |result: Result<String, AdbError>| match result {
+        Ok(output) => {
+            error.set(None);
+            logs.set(output);
+        }
+        Err(e) => {
+            logs.set(String::new());
+            error.set(Some(e));
+        }
+    };
+
+    // Pushes local files or folders into the directory being browsed, stopping at the first failure.
+    let push_here = move |paths: Vec<PathBuf>| async move {
+        let Some(serial) = selected_serial() else {
+            logs.set("No device selected.".to_string());
+            return;
+        };
+        let settings_clone = settings.read().clone();
+        let target = format!("{}/", current_path().trim_end_matches('/'));
+        let mut outputs = Vec::new();
+        // One token for the batch so cancelling any file stops the rest.
+        let cancel = CancellationToken::new();
+        for path in paths {
+            let id = transfers.write().enqueue(TransferDirection::Push, &serial, &path.to_string_lossy(), &target, cancel.clone());
+            match run_transfer(transfers, id, &settings_clone).await {
+                Ok(output) => outputs.push(output),
+                Err(e) => {
+                    report(Err(e));
+                    reload += 1;
+                    return;
+                }
+            }
+        }
+        report(Ok(outputs.join("\n")));
+        reload += 1;
+    };
+
+    let mut toggle_sort = move |key: SortKey| {
+        if sort_key() == key {
+            ascending.set(!ascending());
+        } else {
+            sort_key.set(key);
+            ascending.set(true);
+        }
+    };
+
+    let sort_indicator = move |key: SortKey| {
+        if sort_key() != key {
+            ""
+        } else if ascending() {
+            " ▲"
+        } else {
+            " ▼"
+        }
+    };
+
+    let mut sorted = entries.read().clone();
+    sort_entries(&mut sorted, sort_key(), ascending());
+
+    let mut submit_pending = move || {
+        let Some(action) = pending() else { return };
+        let name = pending_name().trim().to_string();
+        let Some(serial) = selected_serial() else { return };
+        if name.is_empty() || name.contains('/') {
+            logs.set("Enter a name without '/'.".to_string());
+            return;
+        }
+        spawn(async move {
+            let settings_clone = settings.read().clone();
+            let dir = current_path();
+            let result = match &action {
+                PendingAction::NewFolder => remote_fs::make_dir(&settings_clone, &serial, &child_path(&dir, &name))
+                    .await
+                    .map(|_| format!("Created {}", child_path(&dir, &name))),
+                PendingAction::Rename(from) => {
+                    remote_fs::rename(&settings_clone, &serial, &child_path(&dir, from), &child_path(&dir, &name))
+                        .await
+                        .map(|_| format!("Renamed {} to {}", from, name))
+                }
+            };
+            report(result);
+            pending.set(None);
+            reload += 1;
+        });
+    };
 
     rsx! {
         div {
+            ondragover: move |e| e.prevent_default(),
+            // APKs are left to the window-wide handler, which installs them.
+            ondrop: move |e| {
+                e.prevent_default();
+                let paths: Vec<PathBuf> = e.files().iter().map(|f| f.path()).filter(|p| !is_installable(p)).collect();
+                if !paths.is_empty() {
+                    spawn(push_here(paths));
+                }
+            },
             h2 { "File Manager" }
+            if selected_serial().is_none() {
+                p { "No device selected." }
+            }
+            div {
+                style: "display: flex; flex-wrap: wrap; align-items: center; gap: 4px; margin-bottom: 8px;",
+                for (label, path) in breadcrumbs(&current_path()) {
+                    button {
+                        class: "btn btn-secondary",
+                        style: "margin: 0; padding: 4px 10px;",
+                        onclick: move |_| current_path.set(path.clone()),
+                        "{label}"
+                    }
+                }
+            }
             div {
-                button { 
+                button {
+                    class: "btn btn-secondary",
+                    disabled: current_path() == "/",
+                    onclick: move |_| current_path.set(parent_path(&current_path())),
+                    i { class: "fas fa-arrow-up" }
+                    "Up"
+                }
+                button {
+                    class: "btn btn-secondary",
+                    onclick: move |_| reload += 1,
+                    i { class: "fas fa-sync" }
+                    "Refresh"
+                }
+                button {
+                    class: "btn btn-secondary",
+                    onclick: move |_| {
+                        pending_name.set(String::new());
+                        pending.set(Some(PendingAction::NewFolder));
+                    },
+                    i { class: "fas fa-folder-plus" }
+                    "New Folder"
+                }
+                button {
                     class: "btn",
                     onclick: move |_| {
                         spawn(async move {
-                            if let Some(device) = &app_state.read().selected_device {
-                                if let Some(file) = AsyncFileDialog::new().pick_file().await {
-                                    let settings_clone = settings.read().clone();
-                                    let result = adb_push(&settings_clone, &device.serial, file.path().to_str().unwrap(), "/sdcard/").await;
-                                    logs.set(format!("{:?}", result));
-                                }
-                            } else {
+                            if selected_serial().is_none() {
                                 logs.set("No device selected.".to_string());
+                                return;
+                            }
+                            if let Some(files) = AsyncFileDialog::new().pick_files().await {
+                                push_here(files.iter().map(|f| f.path().to_path_buf()).collect()).await;
                             }
                         });
                     },
                     i { class: "fas fa-upload" }
-                    "Push File" 
+                    "Push Files Here"
                 }
-                button { 
+                button {
                     class: "btn",
                     onclick: move |_| {
                         spawn(async move {
-                            if let Some(device) = &app_state.read().selected_device {
-                                if let Some(folder) = AsyncFileDialog::new().pick_folder().await {
-                                    let settings_clone = settings.read().clone();
-                                    let result = adb_pull(&settings_clone, &device.serial, "/sdcard/", folder.path().to_str().unwrap()).await;
-                                    logs.set(format!("{:?}", result));
-                                }
-                            } else {
+                            let Some(serial) = selected_serial() else {
                                 logs.set("No device selected.".to_string());
+                                return;
+                            };
+                            if let Some(folder) = AsyncFileDialog::new().pick_folder().await {
+                                let settings_clone = settings.read().clone();
+                                let target = format!("{}/", current_path().trim_end_matches('/'));
+                                let id = transfers.write().enqueue(TransferDirection::Push, &serial, &folder.path().to_string_lossy(), &target, CancellationToken::new());
+                                report(run_transfer(transfers, id, &settings_clone).await);
+                                reload += 1;
                             }
                         });
                     },
-                    i { class: "fas fa-download" }
-                    "Pull File" 
+                    i { class: "fas fa-folder-open" }
+                    "Push Folder Here"
+                }
+            }
+            if let Some(action) = pending() {
+                div {
+                    style: "display: flex; align-items: center; gap: 8px; margin-bottom: 8px;",
+                    span {
+                        match &action {
+                            PendingAction::NewFolder => "New folder name:".to_string(),
+                            PendingAction::Rename(from) => format!("Rename '{}' to:", from),
+                        }
+                    }
+                    input {
+                        r#type: "text",
+                        value: "{pending_name}",
+                        oninput: move |evt| pending_name.set(evt.value()),
+                        onkeydown: move |evt| {
+                            if evt.key() == Key::Enter {
+                                submit_pending();
+                            } else if evt.key() == Key::Escape {
+                                pending.set(None);
+                            }
+                        },
+                    }
+                    button { class: "btn", style: "margin: 0;", onclick: move |_| submit_pending(), "Save" }
+                    button { class: "btn btn-secondary", style: "margin: 0;", onclick: move |_| pending.set(None), "Cancel" }
+                }
+            }
+            if let Some(e) = error() {
+                ErrorNotice { error: e }
+            }
+            if is_loading() {
+                p { "Loading {current_path}..." }
+            }
+            table {
+                thead {
+                    tr {
+                        th { style: "cursor: pointer;", onclick: move |_| toggle_sort(SortKey::Name), "Name{sort_indicator(SortKey::Name)}" }
+                        th { style: "cursor: pointer;", onclick: move |_| toggle_sort(SortKey::Size), "Size{sort_indicator(SortKey::Size)}" }
+                        th { style: "cursor: pointer;", onclick: move |_| toggle_sort(SortKey::Permissions), "Permissions{sort_indicator(SortKey::Permissions)}" }
+                        th { style: "cursor: pointer;", onclick: move |_| toggle_sort(SortKey::Owner), "Owner{sort_indicator(SortKey::Owner)}" }
+                        th { style: "cursor: pointer;", onclick: move |_| toggle_sort(SortKey::Modified), "Modified{sort_indicator(SortKey::Modified)}" }
+                        th { "Actions" }
+                    }
+                }
+                tbody {
+                    for entry in sorted {
+                        {
+                            let is_selected = selected.read().as_deref() == Some(entry.name.as_str());
+                            let icon = match entry.kind {
+                                EntryKind::Directory => "fas fa-folder",
+                                EntryKind::Symlink => "fas fa-link",
+                                EntryKind::File => "fas fa-file",
+                                EntryKind::Other => "fas fa-microchip",
+                            };
+                            let open_name = entry.name.clone();
+                            let select_name = entry.name.clone();
+                            let pull_name = entry.name.clone();
+                            let rename_name = entry.name.clone();
+                            let delete_name = entry.name.clone();
+                            let navigable = entry.is_navigable();
+                            rsx! {
+                                tr {
+                                    class: if is_selected { "selected" } else { "" },
+                                    onclick: move |_| selected.set(Some(select_name.clone())),
+                                    ondoubleclick: move |_| {
+                                        if navigable {
+                                            current_path.set(child_path(&current_path(), &open_name));
+                                        }
+                                    },
+                                    td {
+                                        i { class: icon, style: "margin-right: 8px; color: var(--secondary-text);" }
+                                        "{entry.name}"
+                                        if let Some(target) = &entry.link_target {
+                                            span { style: "color: var(--secondary-text);", " → {target}" }
+                                        }
+                                    }
+                                    td {
+                                        if entry.kind == EntryKind::File {
+                                            {format_size(entry.size)}
+                                        }
+                                    }
+                                    td { style: "font-family: monospace;", "{entry.permissions}" }
+                                    td { "{entry.owner}:{entry.group}" }
+                                    td { "{entry.modified}" }
+                                    td {
+                                        button {
+                                            onclick: move |evt| {
+                                                evt.stop_propagation();
+                                                let remote = child_path(&current_path(), &pull_name);
+                                                spawn(async move {
+                                                    let Some(serial) = selected_serial() else { return };
+                                                    if let Some(folder) = AsyncFileDialog::new().pick_folder().await {
+                                                        let settings_clone = settings.read().clone();
+                                                        let id = transfers.write().enqueue(TransferDirection::Pull, &serial, &remote, &folder.path().to_string_lossy(), CancellationToken::new());
+                                                        report(run_transfer(transfers, id, &settings_clone).await);
+                                                    }
+                                                });
+                                            },
+                                            i { class: "fas fa-download" }
+                                            "Pull"
+                                        }
+                                        button {
+                                            onclick: move |evt| {
+                                                evt.stop_propagation();
+                                                pending_name.set(rename_name.clone());
+                                                pending.set(Some(PendingAction::Rename(rename_name.clone())));
+                                            },
+                                            i { class: "fas fa-pen" }
+                                            "Rename"
+                                        }
+                                        button {
+                                            onclick: move |evt| {
+                                                evt.stop_propagation();
+                                                let remote = child_path(&current_path(), &delete_name);
+                                                spawn(async move {
+                                                    let Some(serial) = selected_serial() else { return };
+                                                    let confirmed = AsyncMessageDialog::new()
+                                                        .set_level(MessageLevel::Warning)
+                                                        .set_title("Delete")
+                                                        .set_description(format!("Delete {} from the device? This cannot be undone.", remote))
+                                                        .set_buttons(MessageButtons::OkCancel)
+                                                        .show()
+                                                        .await;
+                                                    if confirmed != MessageDialogResult::Ok {
+                                                        return;
+                                                    }
+                                                    let settings_clone = settings.read().clone();
+                                                    let result = remote_fs::remove(&settings_clone, &serial, &remote).await;
+                                                    report(result.map(|_| format!("Deleted {}", remote)));
+                                                    reload += 1;
+                                                });
+                                            },
+                                            i { class: "fas fa-trash" }
+                                            "Delete"
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
                 }
             }
             div {
                 class: "log-output",
+                style: "height: 120px;",
                 pre { {logs} }
             }
         }
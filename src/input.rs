@@ -0,0 +1,271 @@
+use crate::adb::{adb_shell, shell_quote};
+use crate::device::{parse_wm_density, parse_wm_size};
+use crate::error::AdbError;
+use crate::settings::AdbStudioSettings;
+
+/// Drags shorter than this many density-independent pixels count as taps.
+const TAP_SLOP_DP: f64 = 8.0;
+
+/// What the device reports about its screen, needed to turn a point on a mirrored frame into
+/// the coordinates `input` expects.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DisplayInfo {
+    /// Size in the display's natural orientation, honouring any `wm size` override.
+    pub width: u32,
+    pub height: u32,
+    /// Quarter turns from the natural orientation (`Surface.ROTATION_*`).
+    pub rotation: u8,
+    pub density: u32,
+}
+
+impl DisplayInfo {
+    pub async fn query(settings: &AdbStudioSettings, serial: &str) -> Result<Self, AdbError> {
+        let size = adb_shell(settings, serial, "wm size").await?;
+        let density = adb_shell(settings, serial, "wm density").await?;
+        // Older releases report it as SurfaceOrientation, newer ones on the internal viewport.
+        let input = adb_shell(settings, serial, "dumpsys input | grep -m2 -E 'SurfaceOrientation|Viewport INTERNAL'")
+            .await
+            .unwrap_or_default();
+        let (width, height) = parse_wm_size(&size).ok_or_else(|| AdbError::Protocol(format!("unexpected wm size output: {}", size.trim())))?;
+        Ok(DisplayInfo {
+            width,
+            height,
+            rotation: parse_rotation(&input),
+            density: parse_wm_density(&density).unwrap_or(160),
+        })
+    }
+
+    /// Size in the current orientation, which is what `input tap` coordinates refer to.
+    pub fn logical_size(&self) -> (u32, u32) {
+        if self.rotation % 2 == 1 { (self.height, self.width) } else { (self.width, self.height) }
+    }
+
+    /// Maps a point on a `frame_width` x `frame_height` frame to device coordinates.
+    ///
+    /// `screencap` output follows the current orientation on most devices, but some return the
+    /// natural orientation regardless; the frame's shape tells the two apart.
+    pub fn map_point(&self, x: f64, y: f64, frame_width: u32, frame_height: u32) -> (u32, u32) {
+        let (logical_width, logical_height) = self.logical_size();
+        let frame_landscape = frame_width > frame_height;
+        let logical_landscape = logical_width > logical_height;
+        let (x, y) = if frame_landscape == logical_landscape {
+            (x / frame_width as f64 * logical_width as f64, y / frame_height as f64 * logical_height as f64)
+        } else {
+            // The frame is in the natural orientation; rotate the point into the current one.
+            let nx = x / frame_width as f64 * self.width as f64;
+            let ny = y / frame_height as f64 * self.height as f64;
+            match self.rotation {
+                1 => (ny, self.width as f64 - nx),
+                2 => (self.width as f64 - nx, self.height as f64 - ny),
+                3 => (self.height as f64 - ny, nx),
+                _ => (nx, ny),
+            }
+        };
+        (
+            x.clamp(0.0, logical_width.saturating_sub(1) as f64) as u32,
+            y.clamp(0.0, logical_height.saturating_sub(1) as f64) as u32,
+        )
+    }
+
+    /// Movement, in device pixels, below which a drag is treated as a tap.
+    pub fn tap_slop(&self) -> f64 {
+        TAP_SLOP_DP * self.density as f64 / 160.0
+    }
+}
+
+fn parse_rotation(output: &str) -> u8 {
+    for line in output.lines() {
+        let line = line.trim();
+        if let Some(value) = line.strip_prefix("SurfaceOrientation:") {
+            return value.trim().parse().unwrap_or(0);
+        }
+        if let Some((_, rest)) = line.split_once("orientation=") {
+            return rest.split(|c: char| !c.is_ascii_digit()).next().and_then(|v| v.parse().ok()).unwrap_or(0);
+        }
+    }
+    0
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HardwareKey {
+    Back,
+    Home,
+    Recents,
+    Power,
+    VolumeUp,
+    VolumeDown,
+}
+
+impl HardwareKey {
+    pub const ALL: [HardwareKey; 6] = [
+        HardwareKey::Back,
+        HardwareKey::Home,
+        HardwareKey::Recents,
+        HardwareKey::Power,
+        HardwareKey::VolumeDown,
+        HardwareKey::VolumeUp,
+    ];
+
+    pub fn keycode(&self) -> u32 {
+        match self {
+            HardwareKey::Back => 4,
+            HardwareKey::Home => 3,
+            HardwareKey::Recents => 187,
+            HardwareKey::Power => 26,
+            HardwareKey::VolumeUp => 24,
+            HardwareKey::VolumeDown => 25,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            HardwareKey::Back => "Back",
+            HardwareKey::Home => "Home",
+            HardwareKey::Recents => "Recents",
+            HardwareKey::Power => "Power",
+            HardwareKey::VolumeUp => "Volume Up",
+            HardwareKey::VolumeDown => "Volume Down",
+        }
+    }
+
+    pub fn icon(&self) -> &'static str {
+        match self {
+            HardwareKey::Back => "fas fa-arrow-left",
+            HardwareKey::Home => "fas fa-circle",
+            HardwareKey::Recents => "fas fa-square",
+            HardwareKey::Power => "fas fa-power-off",
+            HardwareKey::VolumeUp => "fas fa-volume-up",
+            HardwareKey::VolumeDown => "fas fa-volume-down",
+        }
+    }
+}
+
+/// Maps a key pressed on the desktop to an Android keycode, for keys that don't type text.
+pub fn keycode_for(key: &str) -> Option<u32> {
+    Some(match key {
+        "Enter" => 66,
+        "Backspace" => 67,
+        "Tab" => 61,
+        "Escape" => 4,
+        "Delete" => 112,
+        "ArrowUp" => 19,
+        "ArrowDown" => 20,
+        "ArrowLeft" => 21,
+        "ArrowRight" => 22,
+        "Home" => 122,
+        "End" => 123,
+        "PageUp" => 92,
+        "PageDown" => 93,
+        _ => return None,
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InputAction {
+    Tap { x: u32, y: u32 },
+    Swipe { x1: u32, y1: u32, x2: u32, y2: u32, duration_ms: u64 },
+    Text(String),
+    Key(u32),
+}
+
+impl InputAction {
+    fn command(&self) -> String {
+        match self {
+            InputAction::Tap { x, y } => format!("input tap {} {}", x, y),
+            InputAction::Swipe { x1, y1, x2, y2, duration_ms } => {
+                format!("input swipe {} {} {} {} {}", x1, y1, x2, y2, duration_ms)
+            }
+            // `input text` takes %s for a space and can't type anything outside ASCII.
+            InputAction::Text(text) => {
+                let text = text.chars().filter(char::is_ascii).collect::<String>().replace(' ', "%s");
+                format!("input text {}", shell_quote(&text))
+            }
+            InputAction::Key(code) => format!("input keyevent {}", code),
+        }
+    }
+
+    pub async fn send(&self, settings: &AdbStudioSettings, serial: &str) -> Result<(), AdbError> {
+        adb_shell(settings, serial, &self.command()).await.map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PIXEL: DisplayInfo = DisplayInfo { width: 1080, height: 2400, rotation: 0, density: 420 };
+
+    fn rotated(rotation: u8) -> DisplayInfo {
+        DisplayInfo { rotation, ..PIXEL }
+    }
+
+    #[test]
+    fn maps_frames_in_the_current_orientation() {
+        // A half-size frame, as the mirror scales it, in the current orientation.
+        assert_eq!(PIXEL.map_point(270.0, 600.0, 540, 1200), (540, 1200));
+        assert_eq!(rotated(1).logical_size(), (2400, 1080));
+        assert_eq!(rotated(1).map_point(600.0, 270.0, 1200, 540), (1200, 540));
+        assert_eq!(rotated(3).map_point(0.0, 0.0, 1200, 540), (0, 0));
+        // Upside down looks like the natural orientation, so the frame is taken as already rotated.
+        assert_eq!(rotated(2).map_point(100.0, 200.0, 1080, 2400), (100, 200));
+    }
+
+    #[test]
+    fn rotates_frames_in_the_natural_orientation() {
+        // Some devices return portrait screencaps while turned to landscape. A point 100px from
+        // the natural left edge and 200px from the natural top edge:
+        // turned counter-clockwise (ROTATION_90), the natural top edge is on the left...
+        assert_eq!(rotated(1).map_point(100.0, 200.0, 1080, 2400), (200, 980));
+        assert_eq!(rotated(1).map_point(50.0, 100.0, 540, 1200), (200, 980));
+        // ...and turned clockwise (ROTATION_270), it is on the right.
+        assert_eq!(rotated(3).map_point(100.0, 200.0, 1080, 2400), (2200, 100));
+        // The natural top-left corner ends up bottom-left and top-right respectively.
+        assert_eq!(rotated(1).map_point(0.0, 0.0, 1080, 2400), (0, 1079));
+        assert_eq!(rotated(3).map_point(0.0, 0.0, 1080, 2400), (2399, 0));
+    }
+
+    #[test]
+    fn clamps_to_the_screen() {
+        assert_eq!(PIXEL.map_point(1080.0, 2400.0, 1080, 2400), (1079, 2399));
+        assert_eq!(PIXEL.map_point(-5.0, -5.0, 1080, 2400), (0, 0));
+        assert_eq!(rotated(1).map_point(1080.0, 2400.0, 1080, 2400), (2399, 0));
+    }
+
+    #[test]
+    fn tap_slop_follows_density() {
+        assert_eq!(DisplayInfo { density: 160, ..PIXEL }.tap_slop(), 8.0);
+        assert_eq!(DisplayInfo { density: 480, ..PIXEL }.tap_slop(), 24.0);
+    }
+
+    #[test]
+    fn parses_rotation_from_dumpsys_input() {
+        // Android 9 and older, under the touch screen's configuration.
+        assert_eq!(parse_rotation("      SurfaceOrientation: 1\n"), 1);
+        // Android 10 and newer list display viewports.
+        let viewport = "    Viewport INTERNAL: displayId=0, uniqueId=local:4619827259835644672, port=Optional[0], orientation=3, logicalFrame=[0, 0, 2400, 1080], physicalFrame=[0, 0, 2400, 1080], deviceSize=[1080, 2400], isActive=[true]\n";
+        assert_eq!(parse_rotation(viewport), 3);
+        assert_eq!(parse_rotation(&viewport.replace("orientation=3", "orientation=0")), 0);
+        assert_eq!(parse_rotation(""), 0);
+    }
+
+    #[test]
+    fn builds_input_commands() {
+        assert_eq!(InputAction::Tap { x: 540, y: 1200 }.command(), "input tap 540 1200");
+        assert_eq!(
+            InputAction::Swipe { x1: 540, y1: 1800, x2: 540, y2: 600, duration_ms: 250 }.command(),
+            "input swipe 540 1800 540 600 250"
+        );
+        assert_eq!(InputAction::Key(HardwareKey::Back.keycode()).command(), "input keyevent 4");
+        assert_eq!(keycode_for("Enter"), Some(66));
+        assert_eq!(keycode_for("a"), None);
+    }
+
+    #[test]
+    fn quotes_typed_text() {
+        assert_eq!(InputAction::Text("hello world".to_string()).command(), "input text 'hello%sworld'");
+        // The device shell must not expand or split anything the user typed.
+        assert_eq!(InputAction::Text("it's $HOME; rm -rf *".to_string()).command(), "input text 'it'\\''s%s$HOME;%srm%s-rf%s*'");
+        // `input text` can only type ASCII.
+        assert_eq!(InputAction::Text("caf\u{e9} \u{1f600}ok".to_string()).command(), "input text 'caf%sok'");
+    }
+}
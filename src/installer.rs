@@ -0,0 +1,475 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use dioxus::prelude::*;
+use tokio_util::sync::CancellationToken;
+use crate::adb::{adb_getprop, adb_install, adb_shell, cancellable, shell_quote};
+use crate::device::{parse_getprop, parse_wm_density};
+use crate::error::AdbError;
+use crate::settings::AdbStudioSettings;
+use crate::transfers::{run_transfer, TransferDirection, TransferQueue};
+
+/// Where APKs are staged on the device; the shell user can write here and the package manager
+/// can read it.
+const REMOTE_STAGING_DIR: &str = "/data/local/tmp/adb-studio-install";
+pub const INSTALLABLE_EXTENSIONS: [&str; 3] = ["apk", "apks", "xapk"];
+const KNOWN_ABIS: [&str; 5] = ["arm64-v8a", "armeabi-v7a", "armeabi", "x86_64", "x86"];
+const DENSITIES: [(&str, u32); 7] = [
+    ("ldpi", 120),
+    ("mdpi", 160),
+    ("tvdpi", 213),
+    ("hdpi", 240),
+    ("xhdpi", 320),
+    ("xxhdpi", 480),
+    ("xxxhdpi", 640),
+];
+
+static NEXT_WORK_DIR: AtomicU32 = AtomicU32::new(0);
+static NEXT_STAGING_DIR: AtomicU32 = AtomicU32::new(0);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstallOptions {
+    /// `-r`: reinstall over an existing copy, keeping its data.
+    pub replace: bool,
+    /// `-d`: allow a lower version code than the installed one.
+    pub downgrade: bool,
+    /// `-g`: grant every runtime permission in the manifest.
+    pub grant_all: bool,
+    /// `-t`: allow packages marked `android:testOnly`.
+    pub allow_test: bool,
+    /// `--user`: install for this user only instead of the current one.
+    pub user: Option<u32>,
+}
+
+impl Default for InstallOptions {
+    fn default() -> Self {
+        InstallOptions { replace: true, downgrade: false, grant_all: false, allow_test: false, user: None }
+    }
+}
+
+impl InstallOptions {
+    pub fn flags(&self) -> Vec<String> {
+        let mut flags = Vec::new();
+        for (enabled, flag) in [(self.replace, "-r"), (self.downgrade, "-d"), (self.grant_all, "-g"), (self.allow_test, "-t")] {
+            if enabled {
+                flags.push(flag.to_string());
+            }
+        }
+        if let Some(user) = self.user {
+            flags.push(format!("--user {}", user));
+        }
+        flags
+    }
+}
+
+pub fn is_installable(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| INSTALLABLE_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+fn is_bundle(path: &Path) -> bool {
+    path.extension().map(|ext| ext.eq_ignore_ascii_case("apks") || ext.eq_ignore_ascii_case("xapk")).unwrap_or(false)
+}
+
+/// The parts of the device configuration that decide which config splits of a bundle it needs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceSpec {
+    /// Supported ABIs, most preferred first.
+    pub abis: Vec<String>,
+    pub density: u32,
+    pub languages: Vec<String>,
+}
+
+impl DeviceSpec {
+    pub async fn query(settings: &AdbStudioSettings, serial: &str) -> Result<Self, AdbError> {
+        let props = parse_getprop(&adb_getprop(settings, serial).await?);
+        let prop = |name: &str| props.get(name).filter(|v| !v.is_empty()).cloned();
+        let abis = prop("ro.product.cpu.abilist")
+            .or_else(|| prop("ro.product.cpu.abi"))
+            .map(|list| list.split(',').map(|abi| abi.trim().to_string()).collect())
+            .unwrap_or_default();
+        let density = adb_shell(settings, serial, "wm density").await.ok().and_then(|output| parse_wm_density(&output));
+        let languages = [prop("persist.sys.locale"), prop("ro.product.locale"), prop("persist.sys.language")]
+            .into_iter()
+            .flatten()
+            .filter_map(|locale| locale.split(['-', '_']).next().map(str::to_lowercase))
+            .collect();
+        Ok(DeviceSpec { abis, density: density.unwrap_or(160), languages })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum SplitConfig {
+    /// The module's code and default resources, always needed.
+    Master,
+    Abi(String),
+    Density(u32),
+    Language(String),
+    /// Anything unrecognised is installed rather than risk leaving out something required.
+    Other,
+}
+
+fn split_config(config: &str) -> SplitConfig {
+    let abi = config.replace('_', "-");
+    if config == "master" {
+        SplitConfig::Master
+    } else if KNOWN_ABIS.contains(&abi.as_str()) {
+        SplitConfig::Abi(abi)
+    } else if let Some((_, dpi)) = DENSITIES.iter().find(|(name, _)| *name == config) {
+        SplitConfig::Density(*dpi)
+    } else if (2..=3).contains(&config.len()) && config.chars().all(|c| c.is_ascii_lowercase()) {
+        SplitConfig::Language(config.to_string())
+    } else {
+        SplitConfig::Other
+    }
+}
+
+/// One APK of a bundle, named by the entry it's stored under.
+#[derive(Debug, Clone)]
+struct Split {
+    module: String,
+    config: SplitConfig,
+    entry: String,
+}
+
+/// Picks the splits a device needs from each module, the way bundletool's `install-apks` does:
+/// the master split, the best ABI and density, and any languages the device uses.
+fn select_splits(splits: Vec<Split>, spec: &DeviceSpec) -> Vec<String> {
+    let mut modules: BTreeMap<String, Vec<Split>> = BTreeMap::new();
+    for split in splits {
+        modules.entry(split.module.clone()).or_default().push(split);
+    }
+    let mut selected = Vec::new();
+    for (_, splits) in modules {
+        let abi = spec.abis.iter().find(|abi| splits.iter().any(|s| s.config == SplitConfig::Abi(abi.to_string())));
+        let densities: Vec<u32> = splits.iter().filter_map(|s| if let SplitConfig::Density(d) = s.config { Some(d) } else { None }).collect();
+        // The smallest density at least as high as the device's, else the highest there is.
+        let density = densities.iter().copied().filter(|d| *d >= spec.density).min().or_else(|| densities.iter().copied().max());
+        for split in splits {
+            let keep = match &split.config {
+                SplitConfig::Master | SplitConfig::Other => true,
+                SplitConfig::Abi(a) => Some(a) == abi,
+                SplitConfig::Density(d) => Some(*d) == density,
+                SplitConfig::Language(language) => spec.languages.contains(language),
+            };
+            if keep {
+                selected.push(split.entry);
+            }
+        }
+    }
+    selected
+}
+
+/// A local directory that is deleted with everything in it when dropped.
+struct WorkDir(PathBuf);
+
+impl WorkDir {
+    fn create() -> Result<Self, AdbError> {
+        let id = NEXT_WORK_DIR.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("adb-studio-install-{}-{}", std::process::id(), id));
+        std::fs::create_dir_all(&path)?;
+        Ok(WorkDir(path))
+    }
+}
+
+impl Drop for WorkDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// An expansion file shipped in an `.xapk`, and where it goes on shared storage.
+#[derive(Debug, Clone)]
+pub struct Expansion {
+    pub local: PathBuf,
+    pub remote: String,
+}
+
+/// The APKs to install in one session, and any expansion files to push afterwards.
+pub struct InstallPlan {
+    pub apks: Vec<PathBuf>,
+    pub expansions: Vec<Expansion>,
+    _work_dir: Option<WorkDir>,
+}
+
+impl InstallPlan {
+    /// Several `.apk` files are taken to be the splits of one app. An `.apks` (bundletool) or
+    /// `.xapk` archive is unpacked and only the splits the device needs are kept.
+    pub fn prepare(paths: &[PathBuf], spec: Option<&DeviceSpec>) -> Result<Self, AdbError> {
+        match paths {
+            [] => Err(AdbError::NoSuchFile("no APK selected".to_string())),
+            [bundle] if is_bundle(bundle) => {
+                let spec = spec.ok_or_else(|| AdbError::Protocol("a device spec is needed to pick splits".to_string()))?;
+                InstallPlan::from_bundle(bundle, spec)
+            }
+            paths if paths.iter().any(|p| is_bundle(p)) => Err(AdbError::CommandFailed {
+                code: None,
+                message: "install .apks and .xapk bundles one at a time".to_string(),
+            }),
+            paths => Ok(InstallPlan { apks: paths.to_vec(), expansions: Vec::new(), _work_dir: None }),
+        }
+    }
+
+    fn from_bundle(path: &Path, spec: &DeviceSpec) -> Result<Self, AdbError> {
+        let invalid = |e: zip::result::ZipError| AdbError::Io(format!("{}: {}", path.display(), e));
+        let mut archive = zip::ZipArchive::new(File::open(path)?).map_err(invalid)?;
+        let names: Vec<String> = archive.file_names().map(str::to_string).collect();
+        let (entries, expansions) = if names.iter().any(|n| n == "manifest.json") {
+            let mut manifest = String::new();
+            std::io::Read::read_to_string(&mut archive.by_name("manifest.json").map_err(invalid)?, &mut manifest)?;
+            xapk_entries(&manifest, &names, spec)
+        } else {
+            (apks_entries(&names, spec), Vec::new())
+        };
+        if entries.is_empty() {
+            return Err(AdbError::NoSuchFile(format!("{} contains no APKs for this device", path.display())));
+        }
+
+        let work_dir = WorkDir::create()?;
+        let mut extract = |entry: &str, index: usize| -> Result<PathBuf, AdbError> {
+            let name = Path::new(entry).file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            let target = work_dir.0.join(format!("{}-{}", index, name));
+            let mut file = archive.by_name(entry).map_err(invalid)?;
+            std::io::copy(&mut file, &mut File::create(&target)?)?;
+            Ok(target)
+        };
+        let apks = entries.iter().enumerate().map(|(i, entry)| extract(entry, i)).collect::<Result<Vec<_>, _>>()?;
+        let expansions = expansions
+            .into_iter()
+            .enumerate()
+            .map(|(i, (entry, remote))| Ok(Expansion { local: extract(&entry, apks.len() + i)?, remote }))
+            .collect::<Result<Vec<_>, AdbError>>()?;
+        Ok(InstallPlan { apks, expansions, _work_dir: Some(work_dir) })
+    }
+}
+
+/// Entries to install from a bundletool `.apks`: the matching splits, else a universal APK, else
+/// the standalone APK for the device's ABI.
+fn apks_entries(names: &[String], spec: &DeviceSpec) -> Vec<String> {
+    let splits: Vec<Split> = names
+        .iter()
+        .filter_map(|name| {
+            let stem = name.strip_prefix("splits/")?.strip_suffix(".apk")?;
+            let (module, config) = stem.split_once('-').unwrap_or((stem, "master"));
+            Some(Split { module: module.to_string(), config: split_config(config), entry: name.clone() })
+        })
+        .collect();
+    if !splits.is_empty() {
+        return select_splits(splits, spec);
+    }
+    if let Some(universal) = names.iter().find(|n| n.ends_with("universal.apk")) {
+        return vec![universal.clone()];
+    }
+    let standalones: Vec<&String> = names.iter().filter(|n| n.starts_with("standalones/") && n.ends_with(".apk")).collect();
+    spec.abis
+        .iter()
+        .find_map(|abi| standalones.iter().find(|n| n.contains(&abi.replace('-', "_"))))
+        .or(standalones.first())
+        .map(|n| vec![n.to_string()])
+        .unwrap_or_default()
+}
+
+/// Entries to install from an `.xapk`, whose `manifest.json` lists the splits by id
+/// (`base`, `config.arm64_v8a`, ...) and any OBB expansion files.
+fn xapk_entries(manifest: &str, names: &[String], spec: &DeviceSpec) -> (Vec<String>, Vec<(String, String)>) {
+    let manifest: serde_json::Value = serde_json::from_str(manifest).unwrap_or_default();
+    let mut splits: Vec<Split> = manifest["split_apks"]
+        .as_array()
+        .map(|splits| {
+            splits
+                .iter()
+                .filter_map(|split| {
+                    let file = split["file"].as_str()?;
+                    let id = split["id"].as_str().unwrap_or(file.trim_end_matches(".apk"));
+                    Some(xapk_split(id, file))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    // Single-APK .xapk files only name the package; the APK sits at the top of the archive.
+    if splits.is_empty() {
+        splits = names
+            .iter()
+            .filter(|n| !n.contains('/') && n.ends_with(".apk"))
+            .map(|n| xapk_split(n.trim_end_matches(".apk"), n))
+            .collect();
+    }
+    let expansions = manifest["expansions"]
+        .as_array()
+        .map(|expansions| {
+            expansions
+                .iter()
+                .filter_map(|e| {
+                    let install_path = e["install_path"].as_str().filter(|p| is_safe_relative(p))?;
+                    Some((e["file"].as_str()?.to_string(), format!("/sdcard/{}", install_path)))
+                })
+                .filter(|(file, _)| names.contains(file))
+                .collect()
+        })
+        .unwrap_or_default();
+    (select_splits(splits, spec), expansions)
+}
+
+/// Whether a path from an archive's manifest stays inside the directory it is joined onto: not
+/// absolute and without `..` segments.
+fn is_safe_relative(path: &str) -> bool {
+    let path = Path::new(path);
+    !path.as_os_str().is_empty() && path.components().all(|c| matches!(c, std::path::Component::Normal(_)))
+}
+
+fn xapk_split(id: &str, file: &str) -> Split {
+    let (module, config) = match id.strip_prefix("config.") {
+        Some(config) => ("base", config),
+        None => id.split_once(".config.").unwrap_or((id, "master")),
+    };
+    Split { module: module.to_string(), config: split_config(config), entry: file.to_string() }
+}
+
+/// Splits a mixed set of dropped files into separate installs: each bundle on its own and the
+/// loose `.apk` files together, as the splits of one app.
+pub fn install_groups(paths: &[PathBuf]) -> Vec<Vec<PathBuf>> {
+    let (bundles, apks): (Vec<PathBuf>, Vec<PathBuf>) = paths.iter().cloned().partition(|p| is_bundle(p));
+    let mut groups: Vec<Vec<PathBuf>> = bundles.into_iter().map(|bundle| vec![bundle]).collect();
+    if !apks.is_empty() {
+        groups.push(apks);
+    }
+    groups
+}
+
+/// How far an install has got, for showing progress.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InstallEvent {
+    Preparing,
+    /// An APK or expansion file is being pushed as transfer `transfer` of the queue.
+    Pushing { transfer: u64, index: usize, count: usize },
+    Installing,
+}
+
+/// Pushes the selected APKs or bundle to the device, installs them in one session and then
+/// pushes any expansion files. Pushes run through the transfer queue so they show progress.
+pub async fn install_packages(
+    mut transfers: Signal<TransferQueue>,
+    settings: &AdbStudioSettings,
+    serial: &str,
+    paths: &[PathBuf],
+    options: &InstallOptions,
+    cancel: &CancellationToken,
+    mut report: impl FnMut(InstallEvent),
+) -> Result<String, AdbError> {
+    report(InstallEvent::Preparing);
+    let spec = if paths.iter().any(|p| is_bundle(p)) { Some(DeviceSpec::query(settings, serial).await?) } else { None };
+    let owned_paths = paths.to_vec();
+    let plan = tokio::task::spawn_blocking(move || InstallPlan::prepare(&owned_paths, spec.as_ref()))
+        .await
+        .map_err(|e| AdbError::Io(e.to_string()))??;
+
+    // Each install gets its own directory so concurrent installs don't delete each other's APKs.
+    let staging = format!("{}/{}-{}", REMOTE_STAGING_DIR, std::process::id(), NEXT_STAGING_DIR.fetch_add(1, Ordering::Relaxed));
+    adb_shell(settings, serial, &format!("rm -rf {dir} && mkdir -p {dir}", dir = shell_quote(&staging))).await?;
+    let count = plan.apks.len() + plan.expansions.len();
+    let mut enqueue = move |local: &Path, remote: &str| {
+        transfers.write().enqueue(TransferDirection::Push, serial, &local.to_string_lossy(), remote, cancel.child_token())
+    };
+    let result = async {
+        let mut remote_apks = Vec::new();
+        for (i, apk) in plan.apks.iter().enumerate() {
+            let remote = format!("{}/{}.apk", staging, i);
+            let id = enqueue(apk, &remote);
+            report(InstallEvent::Pushing { transfer: id, index: i, count });
+            run_transfer(transfers, id, settings).await?;
+            remote_apks.push(remote);
+        }
+        report(InstallEvent::Installing);
+        let output = cancellable(cancel, adb_install(settings, serial, &remote_apks, &options.flags())).await?;
+        // Expansion files only land once the app is installed and owns its OBB directory.
+        for (i, expansion) in plan.expansions.iter().enumerate() {
+            let id = enqueue(&expansion.local, &expansion.remote);
+            report(InstallEvent::Pushing { transfer: id, index: plan.apks.len() + i, count });
+            run_transfer(transfers, id, settings).await?;
+        }
+        Ok(output)
+    }
+    .await;
+    let _ = adb_shell(settings, serial, &format!("rm -rf {}", shell_quote(&staging))).await;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> DeviceSpec {
+        DeviceSpec { abis: vec!["arm64-v8a".to_string(), "armeabi-v7a".to_string()], density: 420, languages: vec!["de".to_string()] }
+    }
+
+    fn names(entries: &[&str]) -> Vec<String> {
+        entries.iter().map(|e| e.to_string()).collect()
+    }
+
+    #[test]
+    fn apks_selects_splits_for_device() {
+        let entries = names(&[
+            "toc.pb",
+            "splits/base-master.apk",
+            "splits/base-arm64_v8a.apk",
+            "splits/base-armeabi_v7a.apk",
+            "splits/base-x86.apk",
+            "splits/base-xhdpi.apk",
+            "splits/base-xxhdpi.apk",
+            "splits/base-xxxhdpi.apk",
+            "splits/base-de.apk",
+            "splits/base-fr.apk",
+            "splits/feature-master.apk",
+        ]);
+        let mut selected = apks_entries(&entries, &spec());
+        selected.sort();
+        assert_eq!(
+            selected,
+            names(&["splits/base-arm64_v8a.apk", "splits/base-de.apk", "splits/base-master.apk", "splits/base-xxhdpi.apk", "splits/feature-master.apk"])
+        );
+    }
+
+    #[test]
+    fn apks_falls_back_to_standalone_for_abi() {
+        let entries = names(&["standalones/standalone-x86.apk", "standalones/standalone-armeabi_v7a.apk"]);
+        assert_eq!(apks_entries(&entries, &spec()), names(&["standalones/standalone-armeabi_v7a.apk"]));
+    }
+
+    #[test]
+    fn xapk_reads_manifest() {
+        let manifest = r#"{"package_name":"com.x","split_apks":[
+            {"file":"com.x.apk","id":"base"},
+            {"file":"config.arm64_v8a.apk","id":"config.arm64_v8a"},
+            {"file":"config.x86.apk","id":"config.x86"},
+            {"file":"config.xxhdpi.apk","id":"config.xxhdpi"}],
+            "expansions":[{"file":"Android/obb/com.x/main.1.com.x.obb","install_path":"Android/obb/com.x/main.1.com.x.obb"}]}"#;
+        let entries = names(&["manifest.json", "com.x.apk", "config.arm64_v8a.apk", "config.x86.apk", "config.xxhdpi.apk", "Android/obb/com.x/main.1.com.x.obb"]);
+        let (mut apks, expansions) = xapk_entries(manifest, &entries, &spec());
+        apks.sort();
+        assert_eq!(apks, names(&["com.x.apk", "config.arm64_v8a.apk", "config.xxhdpi.apk"]));
+        assert_eq!(
+            expansions,
+            vec![("Android/obb/com.x/main.1.com.x.obb".to_string(), "/sdcard/Android/obb/com.x/main.1.com.x.obb".to_string())]
+        );
+    }
+
+    #[test]
+    fn xapk_rejects_escaping_install_paths() {
+        let entries = names(&["a.apk", "a.obb", "b.obb", "c.obb"]);
+        let manifest = r#"{"expansions":[
+            {"file":"a.obb","install_path":"../../data/local/tmp/a.obb"},
+            {"file":"b.obb","install_path":"/data/local/tmp/b.obb"},
+            {"file":"c.obb","install_path":"Android/obb/com.x/../../../c.obb"}]}"#;
+        let (apks, expansions) = xapk_entries(manifest, &entries, &spec());
+        assert_eq!(apks, names(&["a.apk"]));
+        assert!(expansions.is_empty(), "{:?}", expansions);
+    }
+
+    #[test]
+    fn install_flags() {
+        let options = InstallOptions { downgrade: true, user: Some(10), ..Default::default() };
+        assert_eq!(options.flags(), vec!["-r", "-d", "--user 10"]);
+    }
+}
@@ -1,45 +1,193 @@
+use std::collections::HashSet;
 use dioxus::prelude::*;
-use tokio::io::{BufReader, AsyncBufReadExt};
-use tokio::process::Command;
-use tokio::sync::mpsc;
+use regex::Regex;
+use tokio_util::sync::CancellationToken;
+use crate::adb::{adb_shell, shell_quote};
+use crate::app::AppState;
+use crate::crash_detector::CrashLog;
+use crate::error::AdbError;
+use crate::logcat::{
+    clear_device_logs, export_jsonl, export_text, parse_pids, parse_threadtime, read_log_file, LogBuffer, LogFilter,
+    LogLevel, LogcatBuffer, LogcatOptions, LogcatStart, LogcatStream,
+};
+use rfd::AsyncFileDialog;
 use crate::settings::AdbStudioSettings;
+use crate::ui::ErrorNotice;
+
+const ROW_HEIGHT: f64 = 20.0;
+const OVERSCAN_ROWS: usize = 20;
+const FLUSH_INTERVAL: tokio::time::Duration = tokio::time::Duration::from_millis(100);
+const LOG_LIST_ID: &str = "log-list";
 
 #[component]
 pub fn LogViewer() -> Element {
-    let mut logs = use_signal(String::new);
-    let mut is_logging = use_signal(|| false);
     let settings = use_context::<Signal<AdbStudioSettings>>();
+    let mut entries = use_signal(|| LogBuffer::new(settings.peek().log_buffer_capacity));
+    let mut scroll_top = use_signal(|| 0.0);
+    let mut viewport_height = use_signal(|| 400.0);
+    let mut follow = use_signal(|| true);
+    let mut is_logging = use_signal(|| false);
+    let mut error = use_signal(|| None::<AdbError>);
+    let mut cancel = use_signal(CancellationToken::new);
+    let mut min_level = use_signal(|| None::<LogLevel>);
+    let mut tag_filter = use_signal(String::new);
+    let mut pid_filter = use_signal(String::new);
+    let mut package_filter = use_signal(String::new);
+    let mut package_pids = use_signal(|| None::<HashSet<u32>>);
+    let mut pattern = use_signal(|| None::<Regex>);
+    let mut pattern_error = use_signal(|| None::<String>);
+    let mut options = use_signal(LogcatOptions::default);
+    let mut since_text = use_signal(String::new);
+    let mut status = use_signal(String::new);
+    let app_state = use_context::<Signal<AppState>>();
+    let mut crash_log = use_context::<Signal<CrashLog>>();
+    let selected_serial = use_memo(move || app_state.read().selected_device.as_ref().map(|d| d.serial.clone()));
 
     use_effect(move || {
-        if *is_logging.read() {
-            let (tx, mut rx_stream) = mpsc::channel(100);
-            let settings_clone = settings.read().clone();
-            spawn(async move {
-                let mut cmd = Command::new(if settings_clone.adb_path.is_empty() { "adb" } else { &settings_clone.adb_path });
-                cmd.arg("logcat");
-                let mut child = cmd.stdout(std::process::Stdio::piped()).spawn().expect("Failed to spawn adb logcat");
-                let stdout = child.stdout.take().expect("Failed to get stdout");
-                let mut reader = BufReader::new(stdout).lines();
-
-                while let Ok(Some(line)) = reader.next_line().await {
-                    if tx.send(line).await.is_err() {
-                        break;
-                    }
-                }
-                child.wait().await.expect("Logcat command failed");
-            });
-
-            spawn(async move {
-                while let Some(line) = rx_stream.recv().await {
-                    logs.with_mut(|l| {
-                        l.push_str(&line);
-                        l.push('\n');
-                    });
-                }
-            });
+        let capacity = settings.read().log_buffer_capacity;
+        if entries.peek().capacity() != capacity {
+            entries.write().set_capacity(capacity);
         }
     });
 
+    use_effect(move || {
+        // Stop the previous logcat before starting another, or when logging is switched off.
+        cancel.peek().cancel();
+        if !*is_logging.read() {
+            return;
+        }
+        // Reading the serial here restarts logcat on the newly selected device.
+        let Some(serial) = selected_serial() else {
+            is_logging.set(false);
+            return;
+        };
+        let token = CancellationToken::new();
+        cancel.set(token.clone());
+        let settings_clone = settings.peek().clone();
+        let logcat_options = options.peek().clone();
+        spawn(async move {
+            let mut stream = match LogcatStream::open(&settings_clone, &serial, &logcat_options).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    error.set(Some(e));
+                    is_logging.set(false);
+                    return;
+                }
+            };
+            error.set(None);
+            status.set(format!("Reading {} logs from {}", if logcat_options.dump { "buffered" } else { "live" }, serial));
+
+            // Lines are collected and handed to the UI in batches; one signal write per line
+            // would re-render the view hundreds of times a second on a busy device.
+            let mut batch = Vec::new();
+            let mut detector = crash_log.write().detector(&serial);
+            let mut flush = tokio::time::interval(FLUSH_INTERVAL);
+            loop {
+                tokio::select! {
+                    line = stream.next_line() => match line {
+                        Ok(Some(line)) => {
+                            if let Some(entry) = parse_threadtime(&line) {
+                                detector.observe(&entry);
+                                batch.push(entry);
+                            }
+                        }
+                        _ => break,
+                    },
+                    _ = flush.tick() => {
+                        if !batch.is_empty() {
+                            entries.write().extend(batch.drain(..));
+                        }
+                        let incidents = detector.take_updates();
+                        if !incidents.is_empty() {
+                            crash_log.write().record(incidents);
+                        }
+                    }
+                    _ = token.cancelled() => {
+                        status.set("Logcat stopped".to_string());
+                        return;
+                    }
+                }
+            }
+            entries.write().extend(batch);
+            crash_log.write().record(detector.take_updates());
+            // A dump ends by itself; a live logcat ends when the device goes away.
+            match stream.finish().await {
+                Ok(()) if logcat_options.dump => status.set("Dump complete".to_string()),
+                Ok(()) => status.set("Logcat ended".to_string()),
+                Err(e) => error.set(Some(e)),
+            }
+            is_logging.set(false);
+        });
+    });
+
+    use_drop(move || cancel.peek().cancel());
+
+    // Apps get a new pid every time they restart, so keep the package's pids fresh.
+    use_future(move || async move {
+        loop {
+            let package = package_filter.peek().trim().to_string();
+            let serial = app_state.peek().selected_device.as_ref().map(|d| d.serial.clone());
+            match (package.is_empty(), serial) {
+                (false, Some(serial)) => {
+                    let settings_clone = settings.peek().clone();
+                    // pidof exits non-zero when nothing matches, which just means "not running".
+                    let output = adb_shell(&settings_clone, &serial, &format!("pidof {}", shell_quote(&package))).await;
+                    let pids = parse_pids(&output.unwrap_or_default());
+                    if package_pids.peek().as_ref() != Some(&pids) {
+                        package_pids.set(Some(pids));
+                    }
+                }
+                _ => {
+                    if package_pids.peek().is_some() {
+                        package_pids.set(None);
+                    }
+                }
+            }
+            tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+        }
+    });
+
+    let visible = use_memo(move || {
+        let typed_pids = parse_pids(&pid_filter());
+        let pids = match package_pids() {
+            Some(pids) => Some(pids.union(&typed_pids).copied().collect()),
+            None if typed_pids.is_empty() => None,
+            None => Some(typed_pids),
+        };
+        let filter = LogFilter {
+            min_level: min_level(),
+            tag: tag_filter(),
+            pids,
+            pattern: pattern(),
+        };
+        entries.read().iter().enumerate().filter(|(_, e)| filter.matches(e)).map(|(i, _)| i).collect::<Vec<_>>()
+    });
+    let total = entries.read().len();
+    let is_empty = entries.read().is_empty();
+    let dropped = entries.read().dropped();
+    let visible_count = visible.read().len();
+
+    use_effect(use_reactive!(|visible_count| {
+        let _ = visible_count;
+        if *follow.peek() {
+            document::eval(&format!(
+                "let el = document.getElementById('{}'); if (el) {{ el.scrollTop = el.scrollHeight; }}",
+                LOG_LIST_ID
+            ));
+        }
+    }));
+
+    // Only the rows inside the viewport (plus some overscan) are rendered; spacers stand in
+    // for the rest so the scrollbar still reflects the full list.
+    let first_row = ((scroll_top() / ROW_HEIGHT) as usize).saturating_sub(OVERSCAN_ROWS).min(visible_count);
+    let last_row = (first_row + (viewport_height() / ROW_HEIGHT).ceil() as usize + 2 * OVERSCAN_ROWS).min(visible_count);
+    let rows = {
+        let buffer = entries.read();
+        visible.read()[first_row..last_row].iter().filter_map(|&i| buffer.get(i).cloned()).collect::<Vec<_>>()
+    };
+    let top_spacer = first_row as f64 * ROW_HEIGHT;
+    let bottom_spacer = (visible_count - last_row) as f64 * ROW_HEIGHT;
+
     rsx! {
         div {
             h2 { "Real-time Logs" }
@@ -53,10 +201,302 @@ pub fn LogViewer() -> Element {
                     i { class: if *is_logging.read() { "fas fa-stop" } else { "fas fa-play" } }
                     if *is_logging.read() { "Stop Logcat" } else { "Start Logcat" }
                 }
+                button {
+                    class: "btn btn-secondary",
+                    onclick: move |_| entries.write().clear(),
+                    i { class: "fas fa-eraser" }
+                    "Clear View"
+                }
+                button {
+                    class: "btn btn-secondary",
+                    disabled: selected_serial().is_none(),
+                    onclick: move |_| {
+                        let Some(serial) = selected_serial() else { return };
+                        let buffers = options.read().buffers.clone();
+                        spawn(async move {
+                            let settings_clone = settings.read().clone();
+                            match clear_device_logs(&settings_clone, &serial, &buffers).await {
+                                Ok(()) => {
+                                    entries.write().clear();
+                                    status.set(format!("Cleared device log buffers on {}", serial));
+                                }
+                                Err(e) => error.set(Some(e)),
+                            }
+                        });
+                    },
+                    i { class: "fas fa-trash" }
+                    "Clear Device Buffer"
+                }
+                button {
+                    class: "btn btn-secondary",
+                    disabled: is_empty,
+                    onclick: move |_| {
+                        spawn(async move {
+                            let Some(file) = AsyncFileDialog::new().add_filter("Log", &["txt", "log"]).set_file_name("logcat.txt").save_file().await else {
+                                return;
+                            };
+                            let text = export_text(entries.read().iter());
+                            match tokio::fs::write(file.path(), text).await {
+                                Ok(()) => status.set(format!("Exported {} entries to {}", entries.read().len(), file.path().display())),
+                                Err(e) => error.set(Some(e.into())),
+                            }
+                        });
+                    },
+                    i { class: "fas fa-file-export" }
+                    "Export Text"
+                }
+                button {
+                    class: "btn btn-secondary",
+                    disabled: is_empty,
+                    onclick: move |_| {
+                        spawn(async move {
+                            let Some(file) = AsyncFileDialog::new().add_filter("JSON Lines", &["jsonl"]).set_file_name("logcat.jsonl").save_file().await else {
+                                return;
+                            };
+                            let result = match export_jsonl(entries.read().iter()) {
+                                Ok(json) => tokio::fs::write(file.path(), json).await.map_err(Into::into),
+                                Err(e) => Err(e),
+                            };
+                            match result {
+                                Ok(()) => status.set(format!("Exported {} entries to {}", entries.read().len(), file.path().display())),
+                                Err(e) => error.set(Some(e)),
+                            }
+                        });
+                    },
+                    i { class: "fas fa-file-code" }
+                    "Export JSONL"
+                }
+                button {
+                    class: "btn btn-secondary",
+                    onclick: move |_| {
+                        spawn(async move {
+                            let Some(file) = AsyncFileDialog::new()
+                                .add_filter("Logs and bugreports", &["txt", "log", "jsonl", "zip"])
+                                .pick_file()
+                                .await
+                            else {
+                                return;
+                            };
+                            is_logging.set(false);
+                            let path = file.path().to_path_buf();
+                            match tokio::task::spawn_blocking(move || read_log_file(&path)).await {
+                                Ok(Ok(loaded)) => {
+                                    // Keep the whole file even when it is larger than the live buffer.
+                                    let capacity = settings.read().log_buffer_capacity.max(loaded.len());
+                                    let count = loaded.len();
+                                    let mut detector = crash_log.write().detector(&file.file_name());
+                                    for entry in &loaded {
+                                        detector.observe(entry);
+                                    }
+                                    crash_log.write().record(detector.take_updates());
+                                    let mut buffer = LogBuffer::new(capacity);
+                                    buffer.extend(loaded);
+                                    entries.set(buffer);
+                                    error.set(None);
+                                    follow.set(false);
+                                    status.set(format!("Offline: {} entries from {}", count, file.file_name()));
+                                }
+                                Ok(Err(e)) => error.set(Some(e)),
+                                Err(e) => error.set(Some(AdbError::Io(e.to_string()))),
+                            }
+                        });
+                    },
+                    i { class: "fas fa-folder-open" }
+                    "Open Log File"
+                }
+            }
+            div {
+                style: "display: flex; flex-wrap: wrap; gap: 8px; align-items: center; margin-bottom: 8px; font-size: 0.875rem;",
+                span { style: "color: var(--secondary-text);", "Buffers:" }
+                for buffer in LogcatBuffer::ALL {
+                    label {
+                        style: "display: flex; align-items: center;",
+                        input {
+                            r#type: "checkbox",
+                            style: "margin: 0 4px 0 0;",
+                            checked: options.read().buffers.contains(&buffer),
+                            disabled: is_logging(),
+                            oninput: move |event| {
+                                let mut opts = options.write();
+                                if event.checked() {
+                                    if !opts.buffers.contains(&buffer) {
+                                        opts.buffers.push(buffer);
+                                    }
+                                } else if opts.buffers.len() > 1 {
+                                    opts.buffers.retain(|b| *b != buffer);
+                                }
+                            },
+                        }
+                        "{buffer.name()}"
+                    }
+                }
+                select {
+                    class: "btn btn-secondary",
+                    style: "margin: 0 0 0 12px;",
+                    disabled: is_logging(),
+                    onchange: move |event| {
+                        options.write().start = match event.value().as_str() {
+                            "now" => LogcatStart::Now,
+                            "last" => LogcatStart::LastLines(1000),
+                            "since" => LogcatStart::Since(since_text()),
+                            _ => LogcatStart::Buffered,
+                        };
+                    },
+                    option { value: "buffered", "Everything buffered" }
+                    option { value: "now", "From now" }
+                    option { value: "last", "Last 1000 entries" }
+                    option { value: "since", "Since time…" }
+                }
+                if matches!(options.read().start, LogcatStart::Since(_)) {
+                    input {
+                        r#type: "text",
+                        placeholder: "MM-DD hh:mm:ss.mmm",
+                        style: "max-width: 180px;",
+                        disabled: is_logging(),
+                        value: "{since_text}",
+                        oninput: move |event| {
+                            since_text.set(event.value());
+                            options.write().start = LogcatStart::Since(event.value());
+                        },
+                    }
+                }
+                label {
+                    style: "display: flex; align-items: center; margin-left: 12px;",
+                    input {
+                        r#type: "checkbox",
+                        style: "margin: 0 4px 0 0;",
+                        checked: options.read().dump,
+                        disabled: is_logging(),
+                        oninput: move |event| options.write().dump = event.checked(),
+                    }
+                    "Dump and exit"
+                }
+            }
+            div {
+                style: "display: flex; flex-wrap: wrap; gap: 8px; align-items: center;",
+                select {
+                    class: "btn btn-secondary",
+                    style: "margin: 0;",
+                    onchange: move |event| {
+                        min_level.set(event.value().chars().next().and_then(LogLevel::from_char));
+                    },
+                    option { value: "", "All levels" }
+                    for level in LogLevel::ALL {
+                        option { value: "{level.as_char()}", "{level.label()} and above" }
+                    }
+                }
+                input {
+                    r#type: "text",
+                    placeholder: "Tag",
+                    style: "max-width: 160px;",
+                    value: "{tag_filter}",
+                    oninput: move |event| tag_filter.set(event.value()),
+                }
+                input {
+                    r#type: "text",
+                    placeholder: "PIDs",
+                    style: "max-width: 120px;",
+                    value: "{pid_filter}",
+                    oninput: move |event| pid_filter.set(event.value()),
+                }
+                input {
+                    r#type: "text",
+                    placeholder: "Package",
+                    style: "max-width: 220px;",
+                    value: "{package_filter}",
+                    oninput: move |event| package_filter.set(event.value()),
+                }
+                input {
+                    r#type: "text",
+                    placeholder: "Regex",
+                    style: "max-width: 240px;",
+                    oninput: move |event| {
+                        let text = event.value();
+                        if text.is_empty() {
+                            pattern.set(None);
+                            pattern_error.set(None);
+                        } else {
+                            match Regex::new(&text) {
+                                Ok(regex) => {
+                                    pattern.set(Some(regex));
+                                    pattern_error.set(None);
+                                }
+                                Err(e) => pattern_error.set(Some(e.to_string())),
+                            }
+                        }
+                    },
+                }
             }
             div {
+                style: "margin-top: 8px; font-size: 0.8rem; color: var(--secondary-text);",
+                if is_empty {
+                    "No log entries yet"
+                } else {
+                    "Showing {visible_count} of {total} entries"
+                }
+                if dropped > 0 {
+                    " · {dropped} older entries dropped"
+                }
+                if let Some(pids) = package_pids() {
+                    if pids.is_empty() {
+                        " · {package_filter} is not running"
+                    } else {
+                        " · {package_filter}: {pids.len()} process(es)"
+                    }
+                }
+            }
+            if !status.read().is_empty() {
+                div { style: "font-size: 0.8rem; color: var(--secondary-text);", "{status}" }
+            }
+            if let Some(message) = pattern_error() {
+                div { style: "color: var(--error-color); font-size: 0.8rem;", "Invalid regex: {message}" }
+            }
+            if let Some(e) = error() {
+                ErrorNotice { error: e }
+            }
+            if !follow() {
+                button {
+                    class: "btn btn-secondary",
+                    style: "margin-top: 8px;",
+                    onclick: move |_| {
+                        follow.set(true);
+                        document::eval(&format!(
+                            "let el = document.getElementById('{}'); if (el) {{ el.scrollTop = el.scrollHeight; }}",
+                            LOG_LIST_ID
+                        ));
+                    },
+                    i { class: "fas fa-arrow-down" }
+                    "Auto-scroll paused · Jump to latest"
+                }
+            }
+            div {
+                id: LOG_LIST_ID,
                 class: "log-output",
-                pre { {logs} }
+                onscroll: move |event| {
+                    scroll_top.set(event.scroll_top());
+                    let at_bottom = event.scroll_top() + event.client_height() as f64 >= event.scroll_height() as f64 - ROW_HEIGHT;
+                    if *follow.peek() != at_bottom {
+                        follow.set(at_bottom);
+                    }
+                },
+                onresize: move |event| {
+                    if let Ok(size) = event.get_content_box_size() {
+                        viewport_height.set(size.height);
+                    }
+                },
+                div { style: "height: {top_spacer}px;" }
+                for entry in rows {
+                    div {
+                        style: "display: flex; gap: 8px; height: {ROW_HEIGHT}px; line-height: {ROW_HEIGHT}px; white-space: nowrap; overflow: hidden;",
+                        title: "{entry.message}",
+                        span { style: "color: var(--secondary-text); flex-shrink: 0;", "{entry.timestamp}" }
+                        span { style: "color: var(--secondary-text); flex-shrink: 0; min-width: 90px;", "{entry.pid}/{entry.tid}" }
+                        span { style: "color: {entry.level.color()}; font-weight: 700; flex-shrink: 0;", "{entry.level.as_char()}" }
+                        span { style: "color: {entry.level.color()}; flex-shrink: 0; width: 160px; overflow: hidden; text-overflow: ellipsis;", "{entry.tag}" }
+                        span { style: "color: var(--primary-text); overflow: hidden; text-overflow: ellipsis;", "{entry.message}" }
+                    }
+                }
+                div { style: "height: {bottom_spacer}px;" }
             }
         }
     }
@@ -0,0 +1,544 @@
+use std::collections::{HashSet, VecDeque};
+use std::process::Stdio;
+use std::io::Read;
+use std::path::Path;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, BufReader, Lines};
+use tokio::process::{Child, ChildStderr, Command};
+use crate::adb::{adb_shell, server_client, shell_quote};
+use crate::error::AdbError;
+use crate::settings::{AdbBackend, AdbStudioSettings};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum LogLevel {
+    Verbose,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl LogLevel {
+    pub const ALL: [LogLevel; 6] = [
+        LogLevel::Verbose,
+        LogLevel::Debug,
+        LogLevel::Info,
+        LogLevel::Warn,
+        LogLevel::Error,
+        LogLevel::Fatal,
+    ];
+
+    pub fn from_char(c: char) -> Option<Self> {
+        match c {
+            'V' => Some(LogLevel::Verbose),
+            'D' => Some(LogLevel::Debug),
+            'I' => Some(LogLevel::Info),
+            'W' => Some(LogLevel::Warn),
+            'E' => Some(LogLevel::Error),
+            // Assert ('A') is logged by wtf() and native aborts; treat it as fatal.
+            'F' | 'A' => Some(LogLevel::Fatal),
+            _ => None,
+        }
+    }
+
+    pub fn as_char(&self) -> char {
+        match self {
+            LogLevel::Verbose => 'V',
+            LogLevel::Debug => 'D',
+            LogLevel::Info => 'I',
+            LogLevel::Warn => 'W',
+            LogLevel::Error => 'E',
+            LogLevel::Fatal => 'F',
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LogLevel::Verbose => "Verbose",
+            LogLevel::Debug => "Debug",
+            LogLevel::Info => "Info",
+            LogLevel::Warn => "Warn",
+            LogLevel::Error => "Error",
+            LogLevel::Fatal => "Fatal",
+        }
+    }
+
+    pub fn color(&self) -> &'static str {
+        match self {
+            LogLevel::Verbose => "var(--secondary-text)",
+            LogLevel::Debug => "var(--accent-color)",
+            LogLevel::Info => "var(--success-color)",
+            LogLevel::Warn => "var(--warning-color)",
+            LogLevel::Error | LogLevel::Fatal => "var(--error-color)",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub pid: u32,
+    pub tid: u32,
+    pub level: LogLevel,
+    pub tag: String,
+    pub message: String,
+}
+
+/// Parses one line of `logcat -v threadtime`:
+/// `MM-DD HH:MM:SS.mmm  PID  TID L TAG     : message`, including the extra uid column that
+/// bugreports add with `-v uid`. Returns `None` for the `--------- beginning of main`
+/// separators and anything else that isn't an entry.
+pub fn parse_threadtime(line: &str) -> Option<LogEntry> {
+    let mut rest = line.trim_end_matches(['\r', '\n']);
+    let mut tokens = Vec::with_capacity(6);
+    while tokens.len() < 6 {
+        rest = rest.trim_start();
+        let end = rest.find(' ')?;
+        tokens.push(&rest[..end]);
+        rest = &rest[end..];
+        if tokens.len() == 5 && level_token(tokens[4]).is_some() {
+            break;
+        }
+    }
+    let (date, time) = (tokens[0], tokens[1]);
+    if !(date.len() == 5 || date.len() == 10) || !time.contains(':') {
+        return None;
+    }
+    let level = level_token(tokens[tokens.len() - 1])?;
+    let (pid, tid) = (tokens[tokens.len() - 3], tokens[tokens.len() - 2]);
+
+    // Tags are padded to a fixed width and may themselves contain spaces, so split on the
+    // first ": " rather than on whitespace.
+    let rest = rest.trim_start();
+    let (tag, message) = match rest.find(": ") {
+        Some(index) => (&rest[..index], &rest[index + 2..]),
+        None => (rest.strip_suffix(':').unwrap_or(rest), ""),
+    };
+    Some(LogEntry {
+        timestamp: format!("{} {}", date, time),
+        pid: pid.parse().ok()?,
+        tid: tid.parse().ok()?,
+        level,
+        tag: tag.trim_end().to_string(),
+        message: message.to_string(),
+    })
+}
+
+fn level_token(token: &str) -> Option<LogLevel> {
+    let mut chars = token.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => LogLevel::from_char(c),
+        _ => None,
+    }
+}
+
+impl LogEntry {
+    /// Formats the entry the way `logcat -v threadtime` prints it, so exports can be re-read.
+    pub fn to_threadtime(&self) -> String {
+        format!("{} {:>5} {:>5} {} {:<8}: {}", self.timestamp, self.pid, self.tid, self.level.as_char(), self.tag, self.message)
+    }
+}
+
+/// Client-side filter applied to parsed entries. Empty fields match everything; an empty pid
+/// set (a package that isn't running) matches nothing.
+#[derive(Debug, Clone, Default)]
+pub struct LogFilter {
+    pub min_level: Option<LogLevel>,
+    pub tag: String,
+    pub pids: Option<HashSet<u32>>,
+    pub pattern: Option<Regex>,
+}
+
+impl LogFilter {
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        if self.min_level.is_some_and(|level| entry.level < level) {
+            return false;
+        }
+        if !self.tag.is_empty() && !entry.tag.to_lowercase().contains(&self.tag.to_lowercase()) {
+            return false;
+        }
+        if self.pids.as_ref().is_some_and(|pids| !pids.contains(&entry.pid)) {
+            return false;
+        }
+        match &self.pattern {
+            Some(pattern) => pattern.is_match(&entry.message) || pattern.is_match(&entry.tag),
+            None => true,
+        }
+    }
+}
+
+/// Parses a pid filter such as `1234, 5678` or the output of `pidof`.
+pub fn parse_pids(text: &str) -> HashSet<u32> {
+    text.split(|c: char| c == ',' || c.is_whitespace())
+        .filter_map(|pid| pid.parse().ok())
+        .collect()
+}
+
+/// Fixed-capacity store for log entries; once full, the oldest entries are dropped.
+#[derive(Debug, Clone, Default)]
+pub struct LogBuffer {
+    entries: VecDeque<LogEntry>,
+    capacity: usize,
+    dropped: u64,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self { entries: VecDeque::new(), capacity: capacity.max(1), dropped: 0 }
+    }
+
+    pub fn extend(&mut self, entries: impl IntoIterator<Item = LogEntry>) {
+        for entry in entries {
+            if self.entries.len() == self.capacity {
+                self.entries.pop_front();
+                self.dropped += 1;
+            }
+            self.entries.push_back(entry);
+        }
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+        while self.entries.len() > self.capacity {
+            self.entries.pop_front();
+            self.dropped += 1;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.dropped = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Entries evicted to stay within capacity since the last clear.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    pub fn get(&self, index: usize) -> Option<&LogEntry> {
+        self.entries.get(index)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &LogEntry> {
+        self.entries.iter()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LogcatBuffer {
+    Main,
+    System,
+    Crash,
+    Events,
+    Radio,
+    Kernel,
+}
+
+impl LogcatBuffer {
+    pub const ALL: [LogcatBuffer; 6] = [
+        LogcatBuffer::Main,
+        LogcatBuffer::System,
+        LogcatBuffer::Crash,
+        LogcatBuffer::Events,
+        LogcatBuffer::Radio,
+        LogcatBuffer::Kernel,
+    ];
+
+    /// What `logcat` reads when no `-b` is given.
+    pub const DEFAULT: [LogcatBuffer; 3] = [LogcatBuffer::Main, LogcatBuffer::System, LogcatBuffer::Crash];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LogcatBuffer::Main => "main",
+            LogcatBuffer::System => "system",
+            LogcatBuffer::Crash => "crash",
+            LogcatBuffer::Events => "events",
+            LogcatBuffer::Radio => "radio",
+            LogcatBuffer::Kernel => "kernel",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LogcatStart {
+    /// Everything still held in the device buffers.
+    Buffered,
+    /// Only entries logged from now on.
+    Now,
+    /// The most recent N entries, then follow.
+    LastLines(u32),
+    /// Entries since a `MM-DD hh:mm:ss.mmm` timestamp.
+    Since(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogcatOptions {
+    pub buffers: Vec<LogcatBuffer>,
+    pub start: LogcatStart,
+    /// Print what is buffered and exit (`-d`) instead of following.
+    pub dump: bool,
+}
+
+impl Default for LogcatOptions {
+    fn default() -> Self {
+        Self { buffers: LogcatBuffer::DEFAULT.to_vec(), start: LogcatStart::Buffered, dump: false }
+    }
+}
+
+impl LogcatOptions {
+    pub fn args(&self) -> Vec<String> {
+        let mut args = vec!["-v".to_string(), "threadtime".to_string()];
+        for buffer in &self.buffers {
+            args.push("-b".to_string());
+            args.push(buffer.name().to_string());
+        }
+        if self.dump {
+            args.push("-d".to_string());
+        }
+        match &self.start {
+            LogcatStart::Buffered => {}
+            // -T 1 prints the newest buffered entry and then follows, which is as close to
+            // "from now" as logcat gets without knowing the device clock.
+            LogcatStart::Now => args.extend(["-T".to_string(), "1".to_string()]),
+            LogcatStart::LastLines(count) => args.extend([if self.dump { "-t" } else { "-T" }.to_string(), count.to_string()]),
+            LogcatStart::Since(time) => args.extend([if self.dump { "-t" } else { "-T" }.to_string(), time.clone()]),
+        }
+        args
+    }
+}
+
+/// A running `logcat` on one device, read line by line.
+pub struct LogcatStream {
+    lines: Lines<BufReader<Box<dyn AsyncRead + Unpin + Send>>>,
+    // The child is held so `adb logcat` is killed when the stream is dropped.
+    child: Option<(Child, ChildStderr)>,
+}
+
+impl LogcatStream {
+    pub async fn open(settings: &AdbStudioSettings, serial: &str, options: &LogcatOptions) -> Result<Self, AdbError> {
+        let args = options.args();
+        let (reader, child): (Box<dyn AsyncRead + Unpin + Send>, _) = match settings.backend {
+            AdbBackend::Server => {
+                let command = args.iter().map(|a| shell_quote(a)).collect::<Vec<_>>().join(" ");
+                let conn = server_client(settings).open(serial, &format!("exec:logcat {}", command)).await?;
+                (Box::new(conn.into_split().0), None)
+            }
+            AdbBackend::Executable => {
+                let mut child = Command::new(settings.adb_program())
+                    .args(["-s", serial, "logcat"])
+                    .args(&args)
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped())
+                    .kill_on_drop(true)
+                    .spawn()
+                    .map_err(|e| AdbError::from_spawn(settings.adb_program(), e))?;
+                let stdout = child.stdout.take().ok_or_else(|| AdbError::Spawn("no stdout".to_string()))?;
+                let stderr = child.stderr.take().ok_or_else(|| AdbError::Spawn("no stderr".to_string()))?;
+                (Box::new(stdout), Some((child, stderr)))
+            }
+        };
+        Ok(Self { lines: BufReader::new(reader).lines(), child })
+    }
+
+    /// The next line, or `None` once logcat has exited.
+    pub async fn next_line(&mut self) -> Result<Option<String>, AdbError> {
+        Ok(self.lines.next_line().await?)
+    }
+
+    /// Waits for logcat to exit after the stream has ended and reports how it went.
+    pub async fn finish(self) -> Result<(), AdbError> {
+        let Some((mut child, mut stderr)) = self.child else { return Ok(()) };
+        let mut message = String::new();
+        let _ = stderr.read_to_string(&mut message).await;
+        let status = child.wait().await?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(AdbError::classify(status.code(), &message))
+        }
+    }
+}
+
+pub async fn clear_device_logs(settings: &AdbStudioSettings, serial: &str, buffers: &[LogcatBuffer]) -> Result<(), AdbError> {
+    let buffers = buffers.iter().map(|b| format!(" -b {}", b.name())).collect::<String>();
+    adb_shell(settings, serial, &format!("logcat -c{}", buffers)).await.map(|_| ())
+}
+
+pub fn export_text<'a>(entries: impl IntoIterator<Item = &'a LogEntry>) -> String {
+    entries.into_iter().map(|e| e.to_threadtime() + "\n").collect()
+}
+
+pub fn export_jsonl<'a>(entries: impl IntoIterator<Item = &'a LogEntry>) -> Result<String, AdbError> {
+    let mut output = String::new();
+    for entry in entries {
+        output.push_str(&serde_json::to_string(entry).map_err(|e| AdbError::Io(e.to_string()))?);
+        output.push('\n');
+    }
+    Ok(output)
+}
+
+/// Reads a saved log for offline viewing: a JSON Lines export, a plain `threadtime` capture, or
+/// an `adb bugreport` (the `.zip` or the text report inside it).
+pub fn read_log_file(path: &Path) -> Result<Vec<LogEntry>, AdbError> {
+    let is_zip = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("zip"));
+    let contents = if is_zip { read_bugreport_zip(path)? } else { String::from_utf8_lossy(&std::fs::read(path)?).to_string() };
+    Ok(parse_log_text(&contents))
+}
+
+pub fn parse_log_text(contents: &str) -> Vec<LogEntry> {
+    let first_line = contents.lines().find(|l| !l.trim().is_empty()).unwrap_or_default();
+    if first_line.trim_start().starts_with('{') {
+        return contents.lines().filter_map(|line| serde_json::from_str(line).ok()).collect();
+    }
+    if contents.starts_with("========================================================") || contents.contains("== dumpstate: ") {
+        return bugreport_logcat_lines(contents).filter_map(parse_threadtime).collect();
+    }
+    contents.lines().filter_map(parse_threadtime).collect()
+}
+
+/// Lines from the logcat sections of a bugreport (`------ SYSTEM LOG (logcat ...) ------`,
+/// `EVENT LOG`, `RADIO LOG`, `LAST LOGCAT`), skipping the dumpsys output in between.
+fn bugreport_logcat_lines(contents: &str) -> impl Iterator<Item = &str> {
+    let mut in_logcat = false;
+    contents.lines().filter(move |line| {
+        if line.starts_with("------ ") {
+            in_logcat = line.contains("(logcat ") && !line.contains(" was the duration of ");
+            return false;
+        }
+        in_logcat
+    })
+}
+
+fn read_bugreport_zip(path: &Path) -> Result<String, AdbError> {
+    let invalid = |e: zip::result::ZipError| AdbError::Io(format!("{}: {}", path.display(), e));
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(path)?).map_err(invalid)?;
+    let name = archive
+        .file_names()
+        .find(|n| n.starts_with("bugreport") && n.ends_with(".txt"))
+        .map(str::to_string)
+        .ok_or_else(|| AdbError::NoSuchFile(format!("{} does not contain a bugreport-*.txt", path.display())))?;
+    let mut contents = Vec::new();
+    archive.by_name(&name).map_err(invalid)?.read_to_end(&mut contents)?;
+    Ok(String::from_utf8_lossy(&contents).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(line: &str) -> LogEntry {
+        parse_threadtime(line).unwrap_or_else(|| panic!("not parsed: {:?}", line))
+    }
+
+    #[test]
+    fn parses_threadtime_lines() {
+        let e = entry("10-18 12:34:56.789  1502  1520 I ActivityManager: Start proc 8243:com.example.app/u0a234 for top-activity {com.example.app/com.example.app.MainActivity}");
+        assert_eq!(e.timestamp, "10-18 12:34:56.789");
+        assert_eq!((e.pid, e.tid), (1502, 1520));
+        assert_eq!(e.level, LogLevel::Info);
+        assert_eq!(e.tag, "ActivityManager");
+        assert!(e.message.starts_with("Start proc 8243:com.example.app/u0a234"));
+
+        // Short tags are padded, and messages can contain ": " themselves.
+        let e = entry("10-18 12:34:57.001  8243  8243 D OkHttp  : --> GET https://example.com/api: 200\r\n");
+        assert_eq!(e.tag, "OkHttp");
+        assert_eq!(e.message, "--> GET https://example.com/api: 200");
+
+        // Tags may contain spaces.
+        let e = entry("10-18 12:34:57.002   612   640 W Binder:612_2: Slow operation");
+        assert_eq!(e.tag, "Binder:612_2");
+        let e = entry("10-18 12:34:57.003  2210  2244 E Unity Main: NullReferenceException");
+        assert_eq!(e.tag, "Unity Main");
+        assert_eq!(e.level, LogLevel::Error);
+
+        let e = entry("10-18 12:34:57.004  9120  9151 A libc    : Fatal signal 6 (SIGABRT)");
+        assert_eq!(e.level, LogLevel::Fatal);
+
+        let e = entry("10-18 12:34:57.005  1502  1502 V WindowManager:");
+        assert_eq!((e.tag.as_str(), e.message.as_str()), ("WindowManager", ""));
+    }
+
+    #[test]
+    fn parses_year_and_uid_columns() {
+        let e = entry("2024-10-18 12:34:56.789  1502  1520 I ActivityManager: Killing 8243");
+        assert_eq!(e.timestamp, "2024-10-18 12:34:56.789");
+        assert_eq!(e.pid, 1502);
+
+        // Bugreports log with -v uid.
+        let e = entry("10-18 12:34:56.789 u0_a234  8243  8260 W System  : A resource failed to call close.");
+        assert_eq!((e.pid, e.tid), (8243, 8260));
+        assert_eq!(e.level, LogLevel::Warn);
+        assert_eq!(e.tag, "System");
+        let e = entry("10-18 12:34:56.789  1000  1502  1520 I ActivityManager: Killing 8243");
+        assert_eq!((e.pid, e.tid), (1502, 1520));
+    }
+
+    #[test]
+    fn skips_lines_that_are_not_entries() {
+        for line in [
+            "--------- beginning of main",
+            "--------- beginning of crash",
+            "",
+            "I/ActivityManager( 1502): Start proc (brief format)",
+            "10-18 12:34:56.789  1502  1520 I",
+            "10-18 12:34:56.789  abcd  1520 I Tag: pid isn't numeric",
+            "12:34:56.789 10-18  1502  1520 X Tag: unknown level",
+        ] {
+            assert_eq!(parse_threadtime(line), None, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn threadtime_round_trips() {
+        let line = "10-18 12:34:57.001  8243  8243 D OkHttp  : --> GET https://example.com/api";
+        assert_eq!(entry(line).to_threadtime(), line);
+        let e = entry("10-18 12:34:57.003  2210  2244 E Unity Main: NullReferenceException");
+        assert_eq!(entry(&e.to_threadtime()), e);
+    }
+
+    #[test]
+    fn filters_entries() {
+        let entries = [
+            entry("10-18 12:34:56.789  1502  1520 I ActivityManager: Start proc 8243:com.example.app"),
+            entry("10-18 12:34:57.001  8243  8243 D OkHttp  : --> GET https://example.com/api"),
+            entry("10-18 12:34:57.002  8243  8260 E AndroidRuntime: FATAL EXCEPTION: main"),
+        ];
+        let matching = |filter: &LogFilter| entries.iter().filter(|e| filter.matches(e)).map(|e| e.tag.as_str()).collect::<Vec<_>>();
+
+        assert_eq!(matching(&LogFilter::default()).len(), 3);
+        assert_eq!(matching(&LogFilter { min_level: Some(LogLevel::Info), ..Default::default() }), ["ActivityManager", "AndroidRuntime"]);
+        assert_eq!(matching(&LogFilter { tag: "activitym".to_string(), ..Default::default() }), ["ActivityManager"]);
+        assert_eq!(matching(&LogFilter { pids: Some(parse_pids("8243")), ..Default::default() }), ["OkHttp", "AndroidRuntime"]);
+        assert!(matching(&LogFilter { pids: Some(HashSet::new()), ..Default::default() }).is_empty());
+
+        // The pattern is tried against the tag as well as the message.
+        let pattern = |p: &str| LogFilter { pattern: Some(Regex::new(p).unwrap()), ..Default::default() };
+        assert_eq!(matching(&pattern("GET https?://")), ["OkHttp"]);
+        assert_eq!(matching(&pattern("^Android")), ["AndroidRuntime"]);
+
+        let combined = LogFilter { min_level: Some(LogLevel::Error), pids: Some(parse_pids("8243")), ..pattern("FATAL") };
+        assert_eq!(matching(&combined), ["AndroidRuntime"]);
+    }
+
+    #[test]
+    fn parses_pid_lists() {
+        assert_eq!(parse_pids("1234, 5678"), HashSet::from([1234, 5678]));
+        assert_eq!(parse_pids("8243 8260\n"), HashSet::from([8243, 8260]));
+        assert!(parse_pids("").is_empty());
+        assert!(parse_pids("pidof: no such process").is_empty());
+    }
+}
@@ -0,0 +1,527 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+use dioxus::prelude::*;
+use tokio::net::UdpSocket;
+use crate::adb::{adb_mdns_services, adb_pair};
+use crate::app::AppState;
+use crate::error::AdbError;
+use crate::settings::AdbStudioSettings;
+use crate::ui::ErrorNotice;
+use crate::wireless::connect_and_remember;
+
+pub const PAIRING_SERVICE: &str = "_adb-tls-pairing._tcp";
+pub const CONNECT_SERVICE: &str = "_adb-tls-connect._tcp";
+pub const MDNS_ADDR: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(224, 0, 0, 251)), 5353);
+/// How long the native browser listens for answers to one query.
+const BROWSE_WAIT: Duration = Duration::from_millis(1500);
+const DISCOVERY_INTERVAL: Duration = Duration::from_secs(5);
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_SRV: u16 = 33;
+const CLASS_IN: u16 = 1;
+
+/// A wireless debugging service seen on the network.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MdnsService {
+    pub name: String,
+    pub service_type: String,
+    pub address: String,
+}
+
+impl MdnsService {
+    pub fn is_pairing(&self) -> bool {
+        self.service_type == PAIRING_SERVICE
+    }
+}
+
+/// Parses `adb mdns services`: one `name  type  host:port` line per service.
+pub fn parse_mdns_services(output: &str) -> Vec<MdnsService> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let (name, service_type, address) = (fields.next()?, fields.next()?, fields.next()?);
+            let service_type = service_type.trim_end_matches('.');
+            service_type.starts_with("_adb").then(|| MdnsService {
+                name: name.to_string(),
+                service_type: service_type.to_string(),
+                address: address.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// A PTR query for each service type, in the `<type>.local` form responders answer to.
+pub fn build_query(service_types: &[&str]) -> Vec<u8> {
+    let mut packet = vec![0u8; 4];
+    packet.extend_from_slice(&(service_types.len() as u16).to_be_bytes());
+    packet.extend_from_slice(&[0; 6]);
+    for service_type in service_types {
+        for label in service_type.split('.').chain(["local"]) {
+            packet.push(label.len() as u8);
+            packet.extend_from_slice(label.as_bytes());
+        }
+        packet.push(0);
+        packet.extend_from_slice(&TYPE_PTR.to_be_bytes());
+        packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+    }
+    packet
+}
+
+/// Reads a possibly compressed name starting at `pos`, returning it and the offset just past it.
+fn read_name(packet: &[u8], mut pos: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut end = None;
+    // Bounds the number of compression pointers followed, so a looping packet can't hang us.
+    for _ in 0..64 {
+        let len = *packet.get(pos)? as usize;
+        if len == 0 {
+            return Some((labels.join("."), end.unwrap_or(pos + 1)));
+        }
+        if len & 0xC0 == 0xC0 {
+            let offset = ((len & 0x3F) << 8) | *packet.get(pos + 1)? as usize;
+            end.get_or_insert(pos + 2);
+            pos = offset;
+            continue;
+        }
+        labels.push(String::from_utf8_lossy(packet.get(pos + 1..pos + 1 + len)?).into_owned());
+        pos += 1 + len;
+    }
+    None
+}
+
+fn read_u16(packet: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*packet.get(pos)?, *packet.get(pos + 1)?]))
+}
+
+struct SrvRecord {
+    port: u16,
+    target: String,
+    /// Where the answer came from, used when no A record for the target was included.
+    sender: IpAddr,
+}
+
+/// Records gathered from mDNS answers, keyed by owner name. Responders spread a service's PTR,
+/// SRV and A records over one or more packets, so they are joined up only when listing services.
+#[derive(Default)]
+pub struct MdnsRecords {
+    instances: Vec<String>,
+    services: HashMap<String, SrvRecord>,
+    hosts: HashMap<String, Ipv4Addr>,
+}
+
+impl MdnsRecords {
+    pub fn add_packet(&mut self, packet: &[u8], sender: IpAddr) {
+        let _ = self.parse_packet(packet, sender);
+    }
+
+    fn parse_packet(&mut self, packet: &[u8], sender: IpAddr) -> Option<()> {
+        let questions = read_u16(packet, 4)?;
+        let records = read_u16(packet, 6)? as usize + read_u16(packet, 8)? as usize + read_u16(packet, 10)? as usize;
+        let mut pos = 12;
+        for _ in 0..questions {
+            pos = read_name(packet, pos)?.1 + 4;
+        }
+        for _ in 0..records {
+            let (owner, next) = read_name(packet, pos)?;
+            let record_type = read_u16(packet, next)?;
+            let len = read_u16(packet, next + 8)? as usize;
+            let data = next + 10;
+            packet.get(data..data + len)?;
+            match record_type {
+                TYPE_PTR => {
+                    let (instance, _) = read_name(packet, data)?;
+                    if !self.instances.contains(&instance) {
+                        self.instances.push(instance);
+                    }
+                }
+                TYPE_SRV => {
+                    let port = read_u16(packet, data + 4)?;
+                    let (target, _) = read_name(packet, data + 6)?;
+                    self.services.insert(owner.to_lowercase(), SrvRecord { port, target, sender });
+                }
+                TYPE_A if len == 4 => {
+                    let ip = Ipv4Addr::new(packet[data], packet[data + 1], packet[data + 2], packet[data + 3]);
+                    self.hosts.insert(owner.to_lowercase(), ip);
+                }
+                _ => {}
+            }
+            pos = data + len;
+        }
+        Some(())
+    }
+
+    /// The adb services whose instance, port and address are all known.
+    pub fn services(&self) -> Vec<MdnsService> {
+        self.instances
+            .iter()
+            .filter_map(|instance| {
+                let service_type = [CONNECT_SERVICE, PAIRING_SERVICE]
+                    .into_iter()
+                    .find(|t| instance.ends_with(&format!(".{}.local", t)))?;
+                let srv = self.services.get(&instance.to_lowercase())?;
+                let host = self.hosts.get(&srv.target.to_lowercase()).copied().map(IpAddr::V4).unwrap_or(srv.sender);
+                Some(MdnsService {
+                    name: instance[..instance.len() - service_type.len() - ".local".len() - 1].to_string(),
+                    service_type: service_type.to_string(),
+                    address: SocketAddr::new(host, srv.port).to_string(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Browses for adb services without the adb server, by sending one query to `target` and
+/// collecting answers for `wait`.
+///
+/// The query goes out from an ephemeral port, so responders answer it directly rather than on
+/// the multicast group, and `target` can be any responder, not just [`MDNS_ADDR`].
+pub async fn browse(target: SocketAddr, wait: Duration) -> Result<Vec<MdnsService>, AdbError> {
+    let socket = UdpSocket::bind(if target.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" }).await?;
+    if target.is_ipv4() {
+        socket.set_multicast_ttl_v4(255)?;
+    }
+    socket.send_to(&build_query(&[CONNECT_SERVICE, PAIRING_SERVICE]), target).await?;
+    let mut records = MdnsRecords::default();
+    let mut buf = vec![0u8; 9000];
+    let deadline = tokio::time::Instant::now() + wait;
+    while let Ok(received) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buf)).await {
+        let (len, from) = received?;
+        records.add_packet(&buf[..len], from.ip());
+    }
+    Ok(records.services())
+}
+
+/// Lists nearby wireless debugging services, combining what the adb server has discovered with
+/// a native browse, since older servers have no mDNS support and newer ones only report what
+/// their own backend found.
+pub async fn discover(settings: &AdbStudioSettings) -> Result<Vec<MdnsService>, AdbError> {
+    let (from_server, native) = tokio::join!(adb_mdns_services(settings), browse(MDNS_ADDR, BROWSE_WAIT));
+    let mut services = match (from_server, native) {
+        (Err(e), Err(_)) => return Err(e),
+        (from_server, native) => {
+            let mut services = from_server.map(|output| parse_mdns_services(&output)).unwrap_or_default();
+            services.extend(native.unwrap_or_default());
+            services
+        }
+    };
+    let mut seen = Vec::new();
+    services.retain(|s| {
+        let key = (s.name.clone(), s.service_type.clone());
+        let fresh = !seen.contains(&key);
+        seen.push(key);
+        fresh
+    });
+    services.sort_by(|a, b| a.name.cmp(&b.name).then(a.service_type.cmp(&b.service_type)));
+    Ok(services)
+}
+
+#[component]
+pub fn NearbyDevices() -> Element {
+    let settings = use_context::<Signal<AdbStudioSettings>>();
+    let app_state = use_context::<Signal<AppState>>();
+    let mut services = use_signal(Vec::<MdnsService>::new);
+    let mut pair_codes = use_signal(HashMap::<String, String>::new);
+    let mut busy = use_signal(|| None::<String>);
+    let mut status = use_signal(String::new);
+    let mut error = use_signal(|| None::<AdbError>);
+
+    use_future(move || async move {
+        loop {
+            let settings_clone = settings.peek().clone();
+            if let Ok(found) = discover(&settings_clone).await {
+                services.set(found);
+            }
+            tokio::time::sleep(DISCOVERY_INTERVAL).await;
+        }
+    });
+
+    // Services for devices that are already connected are covered by the table above.
+    let devices = app_state.read().devices.clone();
+    let nearby: Vec<MdnsService> = services
+        .read()
+        .iter()
+        .filter(|s| {
+            s.is_pairing()
+                || !devices.iter().any(|d| d.serial == s.address || d.serial.starts_with(&format!("{}.", s.name)))
+        })
+        .cloned()
+        .collect();
+
+    rsx! {
+        div {
+            class: "device-info",
+            h3 { "Nearby Devices" }
+            if !status.read().is_empty() {
+                p { style: "font-size: 0.875rem; color: var(--secondary-text); margin-bottom: 8px;", "{status}" }
+            }
+            if let Some(e) = error() {
+                ErrorNotice { error: e }
+            }
+            if nearby.is_empty() {
+                p { style: "font-size: 0.875rem; color: var(--secondary-text);",
+                    "No devices are advertising wireless debugging on this network."
+                }
+            } else {
+                table {
+                    style: "margin-top: 0;",
+                    thead {
+                        tr {
+                            th { "Name" }
+                            th { "Service" }
+                            th { "Address" }
+                            th { "Actions" }
+                        }
+                    }
+                    tbody {
+                        for service in nearby {
+                            {
+                                let address = service.address.clone();
+                                let is_busy = busy.read().as_deref() == Some(address.as_str());
+                                let code = pair_codes.read().get(&address).cloned().unwrap_or_default();
+                                rsx! {
+                                    tr {
+                                        key: "{service.name}{service.service_type}",
+                                        td { "{service.name}" }
+                                        td { if service.is_pairing() { "Pairing" } else { "Debugging" } }
+                                        td { "{service.address}" }
+                                        td {
+                                            if service.is_pairing() {
+                                                {
+                                                    let code_address = address.clone();
+                                                    rsx! {
+                                                        input {
+                                                            r#type: "text",
+                                                            placeholder: "Pairing code",
+                                                            style: "max-width: 120px; margin-right: 6px;",
+                                                            value: "{code}",
+                                                            oninput: move |e| {
+                                                                pair_codes.write().insert(code_address.clone(), e.value());
+                                                            },
+                                                        }
+                                                        button {
+                                                            disabled: is_busy || code.trim().is_empty(),
+                                                            onclick: move |_| {
+                                                                let address = address.clone();
+                                                                let code = code.clone();
+                                                                spawn(async move {
+                                                                    busy.set(Some(address.clone()));
+                                                                    error.set(None);
+                                                                    let settings_clone = settings.read().clone();
+                                                                    match adb_pair(&settings_clone, &address, code.trim()).await {
+                                                                        Ok(message) => {
+                                                                            pair_codes.write().remove(&address);
+                                                                            status.set(message);
+                                                                        }
+                                                                        Err(e) => error.set(Some(e)),
+                                                                    }
+                                                                    busy.set(None);
+                                                                });
+                                                            },
+                                                            i { class: "fas fa-key" }
+                                                            "Pair"
+                                                        }
+                                                    }
+                                                }
+                                            } else {
+                                                {
+                                                    let name = service.name.clone();
+                                                    rsx! {
+                                                        button {
+                                                            disabled: is_busy,
+                                                            onclick: move |_| {
+                                                                let address = address.clone();
+                                                                let name = name.clone();
+                                                                spawn(async move {
+                                                                    busy.set(Some(address.clone()));
+                                                                    error.set(None);
+                                                                    match connect_and_remember(settings, &address, &name).await {
+                                                                        Ok(message) => status.set(message),
+                                                                        Err(e) => error.set(Some(e)),
+                                                                    }
+                                                                    busy.set(None);
+                                                                });
+                                                            },
+                                                            i { class: "fas fa-link" }
+                                                            "Connect"
+                                                        }
+                                                    }
+                                                }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn push_name(packet: &mut Vec<u8>, name: &str) {
+        for label in name.split('.') {
+            packet.push(label.len() as u8);
+            packet.extend_from_slice(label.as_bytes());
+        }
+        packet.push(0);
+    }
+
+    fn push_record(packet: &mut Vec<u8>, owner: &[u8], record_type: u16, data: &[u8]) {
+        packet.extend_from_slice(owner);
+        packet.extend_from_slice(&record_type.to_be_bytes());
+        packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+        packet.extend_from_slice(&120u32.to_be_bytes());
+        packet.extend_from_slice(&(data.len() as u16).to_be_bytes());
+        packet.extend_from_slice(data);
+    }
+
+    /// An answer the way Android's responder sends it: a PTR to the instance, an SRV whose owner
+    /// is compressed to point back at the PTR's data, and optionally an A record for the host.
+    fn response(with_address: bool) -> Vec<u8> {
+        let mut packet = vec![0, 0, 0x84, 0, 0, 0, 0, 2, 0, 0, 0, with_address as u8];
+        let mut owner = Vec::new();
+        push_name(&mut owner, "_adb-tls-connect._tcp.local");
+        let mut instance = Vec::new();
+        push_name(&mut instance, "adb-R5CT1234-AbCdEf._adb-tls-connect._tcp.local");
+        let instance_offset = packet.len() + owner.len() + 10;
+        push_record(&mut packet, &owner, TYPE_PTR, &instance);
+
+        let pointer = [0xC0 | (instance_offset >> 8) as u8, instance_offset as u8];
+        let mut srv = vec![0, 0, 0, 0];
+        srv.extend_from_slice(&37215u16.to_be_bytes());
+        push_name(&mut srv, "Android-2.local");
+        push_record(&mut packet, &pointer, TYPE_SRV, &srv);
+
+        if with_address {
+            let mut host = Vec::new();
+            push_name(&mut host, "Android-2.local");
+            push_record(&mut packet, &host, TYPE_A, &[192, 168, 1, 23]);
+        }
+        packet
+    }
+
+    #[test]
+    fn parses_adb_mdns_services() {
+        let output = "List of discovered mdns services\n\
+            adb-R5CT1234-AbCdEf\t_adb-tls-connect._tcp.\t192.168.1.23:37215\n\
+            adb-R5CT1234-AbCdEf\t_adb-tls-pairing._tcp.\t192.168.1.23:41234\n\
+            Android_printer\t_ipp._tcp.\t192.168.1.40:631\n";
+        let services = parse_mdns_services(output);
+        assert_eq!(
+            services,
+            vec![
+                MdnsService {
+                    name: "adb-R5CT1234-AbCdEf".to_string(),
+                    service_type: CONNECT_SERVICE.to_string(),
+                    address: "192.168.1.23:37215".to_string(),
+                },
+                MdnsService {
+                    name: "adb-R5CT1234-AbCdEf".to_string(),
+                    service_type: PAIRING_SERVICE.to_string(),
+                    address: "192.168.1.23:41234".to_string(),
+                },
+            ]
+        );
+        assert!(!services[0].is_pairing());
+        assert!(services[1].is_pairing());
+        assert!(parse_mdns_services("").is_empty());
+        assert!(parse_mdns_services("* daemon started successfully\n").is_empty());
+    }
+
+    #[test]
+    fn query_asks_for_each_service_type() {
+        let query = build_query(&[CONNECT_SERVICE]);
+        let mut expected = vec![0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+        push_name(&mut expected, "_adb-tls-connect._tcp.local");
+        expected.extend_from_slice(&[0, 12, 0, 1]);
+        assert_eq!(query, expected);
+    }
+
+    #[test]
+    fn reads_compressed_names() {
+        let mut packet = vec![0u8; 12];
+        push_name(&mut packet, "_tcp.local");
+        // "device" followed by a pointer to "_tcp.local" at offset 12.
+        let start = packet.len();
+        packet.extend_from_slice(&[6, b'd', b'e', b'v', b'i', b'c', b'e', 0xC0, 12]);
+        assert_eq!(read_name(&packet, start), Some(("device._tcp.local".to_string(), start + 9)));
+        assert_eq!(read_name(&packet, 12), Some(("_tcp.local".to_string(), start)));
+    }
+
+    #[test]
+    fn stops_following_looping_pointers() {
+        // A pointer to itself, and two pointers to each other.
+        assert_eq!(read_name(&[0xC0, 0], 0), None);
+        assert_eq!(read_name(&[1, b'a', 0xC0, 4, 0xC0, 0], 0), None);
+        // Truncated names and pointers past the end.
+        assert_eq!(read_name(&[5, b'a'], 0), None);
+        assert_eq!(read_name(&[0xC0, 40], 0), None);
+    }
+
+    #[test]
+    fn joins_ptr_srv_and_a_records() {
+        let mut records = MdnsRecords::default();
+        records.add_packet(&response(true), IpAddr::V4(Ipv4Addr::new(10, 0, 0, 5)));
+        assert_eq!(
+            records.services(),
+            vec![MdnsService {
+                name: "adb-R5CT1234-AbCdEf".to_string(),
+                service_type: CONNECT_SERVICE.to_string(),
+                address: "192.168.1.23:37215".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn falls_back_to_sender_address() {
+        let mut records = MdnsRecords::default();
+        records.add_packet(&response(false), IpAddr::V4(Ipv4Addr::new(10, 0, 0, 5)));
+        assert_eq!(records.services()[0].address, "10.0.0.5:37215");
+    }
+
+    #[test]
+    fn ignores_malformed_packets() {
+        let mut records = MdnsRecords::default();
+        records.add_packet(&[0xC0, 0, 1], IpAddr::V4(Ipv4Addr::LOCALHOST));
+        let mut looping = response(true);
+        looping[12] = 0xC0;
+        looping[13] = 12;
+        records.add_packet(&looping, IpAddr::V4(Ipv4Addr::LOCALHOST));
+        let truncated = response(true);
+        records.add_packet(&truncated[..truncated.len() - 3], IpAddr::V4(Ipv4Addr::LOCALHOST));
+        assert!(records.services().iter().all(|s| s.address.ends_with(":37215")));
+    }
+
+    #[tokio::test]
+    async fn browses_a_local_responder() {
+        let responder = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let target = responder.local_addr().unwrap();
+        let answered = tokio::spawn(async move {
+            let mut buf = [0u8; 1500];
+            let (len, from) = responder.recv_from(&mut buf).await.unwrap();
+            // The answer comes in two packets: PTR and SRV first, then the address.
+            let full = response(true);
+            let split = full.len() - 31;
+            let mut first = full[..split].to_vec();
+            first[11] = 0;
+            let mut second = vec![0, 0, 0x84, 0, 0, 0, 0, 1, 0, 0, 0, 0];
+            second.extend_from_slice(&full[split..]);
+            responder.send_to(&first, from).await.unwrap();
+            responder.send_to(&second, from).await.unwrap();
+            buf[..len].to_vec()
+        });
+        let found = browse(target, Duration::from_millis(300)).await.unwrap();
+        assert_eq!(answered.await.unwrap(), build_query(&[CONNECT_SERVICE, PAIRING_SERVICE]));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].name, "adb-R5CT1234-AbCdEf");
+        assert_eq!(found[0].address, "192.168.1.23:37215");
+    }
+}
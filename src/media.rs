@@ -1,66 +1,264 @@
+use std::path::PathBuf;
 use dioxus::prelude::*;
 use rfd::AsyncFileDialog;
-use crate::adb::{adb_screenshot, adb_start_screenrecord, adb_stop_screenrecord};
+use crate::adb::adb_screenshot;
 use crate::app::AppState;
+use crate::error::AdbError;
+use crate::mirror::ScreenMirror;
+use crate::screenrecord::{RecordOptions, RecordingControls, RecordingEvent, RecordingSession, MAX_SEGMENT_SECS};
+use crate::screenshots::{ScreenshotEditor, ScreenshotGallery, ScreenshotHistory};
 use crate::settings::AdbStudioSettings;
+use crate::ui::ErrorNotice;
+
+#[derive(Clone, Copy, PartialEq)]
+enum MediaTab {
+    Capture,
+    Mirror,
+}
 
 #[component]
 pub fn MediaManager() -> Element {
+    let mut tab = use_signal(|| MediaTab::Capture);
+
+    rsx! {
+        div {
+            h2 { "Screenshot & Screen Recording" }
+            div {
+                style: "display: flex; gap: 4px; margin-bottom: 12px;",
+                button {
+                    class: if tab() == MediaTab::Capture { "btn" } else { "btn btn-secondary" },
+                    onclick: move |_| tab.set(MediaTab::Capture),
+                    i { class: "fas fa-camera" }
+                    "Capture"
+                }
+                button {
+                    class: if tab() == MediaTab::Mirror { "btn" } else { "btn btn-secondary" },
+                    onclick: move |_| tab.set(MediaTab::Mirror),
+                    i { class: "fas fa-desktop" }
+                    "Mirror"
+                }
+            }
+            match tab() {
+                MediaTab::Capture => rsx! { MediaCapture {} },
+                MediaTab::Mirror => rsx! { ScreenMirror {} },
+            }
+        }
+    }
+}
+
+#[component]
+fn MediaCapture() -> Element {
     let mut logs = use_signal(String::new);
-    let mut is_recording = use_signal(|| false);
+    let mut recording = use_signal(|| None::<RecordingControls>);
+    let mut record_options = use_signal(RecordOptions::default);
+    let mut output_dir = use_signal(|| None::<PathBuf>);
+    let mut record_error = use_signal(|| None::<AdbError>);
     let app_state = use_context::<Signal<AppState>>();
     let settings = use_context::<Signal<AdbStudioSettings>>();
+    let mut history = use_context::<Signal<ScreenshotHistory>>();
+    let mut selected_shot = use_signal(|| history.peek().shots.last().map(|s| s.id));
+    let mut is_capturing = use_signal(|| false);
+    let is_recording = recording.read().is_some();
+
+    // Leaving the view ends the recording normally; the session still pulls what it recorded.
+    use_drop(move || {
+        if let Some(controls) = recording.peek().as_ref() {
+            controls.stop();
+        }
+    });
 
     rsx! {
         div {
-            h2 { "Screenshot & Screen Recording" }
             div {
                 button { 
                     class: "btn",
+                    disabled: is_capturing(),
                     onclick: move |_| {
                         spawn(async move {
-                            if let Some(device) = &app_state.read().selected_device {
-                                if let Some(file) = AsyncFileDialog::new().save_file().await {
-                                    let settings_clone = settings.read().clone();
-                                    let result = adb_screenshot(&settings_clone, &device.serial, file.path().to_str().unwrap()).await;
-                                    logs.set(format!("{:?}", result));
+                            let device = app_state.read().selected_device.clone();
+                            if let Some(device) = device {
+                                is_capturing.set(true);
+                                let settings_clone = settings.read().clone();
+                                let result = adb_screenshot(&settings_clone, &device.serial).await
+                                    .and_then(|png| history.write().add(&device.serial, device.name(), &png));
+                                match result {
+                                    Ok(id) => {
+                                        selected_shot.set(Some(id));
+                                        logs.set(format!("Screenshot captured from {}", device.name()));
+                                    }
+                                    Err(e) => logs.set(format!("Screenshot failed: {}", e)),
                                 }
+                                is_capturing.set(false);
                             } else {
                                 logs.set("No device selected.".to_string());
                             }
                         });
                     },
                     i { class: "fas fa-camera" }
-                    "Capture Screenshot" 
+                    if is_capturing() { "Capturing..." } else { "Capture Screenshot" }
                 }
                 button { 
                     class: "btn",
                     onclick: move |_| {
+                        if let Some(controls) = recording() {
+                            controls.stop();
+                            logs.set("Stopping recording...".to_string());
+                            return;
+                        }
                         spawn(async move {
-                            if let Some(device) = &app_state.read().selected_device {
-                                let settings_clone = settings.read().clone();
-                                if !is_recording() {
-                                    let result = adb_start_screenrecord(&settings_clone, &device.serial, "/sdcard/video.mp4").await;
-                                    logs.set(format!("{:?}", result));
-                                    is_recording.set(true);
-                                } else {
-                                    let result = adb_stop_screenrecord(&settings_clone, &device.serial).await;
-                                    logs.set(format!("{:?}", result));
-                                    is_recording.set(false);
-                                }
-                            } else {
+                            let device = app_state.read().selected_device.clone();
+                            let Some(device) = device else {
                                 logs.set("No device selected.".to_string());
+                                return;
+                            };
+                            if output_dir().is_none() {
+                                match AsyncFileDialog::new().set_title("Save recordings to").pick_folder().await {
+                                    Some(folder) => output_dir.set(Some(folder.path().to_path_buf())),
+                                    None => return,
+                                }
+                            }
+                            let Some(dir) = output_dir() else { return };
+                            record_error.set(None);
+                            let settings_clone = settings.read().clone();
+                            let mut session = RecordingSession::start(&settings_clone, &device.serial, record_options(), &dir);
+                            recording.set(Some(session.controls()));
+                            logs.set(format!("Recording {}...", device.name()));
+                            while let Some(event) = session.next_event().await {
+                                match event {
+                                    RecordingEvent::SegmentStarted(1) => {}
+                                    RecordingEvent::SegmentStarted(n) => logs.set(format!("Recording segment {}...", n)),
+                                    RecordingEvent::SegmentSaved(path) => logs.set(format!("Saved {}", path.display())),
+                                    RecordingEvent::Finishing => logs.set("Pulling recording from device...".to_string()),
+                                }
+                            }
+                            let result = session.finish().await;
+                            let saved = format!(
+                                "Saved {} file{}:\n{}",
+                                result.files.len(),
+                                if result.files.len() == 1 { "" } else { "s" },
+                                result.files.iter().map(|f| f.display().to_string()).collect::<Vec<_>>().join("\n")
+                            );
+                            match result.error {
+                                None => logs.set(saved),
+                                Some(AdbError::Cancelled) => logs.set("Recording cancelled and discarded.".to_string()),
+                                Some(e) if result.files.is_empty() => {
+                                    logs.set("Recording failed.".to_string());
+                                    record_error.set(Some(e));
+                                }
+                                Some(e) => {
+                                    logs.set(format!("Recording stopped early. {}", saved));
+                                    record_error.set(Some(e));
+                                }
                             }
+                            recording.set(None);
                         });
                     },
-                    i { class: if is_recording() { "fas fa-stop-circle" } else { "fas fa-video" } }
-                    if is_recording() { "Stop Recording" } else { "Start Recording" }
+                    i { class: if is_recording { "fas fa-stop-circle" } else { "fas fa-video" } }
+                    if is_recording { "Stop Recording" } else { "Start Recording" }
+                }
+                if let Some(controls) = recording() {
+                    button {
+                        class: "btn btn-secondary",
+                        onclick: move |_| controls.cancel(),
+                        i { class: "fas fa-times" }
+                        "Cancel Recording"
+                    }
+                }
+            }
+            div {
+                class: "device-info",
+                style: "margin-top: 8px;",
+                h3 { "Recording Options" }
+                div {
+                    style: "display: flex; flex-wrap: wrap; gap: 16px; align-items: flex-end;",
+                    div { class: "form-group",
+                        label { "Bit rate (Mbps)" }
+                        input {
+                            r#type: "number",
+                            min: "1",
+                            max: "100",
+                            placeholder: "Default",
+                            disabled: is_recording,
+                            style: "width: 120px;",
+                            value: record_options.read().bit_rate_mbps.map(|m| m.to_string()).unwrap_or_default(),
+                            oninput: move |e| record_options.write().bit_rate_mbps = e.value().trim().parse().ok().filter(|m| *m > 0),
+                        }
+                    }
+                    div { class: "form-group",
+                        label { "Size" }
+                        input {
+                            r#type: "text",
+                            placeholder: "Native, or e.g. 1280x720",
+                            disabled: is_recording,
+                            style: "width: 180px;",
+                            value: record_options.read().size.clone().unwrap_or_default(),
+                            oninput: move |e| {
+                                let size = e.value().trim().to_string();
+                                record_options.write().size = (!size.is_empty()).then_some(size);
+                            },
+                        }
+                    }
+                    div { class: "form-group",
+                        label { "Time limit (seconds)" }
+                        input {
+                            r#type: "number",
+                            min: "1",
+                            placeholder: "Until stopped",
+                            disabled: is_recording,
+                            style: "width: 140px;",
+                            value: record_options.read().time_limit_secs.map(|s| s.to_string()).unwrap_or_default(),
+                            oninput: move |e| record_options.write().time_limit_secs = e.value().trim().parse().ok().filter(|s| *s > 0),
+                        }
+                    }
+                    div { class: "form-group",
+                        label {
+                            "Timestamp overlay"
+                            input {
+                                r#type: "checkbox",
+                                disabled: is_recording,
+                                checked: record_options.read().bugreport,
+                                onchange: move |e| record_options.write().bugreport = e.checked(),
+                            }
+                        }
+                    }
+                }
+                div {
+                    style: "display: flex; align-items: center; gap: 8px; font-size: 0.875rem; color: var(--secondary-text);",
+                    button {
+                        class: "btn btn-secondary",
+                        style: "margin: 0;",
+                        disabled: is_recording,
+                        onclick: move |_| {
+                            spawn(async move {
+                                if let Some(folder) = AsyncFileDialog::new().set_title("Save recordings to").pick_folder().await {
+                                    output_dir.set(Some(folder.path().to_path_buf()));
+                                }
+                            });
+                        },
+                        i { class: "fas fa-folder-open" }
+                        "Save To..."
+                    }
+                    match output_dir() {
+                        Some(dir) => rsx! { span { "{dir.display()}" } },
+                        None => rsx! { span { "Asked when recording starts" } },
+                    }
+                }
+                p { style: "font-size: 0.75rem; color: var(--secondary-text); margin-top: 8px;",
+                    "Recordings longer than {MAX_SEGMENT_SECS / 60} minutes are saved as consecutive segments."
                 }
             }
+            if let Some(e) = record_error() {
+                ErrorNotice { error: e }
+            }
             div {
                 class: "log-output",
+                style: "height: auto; min-height: 40px; max-height: 120px;",
                 pre { {logs} }
             }
+            ScreenshotGallery { selected: selected_shot, status: logs }
+            if let Some(id) = selected_shot() {
+                ScreenshotEditor { key: "{id}", id, status: logs }
+            }
         }
     }
 }
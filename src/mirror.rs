@@ -0,0 +1,291 @@
+use std::collections::VecDeque;
+use std::io::Cursor;
+use std::time::{Duration, Instant};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use confy::store;
+use dioxus::prelude::*;
+use image::ImageReader;
+use tokio::time::MissedTickBehavior;
+use tokio_stream::StreamExt;
+use tokio_util::sync::CancellationToken;
+use crate::adb::{adb_screenshot, cancellable};
+use crate::app::AppState;
+use crate::error::AdbError;
+use crate::input::{keycode_for, DisplayInfo, HardwareKey, InputAction};
+use crate::settings::AdbStudioSettings;
+use crate::ui::ErrorNotice;
+
+pub const MAX_MIRROR_FPS: u32 = 15;
+/// Frames used to work out the frame rate actually achieved.
+const FPS_WINDOW: usize = 10;
+const MIRROR_MAX_WIDTH: f64 = 900.0;
+const MIRROR_MAX_HEIGHT: f64 = 640.0;
+const MIN_SWIPE_MS: u64 = 50;
+const MAX_SWIPE_MS: u64 = 3000;
+
+/// One captured frame, ready to show.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MirrorFrame {
+    pub data_url: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl MirrorFrame {
+    fn from_png(png: &[u8]) -> Result<Self, AdbError> {
+        let (width, height) = ImageReader::new(Cursor::new(png))
+            .with_guessed_format()?
+            .into_dimensions()
+            .map_err(|e| AdbError::Io(format!("could not decode frame: {}", e)))?;
+        Ok(MirrorFrame {
+            data_url: format!("data:image/png;base64,{}", STANDARD.encode(png)),
+            width,
+            height,
+        })
+    }
+}
+
+/// Mirroring is built on repeated `screencap -p`, so the frame rate is bounded by how quickly the
+/// device can encode a PNG, usually a handful per second. A smooth H.264 stream from
+/// `screenrecord --output-format=h264` would need a video decoder, which the app doesn't bundle.
+pub async fn capture_frame(settings: &AdbStudioSettings, serial: &str) -> Result<MirrorFrame, AdbError> {
+    let png = adb_screenshot(settings, serial).await?;
+    MirrorFrame::from_png(&png)
+}
+
+/// How much a frame is scaled down to fit the view.
+fn display_scale(width: u32, height: u32) -> f64 {
+    (MIRROR_MAX_WIDTH / width as f64).min(MIRROR_MAX_HEIGHT / height as f64).min(1.0)
+}
+
+/// Frames per second over the last few frames.
+fn measured_fps(times: &VecDeque<Instant>) -> f64 {
+    match (times.front(), times.back()) {
+        (Some(first), Some(last)) if times.len() > 1 => {
+            let span = last.duration_since(*first).as_secs_f64();
+            if span > 0.0 { (times.len() - 1) as f64 / span } else { 0.0 }
+        }
+        _ => 0.0,
+    }
+}
+
+#[component]
+pub fn ScreenMirror() -> Element {
+    let mut settings = use_context::<Signal<AdbStudioSettings>>();
+    let app_state = use_context::<Signal<AppState>>();
+    let selected_serial = use_memo(move || app_state.read().selected_device.as_ref().map(|d| d.serial.clone()));
+    let mut is_mirroring = use_signal(|| false);
+    let mut frame = use_signal(|| None::<MirrorFrame>);
+    let mut fps = use_signal(|| 0.0);
+    let mut fps_draft = use_signal(|| None::<u32>);
+    let mut error = use_signal(|| None::<AdbError>);
+    let mut cancel = use_signal(CancellationToken::new);
+    let mut display = use_signal(|| None::<DisplayInfo>);
+    // Where the pointer went down, in frame pixels, and when.
+    let mut press = use_signal(|| None::<(f64, f64, Instant)>);
+    let frame_size = use_memo(move || frame.read().as_ref().map(|f| (f.width, f.height)));
+
+    // Actions go through one queue so taps and keystrokes reach the device in order.
+    let input = use_coroutine(move |mut actions: UnboundedReceiver<InputAction>| async move {
+        while let Some(action) = actions.next().await {
+            let Some(serial) = selected_serial.peek().clone() else { continue };
+            let settings_clone = settings.peek().clone();
+            if let Err(e) = action.send(&settings_clone, &serial).await {
+                error.set(Some(e));
+            }
+        }
+    });
+
+    // Rotating the device changes the frame's shape, which is when the mapping needs refreshing.
+    use_effect(move || {
+        let (Some(serial), Some(_)) = (selected_serial(), frame_size()) else {
+            return;
+        };
+        let settings_clone = settings.peek().clone();
+        spawn(async move {
+            match DisplayInfo::query(&settings_clone, &serial).await {
+                Ok(info) => display.set(Some(info)),
+                Err(e) => error.set(Some(e)),
+            }
+        });
+    });
+
+    let release = move |e: MouseEvent| {
+        let Some((x1, y1, pressed)) = press.take() else {
+            return;
+        };
+        let (Some(info), Some((width, height))) = (display(), frame_size()) else {
+            return;
+        };
+        let scale = display_scale(width, height);
+        let point = e.element_coordinates();
+        let (start_x, start_y) = info.map_point(x1, y1, width, height);
+        let (end_x, end_y) = info.map_point(point.x / scale, point.y / scale, width, height);
+        let distance = ((end_x as f64 - start_x as f64).powi(2) + (end_y as f64 - start_y as f64).powi(2)).sqrt();
+        if distance < info.tap_slop() {
+            input.send(InputAction::Tap { x: start_x, y: start_y });
+        } else {
+            let duration_ms = (pressed.elapsed().as_millis() as u64).clamp(MIN_SWIPE_MS, MAX_SWIPE_MS);
+            input.send(InputAction::Swipe { x1: start_x, y1: start_y, x2: end_x, y2: end_y, duration_ms });
+        }
+    };
+
+    use_effect(move || {
+        cancel.peek().cancel();
+        if !*is_mirroring.read() {
+            return;
+        }
+        let Some(serial) = selected_serial() else {
+            is_mirroring.set(false);
+            return;
+        };
+        // Reading the rate here restarts the capture loop when it changes.
+        let target_fps = settings.read().mirror_fps.clamp(1, MAX_MIRROR_FPS);
+        let token = CancellationToken::new();
+        cancel.set(token.clone());
+        let settings_clone = settings.peek().clone();
+        spawn(async move {
+            error.set(None);
+            let mut ticks = tokio::time::interval(Duration::from_secs_f64(1.0 / target_fps as f64));
+            // A slow capture shouldn't be followed by a burst of catch-up frames.
+            ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            let mut times = VecDeque::with_capacity(FPS_WINDOW);
+            loop {
+                ticks.tick().await;
+                match cancellable(&token, capture_frame(&settings_clone, &serial)).await {
+                    Ok(next) => {
+                        if times.len() == FPS_WINDOW {
+                            times.pop_front();
+                        }
+                        times.push_back(Instant::now());
+                        fps.set(measured_fps(&times));
+                        frame.set(Some(next));
+                    }
+                    Err(AdbError::Cancelled) => return,
+                    Err(e) => {
+                        error.set(Some(e));
+                        is_mirroring.set(false);
+                        return;
+                    }
+                }
+            }
+        });
+    });
+
+    use_drop(move || cancel.peek().cancel());
+
+    // While the slider is dragged only the label follows it; the rate is saved, and the capture
+    // loop restarted, once the slider is released.
+    let target_fps = fps_draft().unwrap_or(settings.read().mirror_fps);
+
+    rsx! {
+        div {
+            div {
+                style: "display: flex; flex-wrap: wrap; align-items: center; gap: 8px;",
+                button {
+                    class: "btn",
+                    disabled: selected_serial().is_none(),
+                    onclick: move |_| {
+                        let mirroring = is_mirroring();
+                        is_mirroring.set(!mirroring);
+                    },
+                    i { class: if is_mirroring() { "fas fa-stop" } else { "fas fa-play" } }
+                    if is_mirroring() { "Stop Mirroring" } else { "Start Mirroring" }
+                }
+                label { style: "display: flex; align-items: center; gap: 8px; margin-bottom: 8px; font-size: 0.875rem;",
+                    "Frame rate"
+                    input {
+                        r#type: "range",
+                        min: "1",
+                        max: "{MAX_MIRROR_FPS}",
+                        value: "{target_fps}",
+                        oninput: move |e| {
+                            if let Ok(rate) = e.value().parse::<u32>() {
+                                fps_draft.set(Some(rate.clamp(1, MAX_MIRROR_FPS)));
+                            }
+                        },
+                        onchange: move |e| {
+                            if let Ok(rate) = e.value().parse::<u32>() {
+                                settings.write().mirror_fps = rate.clamp(1, MAX_MIRROR_FPS);
+                                let _ = store("adb-studio", None, settings.read().clone());
+                            }
+                            fps_draft.set(None);
+                        },
+                    }
+                    "{target_fps} fps"
+                }
+                if is_mirroring() {
+                    span { style: "margin-bottom: 8px; font-size: 0.8rem; color: var(--secondary-text);",
+                        {format!("{:.1} fps actual", fps())}
+                    }
+                }
+            }
+            if let Some(e) = error() {
+                ErrorNotice { error: e }
+            }
+            div {
+                style: "display: flex; flex-wrap: wrap; gap: 4px; margin-top: 8px;",
+                for key in HardwareKey::ALL {
+                    button {
+                        class: "btn btn-secondary",
+                        disabled: selected_serial().is_none(),
+                        title: key.label(),
+                        onclick: move |_| input.send(InputAction::Key(key.keycode())),
+                        i { class: key.icon() }
+                        "{key.label()}"
+                    }
+                }
+            }
+            match frame() {
+                Some(frame) => {
+                    let scale = display_scale(frame.width, frame.height);
+                    rsx! {
+                        div {
+                            tabindex: "0",
+                            style: "margin-top: 8px; display: inline-block; background: #000; border-radius: 8px; padding: 4px; outline: none;",
+                            // Printable keys are typed as text; the rest map to key events.
+                            onkeydown: move |e| {
+                                e.prevent_default();
+                                let action = match e.key() {
+                                    Key::Character(text) if !e.modifiers().ctrl() && !e.modifiers().meta() => InputAction::Text(text),
+                                    key => match keycode_for(&key.to_string()) {
+                                        Some(code) => InputAction::Key(code),
+                                        None => return,
+                                    },
+                                };
+                                input.send(action);
+                            },
+                            img {
+                                src: "{frame.data_url}",
+                                draggable: "false",
+                                style: format!(
+                                    "display: block; width: {}px; height: {}px; border-radius: 4px; cursor: pointer;",
+                                    frame.width as f64 * scale,
+                                    frame.height as f64 * scale
+                                ),
+                                onmousedown: move |e| {
+                                    let point = e.element_coordinates();
+                                    press.set(Some((point.x / scale, point.y / scale, Instant::now())));
+                                },
+                                onmouseup: release,
+                                onmouseleave: release,
+                            }
+                        }
+                        div { style: "font-size: 0.75rem; color: var(--secondary-text); margin-top: 4px;",
+                            "{frame.width} × {frame.height}"
+                            if let Some(info) = display() {
+                                " · {info.density} dpi · rotation {info.rotation * 90}°"
+                            }
+                            " · Click to tap, drag to swipe, click the screen and type to send keys"
+                        }
+                    }
+                }
+                None => rsx! {
+                    p { style: "color: var(--secondary-text);",
+                        if selected_serial().is_some() { "Start mirroring to see the device screen." } else { "Select a device to mirror." }
+                    }
+                },
+            }
+        }
+    }
+}
@@ -0,0 +1,265 @@
+use dioxus::prelude::*;
+use crate::adb::{adb_shell, shell_quote};
+use crate::app_details::PermissionGrant;
+use crate::error::AdbError;
+use crate::settings::AdbStudioSettings;
+use crate::ui::ErrorNotice;
+
+/// Permissions carrying these flags are pinned by the system or a device policy and can't be
+/// changed from the shell.
+const FIXED_FLAGS: [&str; 2] = ["SYSTEM_FIXED", "POLICY_FIXED"];
+/// Flags left behind by the user's choices, cleared when resetting so the app can ask again.
+const USER_FLAGS: &str = "user-set user-fixed";
+
+pub const APP_OP_MODES: [&str; 5] = ["allow", "ignore", "deny", "default", "foreground"];
+
+pub fn is_fixed(permission: &PermissionGrant) -> bool {
+    permission.flags.iter().any(|f| FIXED_FLAGS.contains(&f.as_str()))
+}
+
+/// One line of `appops get <package>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppOp {
+    pub name: String,
+    pub mode: String,
+    /// Set on the app's UID rather than the package, printed as `Uid mode: OP: mode`.
+    pub uid_mode: bool,
+}
+
+/// Parses `appops get`, e.g. `CAMERA: allow; time=+1d2h ago` or `Uid mode: COARSE_LOCATION: foreground`.
+pub fn parse_app_ops(output: &str) -> Vec<AppOp> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            let (uid_mode, line) = match line.strip_prefix("Uid mode:") {
+                Some(rest) => (true, rest.trim()),
+                None => (false, line),
+            };
+            let (name, rest) = line.split_once(':')?;
+            let mode = rest.split(';').next()?.trim();
+            // Skip headings and anything else that isn't `OP_NAME: mode`.
+            let is_op = !name.is_empty() && name.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_');
+            (is_op && !mode.is_empty()).then(|| AppOp { name: name.to_string(), mode: mode.to_string(), uid_mode })
+        })
+        .collect()
+}
+
+/// Grants or revokes a runtime permission for one user; runtime grants are kept per user.
+pub async fn set_permission(
+    settings: &AdbStudioSettings,
+    serial: &str,
+    user_id: u32,
+    package: &str,
+    permission: &str,
+    grant: bool,
+) -> Result<(), AdbError> {
+    let command = format!(
+        "pm {} --user {} {} {}",
+        if grant { "grant" } else { "revoke" },
+        user_id,
+        shell_quote(package),
+        shell_quote(permission)
+    );
+    adb_shell(settings, serial, &command).await.map(|_| ())
+}
+
+pub async fn get_app_ops(settings: &AdbStudioSettings, serial: &str, package: &str) -> Result<Vec<AppOp>, AdbError> {
+    let output = adb_shell(settings, serial, &format!("appops get {}", shell_quote(package))).await?;
+    Ok(parse_app_ops(&output))
+}
+
+pub async fn set_app_op(settings: &AdbStudioSettings, serial: &str, package: &str, op: &AppOp, mode: &str) -> Result<(), AdbError> {
+    let command = format!(
+        "appops set {}{} {} {}",
+        if op.uid_mode { "--uid " } else { "" },
+        shell_quote(package),
+        shell_quote(&op.name),
+        shell_quote(mode)
+    );
+    adb_shell(settings, serial, &command).await.map(|_| ())
+}
+
+/// Puts the app's permissions for one user back to a fresh install: revokes every changeable
+/// runtime permission, clears the user's "don't ask again" choices and resets its app ops.
+///
+/// `pm reset-permissions` would do this for every app on the device, so it isn't used.
+pub async fn reset_permissions(
+    settings: &AdbStudioSettings,
+    serial: &str,
+    user_id: u32,
+    package: &str,
+    permissions: &[PermissionGrant],
+) -> Result<(), AdbError> {
+    for permission in permissions.iter().filter(|p| !is_fixed(p)) {
+        if permission.granted {
+            set_permission(settings, serial, user_id, package, &permission.name, false).await?;
+        }
+        // Not available before Android 10; the revoke above is what matters.
+        let command = format!(
+            "pm clear-permission-flags --user {} {} {} {}",
+            user_id,
+            shell_quote(package),
+            shell_quote(&permission.name),
+            USER_FLAGS
+        );
+        let _ = adb_shell(settings, serial, &command).await;
+    }
+    adb_shell(settings, serial, &format!("appops reset --user {} {}", user_id, shell_quote(package))).await.map(|_| ())
+}
+
+/// Runtime permissions and app ops of one user's copy of the app. `permissions` must be that
+/// user's grants, so the toggles show what `pm grant --user` changes.
+#[component]
+pub fn PermissionsTab(
+    serial: String,
+    package: String,
+    user_id: u32,
+    permissions: Vec<PermissionGrant>,
+    on_change: EventHandler<()>,
+) -> Element {
+    let settings = use_context::<Signal<AdbStudioSettings>>();
+    let mut app_ops = use_signal(Vec::<AppOp>::new);
+    let mut busy = use_signal(|| false);
+    let mut confirm_reset = use_signal(|| false);
+    let mut error = use_signal(|| None::<AdbError>);
+    let mut reload_ops = use_signal(|| 0u32);
+
+    use_effect(use_reactive!(|serial, package| {
+        reload_ops();
+        let settings_clone = settings.peek().clone();
+        spawn(async move {
+            match get_app_ops(&settings_clone, &serial, &package).await {
+                Ok(ops) => app_ops.set(ops),
+                Err(e) => error.set(Some(e)),
+            }
+        });
+    }));
+
+    let toggle_serial = serial.clone();
+    let toggle_package = package.clone();
+    let toggle = use_callback(move |(permission, grant): (String, bool)| {
+        let serial = toggle_serial.clone();
+        let package = toggle_package.clone();
+        spawn(async move {
+            busy.set(true);
+            error.set(None);
+            let settings_clone = settings.read().clone();
+            if let Err(e) = set_permission(&settings_clone, &serial, user_id, &package, &permission, grant).await {
+                error.set(Some(e));
+            }
+            busy.set(false);
+            on_change.call(());
+        });
+    });
+
+    let op_serial = serial.clone();
+    let op_package = package.clone();
+    let set_mode = use_callback(move |(op, mode): (AppOp, String)| {
+        let serial = op_serial.clone();
+        let package = op_package.clone();
+        spawn(async move {
+            busy.set(true);
+            error.set(None);
+            let settings_clone = settings.read().clone();
+            if let Err(e) = set_app_op(&settings_clone, &serial, &package, &op, &mode).await {
+                error.set(Some(e));
+            }
+            busy.set(false);
+            reload_ops += 1;
+        });
+    });
+
+    let reset_permissions_list = permissions.clone();
+    let reset = move |_| {
+        let serial = serial.clone();
+        let package = package.clone();
+        let permissions = reset_permissions_list.clone();
+        confirm_reset.set(false);
+        spawn(async move {
+            busy.set(true);
+            error.set(None);
+            let settings_clone = settings.read().clone();
+            if let Err(e) = reset_permissions(&settings_clone, &serial, user_id, &package, &permissions).await {
+                error.set(Some(e));
+            }
+            busy.set(false);
+            reload_ops += 1;
+            on_change.call(());
+        });
+    };
+
+    rsx! {
+        div {
+            if let Some(e) = error() {
+                ErrorNotice { error: e }
+            }
+            div { style: "display: flex; align-items: center; gap: 8px;",
+                if confirm_reset() {
+                    span { style: "font-size: 0.875rem; margin-bottom: 8px;", "Revoke all runtime permissions and reset app ops?" }
+                    button { class: "btn", disabled: busy(), onclick: reset, "Reset" }
+                    button { class: "btn btn-secondary", onclick: move |_| confirm_reset.set(false), "Cancel" }
+                } else {
+                    button {
+                        class: "btn btn-secondary",
+                        disabled: busy(),
+                        onclick: move |_| confirm_reset.set(true),
+                        i { class: "fas fa-undo" }
+                        "Reset All Permissions"
+                    }
+                }
+            }
+            h3 {
+                "Runtime Permissions"
+                if user_id != 0 {
+                    span { style: "font-size: 0.75rem; margin-left: 6px;", "(user {user_id})" }
+                }
+            }
+            if permissions.is_empty() {
+                p { style: "font-size: 0.875rem; color: var(--secondary-text);", "This app has no runtime permissions." }
+            }
+            for permission in permissions {
+                div { class: "device-info-item",
+                    key: "{user_id}:{permission.name}",
+                    span { class: "label", style: "word-break: break-all;",
+                        "{permission.name}"
+                        if is_fixed(&permission) {
+                            span { style: "font-size: 0.75rem; margin-left: 6px;", "(fixed by system)" }
+                        }
+                    }
+                    label { style: "display: flex; align-items: center; margin-left: 16px; font-size: 0.875rem;",
+                        if permission.granted { "Granted" } else { "Denied" }
+                        input {
+                            r#type: "checkbox",
+                            checked: permission.granted,
+                            disabled: busy() || is_fixed(&permission),
+                            onchange: move |e| toggle.call((permission.name.clone(), e.checked())),
+                        }
+                    }
+                }
+            }
+            h3 { "App Ops" }
+            for op in app_ops() {
+                div { class: "device-info-item",
+                    key: "{op.uid_mode}{op.name}",
+                    span { class: "label",
+                        "{op.name}"
+                        if op.uid_mode {
+                            span { style: "font-size: 0.75rem; margin-left: 6px;", "(UID)" }
+                        }
+                    }
+                    select {
+                        class: "btn btn-secondary",
+                        style: "margin: 0 0 0 16px; padding: 4px 8px;",
+                        disabled: busy(),
+                        value: "{op.mode}",
+                        onchange: move |e| set_mode.call((op.clone(), e.value())),
+                        for mode in APP_OP_MODES {
+                            option { value: mode, "{mode}" }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use crate::adb::{adb_shell, adb_shell_output, shell_quote, shell_result};
+use crate::adb_client::remote_join;
+use crate::error::AdbError;
+use crate::settings::AdbStudioSettings;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EntryKind {
+    Directory,
+    File,
+    Symlink,
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteEntry {
+    pub name: String,
+    pub kind: EntryKind,
+    pub size: u64,
+    pub permissions: String,
+    pub owner: String,
+    pub group: String,
+    pub modified: String,
+    pub link_target: Option<String>,
+}
+
+impl RemoteEntry {
+    pub fn is_navigable(&self) -> bool {
+        matches!(self.kind, EntryKind::Directory | EntryKind::Symlink)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortKey {
+    Name,
+    Size,
+    Permissions,
+    Owner,
+    Modified,
+}
+
+/// Parses `ls -la` from toybox (Android 6+) and from the older toolbox `ls`, which omits the
+/// link count and the size of directories.
+pub fn parse_ls_la(output: &str) -> Vec<RemoteEntry> {
+    output.lines().filter_map(parse_ls_line).filter(|e| e.name != "." && e.name != "..").collect()
+}
+
+fn parse_ls_line(line: &str) -> Option<RemoteEntry> {
+    let tokens = tokenize(line);
+    let (_, permissions) = *tokens.first()?;
+    let kind = match permissions.chars().next()? {
+        'd' => EntryKind::Directory,
+        '-' => EntryKind::File,
+        'l' => EntryKind::Symlink,
+        'b' | 'c' | 'p' | 's' => EntryKind::Other,
+        _ => return None,
+    };
+    if permissions.len() < 10 {
+        return None;
+    }
+    let date_index = tokens.iter().position(|(_, t)| is_date(t))?;
+    let (time_start, time) = *tokens.get(date_index + 1)?;
+    let name_start = time_start + time.len();
+    let mut fields = tokens[1..date_index].iter().map(|(_, t)| *t).collect::<Vec<_>>();
+    // toolbox prints at most owner, group and size (or `major, minor` for devices); toybox adds
+    // the link count in front. Owners can be numeric, so the count is told apart by position.
+    let toolbox_fields = if kind == EntryKind::Other { 4 } else { 3 };
+    if fields.len() > toolbox_fields {
+        fields.remove(0);
+    }
+    let owner = fields.first().copied().unwrap_or_default().to_string();
+    let group = fields.get(1).copied().unwrap_or_default().to_string();
+    let size = match kind {
+        EntryKind::Other => 0,
+        _ => fields.get(2).and_then(|s| s.parse().ok()).unwrap_or(0),
+    };
+
+    let rest = line[name_start..].trim_start();
+    let (name, link_target) = match (kind, rest.split_once(" -> ")) {
+        (EntryKind::Symlink, Some((name, target))) => (name.to_string(), Some(target.to_string())),
+        _ => (rest.to_string(), None),
+    };
+    if name.is_empty() {
+        return None;
+    }
+    Some(RemoteEntry {
+        name,
+        kind,
+        size,
+        permissions: permissions.to_string(),
+        owner,
+        group,
+        modified: format!("{} {}", tokens[date_index].1, time),
+        link_target,
+    })
+}
+
+fn tokenize(line: &str) -> Vec<(usize, &str)> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (index, c) in line.char_indices() {
+        match (c.is_whitespace(), start) {
+            (true, Some(s)) => {
+                tokens.push((s, &line[s..index]));
+                start = None;
+            }
+            (false, None) => start = Some(index),
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        tokens.push((s, &line[s..]));
+    }
+    tokens
+}
+
+fn is_date(token: &str) -> bool {
+    let bytes = token.as_bytes();
+    bytes.len() == 10
+        && bytes[4] == b'-'
+        && bytes[7] == b'-'
+        && bytes.iter().enumerate().all(|(i, b)| i == 4 || i == 7 || b.is_ascii_digit())
+}
+
+/// Directories (and links, which usually point at one) are always listed before files.
+pub fn sort_entries(entries: &mut [RemoteEntry], key: SortKey, ascending: bool) {
+    entries.sort_by(|a, b| {
+        let group = |e: &RemoteEntry| if e.is_navigable() { 0 } else { 1 };
+        let ordering = match key {
+            SortKey::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            SortKey::Size => a.size.cmp(&b.size),
+            SortKey::Permissions => a.permissions.cmp(&b.permissions),
+            SortKey::Owner => a.owner.cmp(&b.owner),
+            SortKey::Modified => a.modified.cmp(&b.modified),
+        };
+        group(a).cmp(&group(b)).then(if ascending { ordering } else { ordering.reverse() })
+    });
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+pub fn parent_path(path: &str) -> String {
+    match path.trim_end_matches('/').rsplit_once('/') {
+        Some(("", _)) | None => "/".to_string(),
+        Some((parent, _)) => parent.to_string(),
+    }
+}
+
+pub fn child_path(dir: &str, name: &str) -> String {
+    if dir == "/" {
+        format!("/{}", name)
+    } else {
+        remote_join(dir, name)
+    }
+}
+
+pub async fn list_dir(settings: &AdbStudioSettings, serial: &str, path: &str) -> Result<Vec<RemoteEntry>, AdbError> {
+    // The trailing slash makes `ls` list the target of symlinked directories such as /sdcard.
+    let dir = if path.ends_with('/') { path.to_string() } else { format!("{}/", path) };
+    let output = adb_shell_output(settings, serial, &format!("ls -la {}", shell_quote(&dir))).await?;
+    // toybox exits non-zero if any entry can't be read, as in / or /proc, but lists the rest.
+    let entries = parse_ls_la(&String::from_utf8_lossy(&output.stdout));
+    if entries.is_empty() {
+        shell_result(output)?;
+    }
+    Ok(entries)
+}
+
+pub async fn make_dir(settings: &AdbStudioSettings, serial: &str, path: &str) -> Result<(), AdbError> {
+    adb_shell(settings, serial, &format!("mkdir -p {}", shell_quote(path))).await.map(|_| ())
+}
+
+pub async fn rename(settings: &AdbStudioSettings, serial: &str, from: &str, to: &str) -> Result<(), AdbError> {
+    adb_shell(settings, serial, &format!("mv {} {}", shell_quote(from), shell_quote(to))).await.map(|_| ())
+}
+
+pub async fn remove(settings: &AdbStudioSettings, serial: &str, path: &str) -> Result<(), AdbError> {
+    adb_shell(settings, serial, &format!("rm -rf {}", shell_quote(path))).await.map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(line: &str) -> RemoteEntry {
+        parse_ls_line(line).unwrap_or_else(|| panic!("not parsed: {}", line))
+    }
+
+    #[test]
+    fn parses_toybox_lines() {
+        let file = entry("-rw-rw----  1 u0_a123 sdcard_rw 1234 2023-05-01 12:34 file  with spaces.txt");
+        assert_eq!(file.kind, EntryKind::File);
+        assert_eq!(file.name, "file  with spaces.txt");
+        assert_eq!((file.owner.as_str(), file.group.as_str(), file.size), ("u0_a123", "sdcard_rw", 1234));
+        assert_eq!(file.permissions, "-rw-rw----");
+        assert_eq!(file.modified, "2023-05-01 12:34");
+
+        let dir = entry("drwxrwx--x 14 root sdcard_rw 4096 2023-05-01 12:34 Android");
+        assert_eq!((dir.kind, dir.size), (EntryKind::Directory, 4096));
+
+        let link = entry("lrwxrwxrwx  1 root root 21 2009-01-01 00:00 sdcard -> /storage/self/primary");
+        assert_eq!(link.kind, EntryKind::Symlink);
+        assert_eq!(link.name, "sdcard");
+        assert_eq!(link.link_target.as_deref(), Some("/storage/self/primary"));
+
+        let device = entry("crw-rw-rw-  1 root root   1,   3 2023-05-01 12:34 null");
+        assert_eq!((device.kind, device.size, device.owner.as_str()), (EntryKind::Other, 0, "root"));
+
+        let numeric = entry("-rw-------  1 1000 1000 512 2023-05-01 12:34 settings.xml");
+        assert_eq!((numeric.owner.as_str(), numeric.group.as_str(), numeric.size), ("1000", "1000", 512));
+    }
+
+    #[test]
+    fn parses_toolbox_lines() {
+        let dir = entry("drwxr-xr-x root     root              2016-01-01 00:00 acct");
+        assert_eq!((dir.kind, dir.owner.as_str(), dir.group.as_str(), dir.size), (EntryKind::Directory, "root", "root", 0));
+
+        let file = entry("-rw-r--r-- root     root         6 2016-01-01 00:00 old.txt");
+        assert_eq!((file.owner.as_str(), file.size), ("root", 6));
+
+        let numeric = entry("-rw-r--r-- 1000     1000       123 2016-01-01 00:00 numeric.txt");
+        assert_eq!((numeric.owner.as_str(), numeric.group.as_str(), numeric.size), ("1000", "1000", 123));
+
+        let numeric_dir = entry("drwxrwx--x 1000     2001              2016-01-01 00:00 data");
+        assert_eq!((numeric_dir.owner.as_str(), numeric_dir.group.as_str()), ("1000", "2001"));
+
+        let link = entry("lrwxrwxrwx root     root              2016-01-01 00:00 etc -> /system/etc");
+        assert_eq!(link.link_target.as_deref(), Some("/system/etc"));
+
+        let device = entry("crw-rw-rw- root     root       1,   3 2016-01-01 00:00 null");
+        assert_eq!((device.kind, device.owner.as_str(), device.group.as_str()), (EntryKind::Other, "root", "root"));
+    }
+
+    #[test]
+    fn skips_headers_errors_and_dot_entries() {
+        let output = "total 52\n\
+            drwxrwx--x 14 root sdcard_rw 4096 2023-05-01 12:34 .\n\
+            drwx--x--x  4 root sdcard_rw 4096 2023-05-01 12:34 ..\n\
+            ls: /proc/1/cwd: Permission denied\n\
+            -rw-rw----  1 u0_a123 sdcard_rw 10 2023-05-01 12:34 a.txt\n";
+        let entries = parse_ls_la(output);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "a.txt");
+    }
+
+    #[test]
+    fn paths() {
+        assert_eq!(parent_path("/sdcard"), "/");
+        assert_eq!(parent_path("/sdcard/DCIM/"), "/sdcard");
+        assert_eq!(parent_path("/"), "/");
+        assert_eq!(child_path("/", "sdcard"), "/sdcard");
+        assert_eq!(child_path("/sdcard/", "DCIM"), "/sdcard/DCIM");
+    }
+}
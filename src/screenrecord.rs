@@ -0,0 +1,393 @@
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use chrono::Local;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, BufReader};
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::process::{Child, Command};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use crate::adb::{adb_pull, adb_shell, server_client, shell_quote};
+use crate::error::AdbError;
+use crate::settings::{AdbBackend, AdbStudioSettings};
+
+/// `screenrecord` refuses to record longer than this in one go, so longer sessions are chained
+/// from several segments.
+pub const MAX_SEGMENT_SECS: u64 = 180;
+const REMOTE_DIR: &str = "/data/local/tmp";
+/// How long a stopped recorder gets to finish writing its file before it is killed.
+const STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RecordOptions {
+    pub bit_rate_mbps: Option<u32>,
+    /// `WIDTHxHEIGHT`; the device's native resolution when unset.
+    pub size: Option<String>,
+    /// Total length of the session; it runs until stopped when unset.
+    pub time_limit_secs: Option<u64>,
+    /// Overlays the timestamp and build info like `screenrecord --bugreport`.
+    pub bugreport: bool,
+}
+
+impl RecordOptions {
+    fn command(&self, remote_path: &str, segment_secs: u64) -> String {
+        let mut command = format!("screenrecord --time-limit {}", segment_secs);
+        if let Some(mbps) = self.bit_rate_mbps {
+            command.push_str(&format!(" --bit-rate {}", mbps as u64 * 1_000_000));
+        }
+        if let Some(size) = &self.size {
+            command.push_str(&format!(" --size {}", shell_quote(size)));
+        }
+        if self.bugreport {
+            command.push_str(" --bugreport");
+        }
+        command.push(' ');
+        command.push_str(&shell_quote(remote_path));
+        command
+    }
+}
+
+/// Chains segments so that together they cover the session's time limit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SegmentSchedule {
+    /// Seconds left of the time limit; `None` records until stopped.
+    remaining: Option<u64>,
+}
+
+impl SegmentSchedule {
+    fn new(time_limit_secs: Option<u64>) -> Self {
+        SegmentSchedule { remaining: time_limit_secs }
+    }
+
+    /// Length of the next segment: what is left of the time limit, at most what `screenrecord`
+    /// allows.
+    fn next_limit(&self) -> u64 {
+        self.remaining.map_or(MAX_SEGMENT_SECS, |r| r.min(MAX_SEGMENT_SECS))
+    }
+
+    /// Records that a segment with `limit` ran for `elapsed` seconds and returns whether another
+    /// one should follow.
+    fn segment_ended(&mut self, limit: u64, elapsed: u64) -> bool {
+        // A segment that ended well before its limit was stopped by the device (screen off,
+        // display change), so don't keep restarting it.
+        if elapsed + 2 < limit {
+            return false;
+        }
+        // A full segment counts as its whole limit: the measured time is rounded down, and a
+        // second lost to it would otherwise become a one-second segment at the end.
+        self.remaining = self.remaining.map(|r| r.saturating_sub(limit));
+        self.remaining != Some(0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordingEvent {
+    SegmentStarted(u32),
+    SegmentSaved(PathBuf),
+    Finishing,
+}
+
+/// One running `screenrecord` process. The shell prints its pid before exec'ing the recorder so
+/// that exact process can be interrupted, rather than every `screenrecord` on the device.
+struct Recorder {
+    pid: u32,
+    output: JoinHandle<Result<String, AdbError>>,
+    // Held so the adb process or socket stays open until the recorder is done.
+    _child: Option<Child>,
+    _socket: Option<OwnedWriteHalf>,
+}
+
+impl Recorder {
+    async fn spawn(settings: &AdbStudioSettings, serial: &str, command: &str) -> Result<Self, AdbError> {
+        let script = format!("echo $$; exec {}", command);
+        let (reader, child, socket): (Box<dyn AsyncRead + Unpin + Send>, _, _) = match settings.backend {
+            AdbBackend::Server => {
+                let (read, write) = server_client(settings).open(serial, &format!("exec:{}", script)).await?.into_split();
+                (Box::new(read), None, Some(write))
+            }
+            AdbBackend::Executable => {
+                let mut child = Command::new(settings.adb_program())
+                    .args(["-s", serial, "shell", &script])
+                    .stdout(Stdio::piped())
+                    .stderr(Stdio::piped())
+                    .kill_on_drop(true)
+                    .spawn()
+                    .map_err(|e| AdbError::from_spawn(settings.adb_program(), e))?;
+                let stdout = child.stdout.take().ok_or_else(|| AdbError::Spawn("no stdout".to_string()))?;
+                let stderr = child.stderr.take().ok_or_else(|| AdbError::Spawn("no stderr".to_string()))?;
+                (Box::new(stdout.chain(stderr)), Some(child), None)
+            }
+        };
+        let mut reader = BufReader::new(reader);
+        let mut first_line = String::new();
+        reader.read_line(&mut first_line).await?;
+        let pid = first_line
+            .trim()
+            .parse()
+            .map_err(|_| AdbError::classify(None, &first_line))?;
+        let output = tokio::spawn(async move {
+            let mut output = String::new();
+            reader.read_to_string(&mut output).await?;
+            Ok(output)
+        });
+        Ok(Recorder { pid, output, _child: child, _socket: socket })
+    }
+
+    /// Waits for the recorder to exit by itself and returns what it printed.
+    async fn wait(&mut self) -> Result<String, AdbError> {
+        (&mut self.output).await.map_err(|e| AdbError::Io(e.to_string()))?
+    }
+
+    /// Sends SIGINT, which makes `screenrecord` finalize the MP4 before it exits.
+    async fn interrupt(mut self, settings: &AdbStudioSettings, serial: &str) {
+        let _ = adb_shell(settings, serial, &format!("kill -INT {}", self.pid)).await;
+        if tokio::time::timeout(STOP_TIMEOUT, self.wait()).await.is_err() {
+            self.kill(settings, serial).await;
+        }
+    }
+
+    async fn kill(self, settings: &AdbStudioSettings, serial: &str) {
+        self.output.abort();
+        let _ = adb_shell(settings, serial, &format!("kill -KILL {}", self.pid)).await;
+    }
+}
+
+/// Handles for stopping a [`RecordingSession`] from anywhere in the UI.
+#[derive(Clone)]
+pub struct RecordingControls {
+    stop: CancellationToken,
+    cancel: CancellationToken,
+}
+
+impl RecordingControls {
+    /// Ends the recording, keeping and pulling what has been recorded.
+    pub fn stop(&self) {
+        self.stop.cancel();
+    }
+
+    /// Ends the recording and throws it away, on the device and locally.
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+}
+
+/// How a session ended: the segments saved locally, and the error that cut it short if any.
+/// A later segment failing still leaves the earlier ones on disk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordingResult {
+    pub files: Vec<PathBuf>,
+    pub error: Option<AdbError>,
+}
+
+/// A screen recording on one device, possibly made of several chained segments, each pulled
+/// into the local folder and removed from the device as soon as it is complete.
+pub struct RecordingSession {
+    controls: RecordingControls,
+    events: UnboundedReceiver<RecordingEvent>,
+    task: JoinHandle<RecordingResult>,
+}
+
+impl RecordingSession {
+    pub fn start(settings: &AdbStudioSettings, serial: &str, options: RecordOptions, local_dir: &Path) -> Self {
+        let controls = RecordingControls { stop: CancellationToken::new(), cancel: CancellationToken::new() };
+        let (sender, events) = mpsc::unbounded_channel();
+        let task = tokio::spawn(record(
+            settings.clone(),
+            serial.to_string(),
+            options,
+            local_dir.to_path_buf(),
+            controls.clone(),
+            sender,
+        ));
+        RecordingSession { controls, events, task }
+    }
+
+    pub fn controls(&self) -> RecordingControls {
+        self.controls.clone()
+    }
+
+    /// The next progress event, or `None` once the session is over.
+    pub async fn next_event(&mut self) -> Option<RecordingEvent> {
+        self.events.recv().await
+    }
+
+    /// Waits for the session to end and returns the local files, one per segment.
+    pub async fn finish(self) -> RecordingResult {
+        self.task
+            .await
+            .unwrap_or_else(|e| RecordingResult { files: Vec::new(), error: Some(AdbError::Io(e.to_string())) })
+    }
+}
+
+async fn record(
+    settings: AdbStudioSettings,
+    serial: String,
+    options: RecordOptions,
+    local_dir: PathBuf,
+    controls: RecordingControls,
+    events: UnboundedSender<RecordingEvent>,
+) -> RecordingResult {
+    let stamp = Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let mut pulls: Vec<JoinHandle<Result<PathBuf, AdbError>>> = Vec::new();
+    // Records segments until the session is stopped, cancelled or over, starting a pull for
+    // each finished one. Errors end the recording but not the pulls already started.
+    let result: Result<(), AdbError> = async {
+        let local_name = format!("screenrecord-{}-{}", serial.replace([':', '.'], "_"), stamp);
+        let mut schedule = SegmentSchedule::new(options.time_limit_secs);
+        let mut segment = 0;
+
+        loop {
+            segment += 1;
+            let limit = schedule.next_limit();
+            let remote = format!("{}/adbstudio-{}-{}.mp4", REMOTE_DIR, stamp, segment);
+            let mut recorder = Recorder::spawn(&settings, &serial, &options.command(&remote, limit)).await?;
+            let started = Instant::now();
+            let _ = events.send(RecordingEvent::SegmentStarted(segment));
+
+            enum Outcome {
+                Exited(Result<String, AdbError>),
+                Stopped,
+                Cancelled,
+            }
+            let outcome = tokio::select! {
+                output = recorder.wait() => Outcome::Exited(output),
+                _ = controls.stop.cancelled() => Outcome::Stopped,
+                _ = controls.cancel.cancelled() => Outcome::Cancelled,
+            };
+            let stopped = match outcome {
+                Outcome::Exited(output) => {
+                    let output = output?;
+                    // A recorder that died without leaving a file couldn't start (bad size, no
+                    // permission, no encoder); its output says why.
+                    let check = adb_shell(&settings, &serial, &format!("[ -s {} ] && echo ok", shell_quote(&remote))).await;
+                    if !check.unwrap_or_default().contains("ok") {
+                        return Err(AdbError::classify(None, &output));
+                    }
+                    false
+                }
+                Outcome::Stopped => {
+                    recorder.interrupt(&settings, &serial).await;
+                    true
+                }
+                Outcome::Cancelled => {
+                    recorder.kill(&settings, &serial).await;
+                    return Err(AdbError::Cancelled);
+                }
+            };
+
+            let local = local_dir.join(if segment == 1 { format!("{}.mp4", local_name) } else { format!("{}-{}.mp4", local_name, segment) });
+            let (pull_settings, pull_serial, pull_events) = (settings.clone(), serial.clone(), events.clone());
+            pulls.push(tokio::spawn(async move {
+                let local_path = local.to_string_lossy().to_string();
+                adb_pull(&pull_settings, &pull_serial, &remote, &local_path, None).await?;
+                let _ = adb_shell(&pull_settings, &pull_serial, &format!("rm -f {}", shell_quote(&remote))).await;
+                let _ = pull_events.send(RecordingEvent::SegmentSaved(local.clone()));
+                Ok(local)
+            }));
+
+            if stopped || !schedule.segment_ended(limit, started.elapsed().as_secs()) {
+                return Ok(());
+            }
+        }
+    }
+    .await;
+
+    if result == Err(AdbError::Cancelled) {
+        let _ = adb_shell(&settings, &serial, &format!("rm -f {}/adbstudio-{}-*.mp4", REMOTE_DIR, stamp)).await;
+        for pull in pulls {
+            if let Ok(Ok(path)) = pull.await {
+                let _ = tokio::fs::remove_file(path).await;
+            }
+        }
+        return RecordingResult { files: Vec::new(), error: Some(AdbError::Cancelled) };
+    }
+
+    let _ = events.send(RecordingEvent::Finishing);
+    let mut files = Vec::new();
+    let mut error = result.err();
+    for pull in pulls {
+        match pull.await.map_err(|e| AdbError::Io(e.to_string())).and_then(|pulled| pulled) {
+            Ok(path) => files.push(path),
+            Err(e) => {
+                error.get_or_insert(e);
+            }
+        }
+    }
+    RecordingResult { files, error }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REMOTE: &str = "/data/local/tmp/adbstudio-20241018-120000-1.mp4";
+
+    #[test]
+    fn builds_screenrecord_command() {
+        assert_eq!(
+            RecordOptions::default().command(REMOTE, 180),
+            "screenrecord --time-limit 180 '/data/local/tmp/adbstudio-20241018-120000-1.mp4'"
+        );
+        let options = RecordOptions { bit_rate_mbps: Some(8), size: Some("1280x720".to_string()), time_limit_secs: Some(60), bugreport: true };
+        assert_eq!(
+            options.command(REMOTE, 60),
+            "screenrecord --time-limit 60 --bit-rate 8000000 --size '1280x720' --bugreport '/data/local/tmp/adbstudio-20241018-120000-1.mp4'"
+        );
+    }
+
+    #[test]
+    fn scales_bit_rate_without_overflow() {
+        let options = |mbps| RecordOptions { bit_rate_mbps: Some(mbps), ..Default::default() };
+        assert!(options(4).command(REMOTE, 10).contains(" --bit-rate 4000000 "));
+        // 5000 Mbps is past u32::MAX bits per second.
+        assert!(options(5000).command(REMOTE, 10).contains(" --bit-rate 5000000000 "));
+    }
+
+    #[test]
+    fn quotes_size() {
+        let options = RecordOptions { size: Some("720x1280; reboot".to_string()), ..Default::default() };
+        assert!(options.command(REMOTE, 10).contains(" --size '720x1280; reboot' "));
+    }
+
+    #[test]
+    fn records_until_stopped_without_a_time_limit() {
+        let mut schedule = SegmentSchedule::new(None);
+        for _ in 0..5 {
+            assert_eq!(schedule.next_limit(), MAX_SEGMENT_SECS);
+            assert!(schedule.segment_ended(MAX_SEGMENT_SECS, MAX_SEGMENT_SECS));
+        }
+    }
+
+    #[test]
+    fn chains_segments_to_the_time_limit() {
+        let mut schedule = SegmentSchedule::new(Some(400));
+        let mut limits = Vec::new();
+        loop {
+            let limit = schedule.next_limit();
+            limits.push(limit);
+            // The measured time is rounded down, so full segments often come out a second short.
+            if !schedule.segment_ended(limit, limit - 1) {
+                break;
+            }
+        }
+        assert_eq!(limits, [180, 180, 40]);
+
+        let mut schedule = SegmentSchedule::new(Some(60));
+        assert_eq!(schedule.next_limit(), 60);
+        assert!(!schedule.segment_ended(60, 60));
+
+        let mut schedule = SegmentSchedule::new(Some(360));
+        assert!(schedule.segment_ended(180, 180));
+        assert_eq!(schedule.next_limit(), 180);
+        assert!(!schedule.segment_ended(180, 181));
+    }
+
+    #[test]
+    fn stops_chaining_when_the_device_ends_a_segment() {
+        let mut schedule = SegmentSchedule::new(None);
+        assert!(!schedule.segment_ended(MAX_SEGMENT_SECS, 42));
+        let mut schedule = SegmentSchedule::new(Some(600));
+        assert!(!schedule.segment_ended(180, 100));
+    }
+}
@@ -0,0 +1,660 @@
+use std::borrow::Cow;
+use std::io::Cursor;
+use std::rc::Rc;
+use std::sync::Mutex;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Local};
+use dioxus::prelude::*;
+use image::{imageops, ImageFormat, RgbaImage};
+use rfd::AsyncFileDialog;
+use serde_json::json;
+use crate::error::AdbError;
+use crate::ui::ErrorNotice;
+
+const MAX_SCREENSHOTS: usize = 30;
+const THUMBNAIL_WIDTH: u32 = 160;
+const EDITOR_MAX_WIDTH: f64 = 720.0;
+const EDITOR_MAX_HEIGHT: f64 = 600.0;
+/// Annotation colors; real values rather than theme variables because they end up in the PNG.
+const COLORS: [&str; 5] = ["#f85149", "#d29922", "#3fb950", "#2f81f7", "#ffffff"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Annotation {
+    Rect { x: f64, y: f64, w: f64, h: f64, color: String },
+    Arrow { x1: f64, y1: f64, x2: f64, y2: f64, color: String },
+    Text { x: f64, y: f64, text: String, color: String },
+}
+
+impl Annotation {
+    fn translate(&mut self, dx: f64, dy: f64) {
+        match self {
+            Annotation::Rect { x, y, .. } | Annotation::Text { x, y, .. } => {
+                *x += dx;
+                *y += dy;
+            }
+            Annotation::Arrow { x1, y1, x2, y2, .. } => {
+                *x1 += dx;
+                *y1 += dy;
+                *x2 += dx;
+                *y2 += dy;
+            }
+        }
+    }
+
+    /// The three corners of an arrow's head, sized relative to the stroke.
+    fn arrow_head(x1: f64, y1: f64, x2: f64, y2: f64, stroke: f64) -> [(f64, f64); 3] {
+        let angle = (y2 - y1).atan2(x2 - x1);
+        let size = stroke * 4.0;
+        let spread = std::f64::consts::PI / 7.0;
+        [
+            (x2, y2),
+            (x2 - size * (angle - spread).cos(), y2 - size * (angle - spread).sin()),
+            (x2 - size * (angle + spread).cos(), y2 - size * (angle + spread).sin()),
+        ]
+    }
+}
+
+/// A captured screenshot along with any crop and annotations applied to it in the editor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Screenshot {
+    pub id: u64,
+    pub serial: String,
+    pub device: String,
+    pub taken: DateTime<Local>,
+    pub image: RgbaImage,
+    pub data_url: String,
+    pub thumbnail_url: String,
+    pub annotations: Vec<Annotation>,
+}
+
+impl Screenshot {
+    fn from_png(id: u64, serial: &str, device: &str, png: &[u8]) -> Result<Self, AdbError> {
+        let image = image::load_from_memory_with_format(png, ImageFormat::Png)
+            .map_err(|e| AdbError::Io(format!("could not decode screenshot: {}", e)))?
+            .to_rgba8();
+        let mut shot = Screenshot {
+            id,
+            serial: serial.to_string(),
+            device: device.to_string(),
+            taken: Local::now(),
+            image,
+            data_url: String::new(),
+            thumbnail_url: String::new(),
+            annotations: Vec::new(),
+        };
+        shot.data_url = data_url(png);
+        shot.refresh_thumbnail()?;
+        Ok(shot)
+    }
+
+    fn refresh_thumbnail(&mut self) -> Result<(), AdbError> {
+        let (width, height) = self.image.dimensions();
+        let thumb_height = (height as u64 * THUMBNAIL_WIDTH as u64 / width.max(1) as u64).max(1) as u32;
+        let thumbnail = imageops::thumbnail(&self.image, THUMBNAIL_WIDTH.min(width), thumb_height);
+        self.thumbnail_url = data_url(&encode_png(&thumbnail)?);
+        Ok(())
+    }
+
+    /// Crops to the given rectangle in image pixels, moving annotations along with it.
+    pub fn crop(&mut self, x: u32, y: u32, width: u32, height: u32) -> Result<(), AdbError> {
+        let (image_width, image_height) = self.image.dimensions();
+        let x = x.min(image_width.saturating_sub(1));
+        let y = y.min(image_height.saturating_sub(1));
+        let width = width.clamp(1, image_width - x);
+        let height = height.clamp(1, image_height - y);
+        self.image = imageops::crop_imm(&self.image, x, y, width, height).to_image();
+        self.data_url = data_url(&encode_png(&self.image)?);
+        self.refresh_thumbnail()?;
+        for annotation in &mut self.annotations {
+            annotation.translate(-(x as f64), -(y as f64));
+        }
+        Ok(())
+    }
+
+    pub fn stroke_width(&self) -> f64 {
+        (self.image.width() as f64 / 200.0).max(3.0)
+    }
+
+    pub fn font_size(&self) -> f64 {
+        (self.image.width() as f64 / 24.0).max(16.0)
+    }
+
+    pub fn file_name(&self) -> String {
+        format!("screenshot-{}-{}.png", self.serial.replace([':', '.'], "_"), self.taken.format("%Y%m%d-%H%M%S"))
+    }
+}
+
+/// Screenshots taken this session, shared through context so they survive switching views.
+///
+/// Each shot holds a full decoded image, so they're shared rather than copied when rendering.
+#[derive(Clone, Default)]
+pub struct ScreenshotHistory {
+    pub shots: Vec<Rc<Screenshot>>,
+    next_id: u64,
+}
+
+impl ScreenshotHistory {
+    pub fn add(&mut self, serial: &str, device: &str, png: &[u8]) -> Result<u64, AdbError> {
+        self.next_id += 1;
+        self.shots.push(Rc::new(Screenshot::from_png(self.next_id, serial, device, png)?));
+        if self.shots.len() > MAX_SCREENSHOTS {
+            self.shots.remove(0);
+        }
+        Ok(self.next_id)
+    }
+
+    pub fn get(&self, id: u64) -> Option<Rc<Screenshot>> {
+        self.shots.iter().find(|s| s.id == id).cloned()
+    }
+
+    /// Copies the shot first only if a save or copy is still using it.
+    pub fn get_mut(&mut self, id: u64) -> Option<&mut Screenshot> {
+        self.shots.iter_mut().find(|s| s.id == id).map(Rc::make_mut)
+    }
+
+    pub fn remove(&mut self, id: u64) {
+        self.shots.retain(|s| s.id != id);
+    }
+}
+
+fn encode_png(image: &RgbaImage) -> Result<Vec<u8>, AdbError> {
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|e| AdbError::Io(format!("could not encode PNG: {}", e)))?;
+    Ok(png)
+}
+
+fn data_url(png: &[u8]) -> String {
+    format!("data:image/png;base64,{}", STANDARD.encode(png))
+}
+
+/// Produces the PNG to export. Annotations are drawn onto an offscreen canvas in the webview so
+/// the text looks the same as it does in the editor.
+async fn render_png(shot: &Screenshot) -> Result<Vec<u8>, AdbError> {
+    if shot.annotations.is_empty() {
+        return encode_png(&shot.image);
+    }
+    let stroke = shot.stroke_width();
+    let shapes = shot
+        .annotations
+        .iter()
+        .map(|a| match a {
+            Annotation::Rect { x, y, w, h, color } => json!({ "kind": "rect", "x": x, "y": y, "w": w, "h": h, "color": color }),
+            Annotation::Arrow { x1, y1, x2, y2, color } => json!({
+                "kind": "arrow", "x1": x1, "y1": y1, "x2": x2, "y2": y2, "color": color,
+                "head": Annotation::arrow_head(*x1, *y1, *x2, *y2, stroke),
+            }),
+            Annotation::Text { x, y, text, color } => json!({ "kind": "text", "x": x, "y": y, "text": text, "color": color }),
+        })
+        .collect::<Vec<_>>();
+    let script = format!(
+        r#"
+        const shot = new Image();
+        shot.src = {src};
+        await shot.decode();
+        const canvas = document.createElement('canvas');
+        canvas.width = shot.naturalWidth;
+        canvas.height = shot.naturalHeight;
+        const ctx = canvas.getContext('2d');
+        ctx.drawImage(shot, 0, 0);
+        ctx.lineWidth = {stroke};
+        ctx.lineCap = 'round';
+        ctx.lineJoin = 'round';
+        ctx.font = '600 {font}px sans-serif';
+        ctx.textBaseline = 'top';
+        for (const a of {shapes}) {{
+            ctx.strokeStyle = a.color;
+            ctx.fillStyle = a.color;
+            if (a.kind === 'rect') {{
+                ctx.strokeRect(a.x, a.y, a.w, a.h);
+            }} else if (a.kind === 'arrow') {{
+                ctx.beginPath();
+                ctx.moveTo(a.x1, a.y1);
+                ctx.lineTo(a.x2, a.y2);
+                ctx.stroke();
+                ctx.beginPath();
+                ctx.moveTo(a.head[0][0], a.head[0][1]);
+                ctx.lineTo(a.head[1][0], a.head[1][1]);
+                ctx.lineTo(a.head[2][0], a.head[2][1]);
+                ctx.closePath();
+                ctx.fill();
+            }} else {{
+                ctx.fillText(a.text, a.x, a.y);
+            }}
+        }}
+        return canvas.toDataURL('image/png');
+        "#,
+        src = serde_json::to_string(&shot.data_url).unwrap_or_default(),
+        stroke = stroke,
+        font = shot.font_size(),
+        shapes = serde_json::Value::Array(shapes),
+    );
+    let url = document::eval(&script)
+        .join::<String>()
+        .await
+        .map_err(|e| AdbError::Io(format!("could not render annotations: {:?}", e)))?;
+    let encoded = url.split_once(',').map(|(_, data)| data).unwrap_or_default();
+    STANDARD
+        .decode(encoded)
+        .map_err(|e| AdbError::Io(format!("could not render annotations: {}", e)))
+}
+
+/// On X11 and Wayland the clipboard is served by the process that set it, so the handle lives as
+/// long as the app instead of being dropped right after copying.
+static CLIPBOARD: Mutex<Option<arboard::Clipboard>> = Mutex::new(None);
+
+fn copy_png_to_clipboard(png: &[u8]) -> Result<(), AdbError> {
+    let image = image::load_from_memory_with_format(png, ImageFormat::Png)
+        .map_err(|e| AdbError::Io(format!("could not decode screenshot: {}", e)))?
+        .to_rgba8();
+    let clipboard_error = |e: arboard::Error| AdbError::Io(format!("could not copy to clipboard: {}", e));
+    let mut guard = CLIPBOARD.lock().unwrap_or_else(|e| e.into_inner());
+    if guard.is_none() {
+        *guard = Some(arboard::Clipboard::new().map_err(clipboard_error)?);
+    }
+    let clipboard = guard.as_mut().expect("clipboard initialised above");
+    clipboard
+        .set_image(arboard::ImageData {
+            width: image.width() as usize,
+            height: image.height() as usize,
+            bytes: Cow::Owned(image.into_raw()),
+        })
+        .map_err(clipboard_error)
+}
+
+/// Asks where to save the screenshot, with its annotations, and writes it there.
+pub async fn save_screenshot(shot: Rc<Screenshot>) -> Result<Option<String>, AdbError> {
+    let Some(file) = AsyncFileDialog::new()
+        .add_filter("PNG", &["png"])
+        .set_file_name(shot.file_name())
+        .save_file()
+        .await
+    else {
+        return Ok(None);
+    };
+    let png = render_png(&shot).await?;
+    tokio::fs::write(file.path(), png).await?;
+    Ok(Some(file.path().display().to_string()))
+}
+
+pub async fn copy_screenshot(shot: Rc<Screenshot>) -> Result<(), AdbError> {
+    let png = render_png(&shot).await?;
+    tokio::task::spawn_blocking(move || copy_png_to_clipboard(&png))
+        .await
+        .map_err(|e| AdbError::Io(e.to_string()))?
+}
+
+#[component]
+pub fn ScreenshotGallery(selected: Signal<Option<u64>>, status: Signal<String>) -> Element {
+    let mut history = use_context::<Signal<ScreenshotHistory>>();
+    let mut error = use_signal(|| None::<AdbError>);
+    let shots = history.read().shots.iter().rev().cloned().collect::<Vec<_>>();
+    let save = move |id: u64| {
+        let Some(shot) = history.peek().get(id) else {
+            return;
+        };
+        spawn(async move {
+            match save_screenshot(shot).await {
+                Ok(Some(path)) => status.set(format!("Saved to {}", path)),
+                Ok(None) => {}
+                Err(e) => error.set(Some(e)),
+            }
+        });
+    };
+    let copy = move |id: u64| {
+        let Some(shot) = history.peek().get(id) else {
+            return;
+        };
+        spawn(async move {
+            match copy_screenshot(shot).await {
+                Ok(()) => status.set("Copied to clipboard".to_string()),
+                Err(e) => error.set(Some(e)),
+            }
+        });
+    };
+
+    rsx! {
+        if let Some(e) = error() {
+            ErrorNotice { error: e }
+        }
+        if !shots.is_empty() {
+            h3 { "Screenshots ({shots.len()})" }
+        }
+        div {
+            style: "display: flex; flex-wrap: wrap; gap: 12px;",
+            for (id, shot) in shots.iter().map(|s| (s.id, s.clone())) {
+                div {
+                    key: "{id}",
+                    style: format!(
+                        "width: {}px; padding: 8px; background: var(--card-bg); border: 1px solid {}; border-radius: 8px; cursor: pointer;",
+                        THUMBNAIL_WIDTH + 16,
+                        if selected() == Some(id) { "var(--accent-color)" } else { "var(--border-color)" }
+                    ),
+                    onclick: move |_| selected.set(Some(id)),
+                    img { src: "{shot.thumbnail_url}", style: "width: 100%; border-radius: 4px; display: block;" }
+                    div { style: "font-size: 0.75rem; color: var(--secondary-text); margin-top: 6px;",
+                        {format!("{} · {}", shot.taken.format("%H:%M:%S"), shot.device)}
+                    }
+                    div {
+                        style: "display: flex; gap: 4px; margin-top: 6px;",
+                        button {
+                            class: "btn btn-secondary",
+                            style: "margin: 0; padding: 4px 8px;",
+                            title: "Save",
+                            onclick: move |e| {
+                                e.stop_propagation();
+                                save(id);
+                            },
+                            i { class: "fas fa-save" }
+                        }
+                        button {
+                            class: "btn btn-secondary",
+                            style: "margin: 0; padding: 4px 8px;",
+                            title: "Copy to clipboard",
+                            onclick: move |e| {
+                                e.stop_propagation();
+                                copy(id);
+                            },
+                            i { class: "fas fa-copy" }
+                        }
+                        button {
+                            class: "btn btn-secondary",
+                            style: "margin: 0; padding: 4px 8px;",
+                            title: "Remove",
+                            onclick: move |e| {
+                                e.stop_propagation();
+                                history.write().remove(id);
+                                if selected() == Some(id) {
+                                    selected.set(None);
+                                }
+                            },
+                            i { class: "fas fa-trash" }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Tool {
+    Crop,
+    Rect,
+    Arrow,
+    Text,
+}
+
+impl Tool {
+    const ALL: [Tool; 4] = [Tool::Crop, Tool::Rect, Tool::Arrow, Tool::Text];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Tool::Crop => "Crop",
+            Tool::Rect => "Rectangle",
+            Tool::Arrow => "Arrow",
+            Tool::Text => "Text",
+        }
+    }
+
+    fn icon(&self) -> &'static str {
+        match self {
+            Tool::Crop => "fas fa-crop-alt",
+            Tool::Rect => "far fa-square",
+            Tool::Arrow => "fas fa-long-arrow-alt-right",
+            Tool::Text => "fas fa-font",
+        }
+    }
+}
+
+/// A drag in image pixels: where it started and where the pointer is now.
+type Drag = (f64, f64, f64, f64);
+
+fn normalized((x1, y1, x2, y2): Drag) -> (f64, f64, f64, f64) {
+    (x1.min(x2), y1.min(y2), (x2 - x1).abs(), (y2 - y1).abs())
+}
+
+#[component]
+pub fn ScreenshotEditor(id: u64, status: Signal<String>) -> Element {
+    let mut history = use_context::<Signal<ScreenshotHistory>>();
+    let mut tool = use_signal(|| Tool::Rect);
+    let mut color = use_signal(|| COLORS[0].to_string());
+    let mut label = use_signal(String::new);
+    let mut drag = use_signal(|| None::<Drag>);
+    let mut crop = use_signal(|| None::<Drag>);
+    let mut error = use_signal(|| None::<AdbError>);
+
+    let Some(shot) = history.read().get(id) else {
+        return rsx! {};
+    };
+    let (width, height) = (shot.image.width() as f64, shot.image.height() as f64);
+    let scale = (EDITOR_MAX_WIDTH / width).min(EDITOR_MAX_HEIGHT / height).min(1.0);
+    let stroke = shot.stroke_width();
+    let font_size = shot.font_size();
+    // Pointer positions arrive in display pixels; everything is stored in image pixels.
+    let to_image = move |e: &MouseEvent| {
+        let p = e.element_coordinates();
+        ((p.x / scale).clamp(0.0, width), (p.y / scale).clamp(0.0, height))
+    };
+    let preview = drag().map(|d| match tool() {
+        Tool::Crop => None,
+        Tool::Rect => {
+            let (x, y, w, h) = normalized(d);
+            Some(Annotation::Rect { x, y, w, h, color: color() })
+        }
+        Tool::Arrow => Some(Annotation::Arrow { x1: d.0, y1: d.1, x2: d.2, y2: d.3, color: color() }),
+        Tool::Text => None,
+    });
+    let crop_area = if tool() == Tool::Crop { drag().or(crop()) } else { None }.map(normalized);
+    let shapes = shot.annotations.iter().cloned().chain(preview.flatten()).collect::<Vec<_>>();
+
+    rsx! {
+        div {
+            class: "device-info",
+            h3 { "Edit Screenshot" }
+            div {
+                style: "display: flex; flex-wrap: wrap; align-items: center; gap: 4px;",
+                for t in Tool::ALL {
+                    button {
+                        class: if tool() == t { "btn" } else { "btn btn-secondary" },
+                        onclick: move |_| {
+                            tool.set(t);
+                            drag.set(None);
+                        },
+                        i { class: t.icon() }
+                        "{t.label()}"
+                    }
+                }
+                for c in COLORS {
+                    button {
+                        title: "{c}",
+                        style: format!(
+                            "width: 24px; height: 24px; margin: 0 2px 8px; border-radius: 50%; cursor: pointer; background: {}; border: 2px solid {};",
+                            c,
+                            if color() == c { "var(--primary-text)" } else { "var(--border-color)" }
+                        ),
+                        onclick: move |_| color.set(c.to_string()),
+                    }
+                }
+                if tool() == Tool::Text {
+                    input {
+                        r#type: "text",
+                        placeholder: "Label text, then click the image",
+                        value: "{label}",
+                        style: "max-width: 240px; margin-bottom: 8px;",
+                        oninput: move |e| label.set(e.value()),
+                    }
+                }
+            }
+            div {
+                button {
+                    class: "btn btn-secondary",
+                    disabled: shot.annotations.is_empty(),
+                    onclick: move |_| {
+                        if let Some(shot) = history.write().get_mut(id) {
+                            shot.annotations.pop();
+                        }
+                    },
+                    i { class: "fas fa-undo" }
+                    "Undo"
+                }
+                button {
+                    class: "btn btn-secondary",
+                    disabled: shot.annotations.is_empty(),
+                    onclick: move |_| {
+                        if let Some(shot) = history.write().get_mut(id) {
+                            shot.annotations.clear();
+                        }
+                    },
+                    i { class: "fas fa-eraser" }
+                    "Clear Annotations"
+                }
+                if tool() == Tool::Crop {
+                    button {
+                        class: "btn btn-secondary",
+                        disabled: crop().is_none(),
+                        onclick: move |_| {
+                            let Some((x, y, w, h)) = crop().map(normalized) else {
+                                return;
+                            };
+                            if let Some(shot) = history.write().get_mut(id) {
+                                if let Err(e) = shot.crop(x as u32, y as u32, w as u32, h as u32) {
+                                    error.set(Some(e));
+                                }
+                            }
+                            crop.set(None);
+                        },
+                        i { class: "fas fa-check" }
+                        "Apply Crop"
+                    }
+                }
+                button {
+                    class: "btn",
+                    onclick: move |_| {
+                        let Some(shot) = history.peek().get(id) else {
+                            return;
+                        };
+                        spawn(async move {
+                            match save_screenshot(shot).await {
+                                Ok(Some(path)) => status.set(format!("Saved to {}", path)),
+                                Ok(None) => {}
+                                Err(e) => error.set(Some(e)),
+                            }
+                        });
+                    },
+                    i { class: "fas fa-save" }
+                    "Save As..."
+                }
+                button {
+                    class: "btn btn-secondary",
+                    onclick: move |_| {
+                        let Some(shot) = history.peek().get(id) else {
+                            return;
+                        };
+                        spawn(async move {
+                            match copy_screenshot(shot).await {
+                                Ok(()) => status.set("Copied to clipboard".to_string()),
+                                Err(e) => error.set(Some(e)),
+                            }
+                        });
+                    },
+                    i { class: "fas fa-copy" }
+                    "Copy"
+                }
+            }
+            if let Some(e) = error() {
+                ErrorNotice { error: e }
+            }
+            div {
+                style: format!("position: relative; width: {}px; height: {}px; margin-top: 8px;", width * scale, height * scale),
+                img {
+                    src: "{shot.data_url}",
+                    draggable: "false",
+                    style: "width: 100%; height: 100%; display: block; border-radius: 4px;",
+                }
+                svg {
+                    view_box: "0 0 {width} {height}",
+                    preserve_aspect_ratio: "none",
+                    style: "position: absolute; inset: 0; width: 100%; height: 100%; cursor: crosshair;",
+                    onmousedown: move |e| {
+                        let (x, y) = to_image(&e);
+                        if tool() == Tool::Text {
+                            let text = label().trim().to_string();
+                            if !text.is_empty() {
+                                if let Some(shot) = history.write().get_mut(id) {
+                                    shot.annotations.push(Annotation::Text { x, y, text, color: color() });
+                                }
+                            }
+                            return;
+                        }
+                        drag.set(Some((x, y, x, y)));
+                    },
+                    onmousemove: move |e| {
+                        if let Some((x1, y1, _, _)) = drag() {
+                            let (x, y) = to_image(&e);
+                            drag.set(Some((x1, y1, x, y)));
+                        }
+                    },
+                    onmouseup: move |_| {
+                        let Some(d) = drag() else {
+                            return;
+                        };
+                        drag.set(None);
+                        let (x, y, w, h) = normalized(d);
+                        // Ignore clicks that didn't really drag anything.
+                        if w < 4.0 && h < 4.0 {
+                            return;
+                        }
+                        let annotation = match tool() {
+                            Tool::Crop => {
+                                crop.set(Some(d));
+                                return;
+                            }
+                            Tool::Rect => Annotation::Rect { x, y, w, h, color: color() },
+                            Tool::Arrow => Annotation::Arrow { x1: d.0, y1: d.1, x2: d.2, y2: d.3, color: color() },
+                            Tool::Text => return,
+                        };
+                        if let Some(shot) = history.write().get_mut(id) {
+                            shot.annotations.push(annotation);
+                        }
+                    },
+                    for shape in shapes {
+                        match shape {
+                            Annotation::Rect { x, y, w, h, color } => rsx! {
+                                rect { x: "{x}", y: "{y}", width: "{w}", height: "{h}", fill: "none", stroke: "{color}", stroke_width: "{stroke}", pointer_events: "none" }
+                            },
+                            Annotation::Arrow { x1, y1, x2, y2, color } => {
+                                let head = Annotation::arrow_head(x1, y1, x2, y2, stroke)
+                                    .iter()
+                                    .map(|(x, y)| format!("{},{}", x, y))
+                                    .collect::<Vec<_>>()
+                                    .join(" ");
+                                rsx! {
+                                    line { x1: "{x1}", y1: "{y1}", x2: "{x2}", y2: "{y2}", stroke: "{color}", stroke_width: "{stroke}", stroke_linecap: "round", pointer_events: "none" }
+                                    polygon { points: "{head}", fill: "{color}", pointer_events: "none" }
+                                }
+                            }
+                            Annotation::Text { x, y, text, color } => rsx! {
+                                text { x: "{x}", y: "{y}", fill: "{color}", font_size: "{font_size}", font_weight: "600", font_family: "sans-serif", dominant_baseline: "hanging", pointer_events: "none", "{text}" }
+                            },
+                        }
+                    }
+                    if let Some((x, y, w, h)) = crop_area {
+                        rect { x: "{x}", y: "{y}", width: "{w}", height: "{h}", fill: "rgba(47,129,247,0.15)", stroke: "#2f81f7", stroke_width: "{stroke / 2.0}", stroke_dasharray: "{stroke * 2.0}", pointer_events: "none" }
+                    }
+                }
+            }
+            div { style: "font-size: 0.75rem; color: var(--secondary-text); margin-top: 6px;",
+                {format!(
+                    "{} × {} · {} ({}) · {}",
+                    shot.image.width(),
+                    shot.image.height(),
+                    shot.device,
+                    shot.serial,
+                    shot.taken.format("%Y-%m-%d %H:%M:%S")
+                )}
+            }
+        }
+    }
+}
@@ -1,16 +1,84 @@
 use dioxus::prelude::*;
-use confy::{load, store};
+use confy::store;
 use serde::{Serialize, Deserialize};
 
-#[derive(Default, Serialize, Deserialize, Clone)]
+#[derive(Default, Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum AdbBackend {
+    /// Talk to the adb server over its TCP protocol.
+    #[default]
+    Server,
+    /// Spawn the `adb` binary for every call.
+    Executable,
+}
+
+/// A device that has been reached over the network before, kept so it can be reconnected.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct KnownDevice {
+    pub address: String,
+    pub name: String,
+}
+
+pub const DEFAULT_LOG_BUFFER_CAPACITY: usize = 50_000;
+pub const MIN_LOG_BUFFER_CAPACITY: usize = 1000;
+pub const DEFAULT_MIRROR_FPS: u32 = 4;
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct AdbStudioSettings {
     pub adb_path: String,
     pub dark_mode: bool,
+    pub backend: AdbBackend,
+    /// Maximum number of logcat entries kept in memory; older entries are dropped.
+    pub log_buffer_capacity: usize,
+    /// Frames per second requested by the screen mirror.
+    pub mirror_fps: u32,
+    pub known_devices: Vec<KnownDevice>,
+}
+
+impl Default for AdbStudioSettings {
+    fn default() -> Self {
+        Self {
+            adb_path: String::new(),
+            dark_mode: false,
+            backend: AdbBackend::default(),
+            log_buffer_capacity: DEFAULT_LOG_BUFFER_CAPACITY,
+            mirror_fps: DEFAULT_MIRROR_FPS,
+            known_devices: Vec::new(),
+        }
+    }
+}
+
+impl AdbStudioSettings {
+    pub fn adb_program(&self) -> &str {
+        if self.adb_path.is_empty() { "adb" } else { &self.adb_path }
+    }
+
+    /// Adds or updates a network device; a blank name keeps the one already saved.
+    pub fn remember_device(&mut self, address: &str, name: &str) {
+        match self.known_devices.iter_mut().find(|d| d.address == address) {
+            Some(device) if !name.is_empty() => device.name = name.to_string(),
+            Some(_) => {}
+            None => self.known_devices.push(KnownDevice { address: address.to_string(), name: name.to_string() }),
+        }
+    }
+
+    pub fn forget_device(&mut self, address: &str) {
+        self.known_devices.retain(|d| d.address != address);
+    }
+}
+
+/// Reads a committed Log Buffer Size entry. Returns `None` for text that isn't a number, which
+/// keeps the current size.
+pub fn parse_log_buffer_capacity(text: &str) -> Option<usize> {
+    text.trim().parse::<usize>().ok().map(|capacity| capacity.max(MIN_LOG_BUFFER_CAPACITY))
 }
 
 #[component]
 pub fn Settings() -> Element {
-    let mut settings = use_signal(|| load::<AdbStudioSettings>("adb-studio", None).unwrap_or_default());
+    let mut settings = use_context::<Signal<AdbStudioSettings>>();
+    // The number being typed. The buffer size only changes once the edit is committed, so the
+    // intermediate values never shrink a running log.
+    let mut capacity_draft = use_signal(|| settings.peek().log_buffer_capacity.to_string());
 
     rsx! {
         div {
@@ -27,6 +95,36 @@ pub fn Settings() -> Element {
                     },
                 }
             }
+            div { class: "form-group",
+                label { "ADB Connection" }
+                select {
+                    class: "btn btn-secondary",
+                    value: if settings.read().backend == AdbBackend::Server { "server" } else { "executable" },
+                    onchange: move |event| {
+                        settings.write().backend = if event.value() == "server" { AdbBackend::Server } else { AdbBackend::Executable };
+                        let _ = store("adb-studio", None, settings.read().clone());
+                    },
+                    option { value: "server", "ADB server protocol (native)" }
+                    option { value: "executable", "Spawn adb binary (fallback)" }
+                }
+            }
+            div { class: "form-group",
+                label { "Log Buffer Size (entries)" }
+                input {
+                    r#type: "number",
+                    min: "{MIN_LOG_BUFFER_CAPACITY}",
+                    step: "1000",
+                    value: "{capacity_draft}",
+                    oninput: move |event| capacity_draft.set(event.value()),
+                    onchange: move |event| {
+                        if let Some(capacity) = parse_log_buffer_capacity(&event.value()) {
+                            settings.write().log_buffer_capacity = capacity;
+                            let _ = store("adb-studio", None, settings.read().clone());
+                        }
+                        capacity_draft.set(settings.peek().log_buffer_capacity.to_string());
+                    },
+                }
+            }
             div { class: "form-group",
                 label { 
                     "Dark Mode"
@@ -42,4 +140,58 @@ pub fn Settings() -> Element {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logcat::{LogBuffer, LogEntry, LogLevel};
+
+    fn filled_buffer(capacity: usize, count: usize) -> LogBuffer {
+        let mut buffer = LogBuffer::new(capacity);
+        buffer.extend((0..count).map(|i| LogEntry {
+            timestamp: "10-18 12:00:00.000".to_string(),
+            pid: 1502,
+            tid: 1520,
+            level: LogLevel::Info,
+            tag: "ActivityManager".to_string(),
+            message: format!("entry {}", i),
+        }));
+        buffer
+    }
+
+    #[test]
+    fn log_buffer_shrinks_only_on_commit() {
+        let mut settings = AdbStudioSettings::default();
+        let mut buffer = filled_buffer(settings.log_buffer_capacity, 15_000);
+
+        // Clearing the field and typing 20000 only edits the draft; the setting the log viewer
+        // follows changes once, when the edit is committed.
+        let mut draft = String::new();
+        for key in "20000".chars() {
+            draft.push(key);
+            assert_eq!(settings.log_buffer_capacity, DEFAULT_LOG_BUFFER_CAPACITY);
+        }
+        settings.log_buffer_capacity = parse_log_buffer_capacity(&draft).unwrap();
+        buffer.set_capacity(settings.log_buffer_capacity);
+        assert_eq!(buffer.capacity(), 20_000);
+        assert_eq!(buffer.len(), 15_000);
+        assert_eq!(buffer.dropped(), 0);
+
+        // A committed value below the minimum is clamped, and only then are entries dropped.
+        settings.log_buffer_capacity = parse_log_buffer_capacity("2").unwrap();
+        buffer.set_capacity(settings.log_buffer_capacity);
+        assert_eq!(buffer.len(), MIN_LOG_BUFFER_CAPACITY);
+        assert_eq!(buffer.get(0).unwrap().message, "entry 14000");
+    }
+
+    #[test]
+    fn parses_log_buffer_capacity() {
+        assert_eq!(parse_log_buffer_capacity("20000"), Some(20_000));
+        assert_eq!(parse_log_buffer_capacity(" 5000 "), Some(5000));
+        assert_eq!(parse_log_buffer_capacity("0"), Some(MIN_LOG_BUFFER_CAPACITY));
+        assert_eq!(parse_log_buffer_capacity(""), None);
+        assert_eq!(parse_log_buffer_capacity("-1"), None);
+        assert_eq!(parse_log_buffer_capacity("1e4"), None);
+    }
+}
@@ -0,0 +1,181 @@
+use std::process::Stdio;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::process::Command;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use crate::adb::server_client;
+use crate::adb_client::{read_shell_packet, write_shell_packet, SHELL_EXIT, SHELL_STDERR, SHELL_STDIN, SHELL_STDOUT, SHELL_WINDOW_SIZE};
+use crate::error::AdbError;
+use crate::settings::{AdbBackend, AdbStudioSettings};
+
+const TERM: &str = "xterm-256color";
+
+pub enum SessionEvent {
+    Output(Vec<u8>),
+    Exited(Option<i32>),
+}
+
+enum SessionInput {
+    Data(Vec<u8>),
+    Resize { rows: u16, cols: u16 },
+}
+
+/// Handle to a persistent `adb shell` running on a pseudo-terminal. Dropping every clone of the
+/// handle closes the shell.
+#[derive(Clone)]
+pub struct ShellSession {
+    input: UnboundedSender<SessionInput>,
+    resizable: bool,
+}
+
+impl PartialEq for ShellSession {
+    fn eq(&self, other: &Self) -> bool {
+        self.input.same_channel(&other.input)
+    }
+}
+
+impl ShellSession {
+    pub async fn open(
+        settings: &AdbStudioSettings,
+        serial: &str,
+        rows: u16,
+        cols: u16,
+    ) -> Result<(Self, UnboundedReceiver<SessionEvent>), AdbError> {
+        let (input_tx, input_rx) = mpsc::unbounded_channel();
+        let (event_tx, event_rx) = mpsc::unbounded_channel();
+        let resizable = match settings.backend {
+            AdbBackend::Server => open_server_shell(settings, serial, input_rx, event_tx).await?,
+            AdbBackend::Executable => open_executable_shell(settings, serial, input_rx, event_tx)?,
+        };
+        let session = Self { input: input_tx, resizable };
+        session.resize(rows, cols);
+        Ok((session, event_rx))
+    }
+
+    pub fn write(&self, data: impl Into<Vec<u8>>) {
+        let _ = self.input.send(SessionInput::Data(data.into()));
+    }
+
+    /// Only shells opened through the shell v2 protocol can follow the view size; the others
+    /// keep the size the device picked.
+    pub fn resize(&self, rows: u16, cols: u16) {
+        if self.resizable {
+            let _ = self.input.send(SessionInput::Resize { rows, cols });
+        }
+    }
+
+    pub fn is_resizable(&self) -> bool {
+        self.resizable
+    }
+}
+
+async fn open_server_shell(
+    settings: &AdbStudioSettings,
+    serial: &str,
+    mut input: UnboundedReceiver<SessionInput>,
+    events: UnboundedSender<SessionEvent>,
+) -> Result<bool, AdbError> {
+    let (conn, shell_v2) = server_client(settings).open_pty_shell(serial, TERM).await?;
+    let (mut reader, mut writer) = conn.into_split();
+
+    let reader_task = tokio::spawn(async move {
+        if !shell_v2 {
+            pump_raw(&mut reader, &events).await;
+            let _ = events.send(SessionEvent::Exited(None));
+            return;
+        }
+        let mut exit_code = None;
+        while let Ok(Some((id, data))) = read_shell_packet(&mut reader).await {
+            let output = match id {
+                SHELL_STDOUT | SHELL_STDERR => data,
+                SHELL_EXIT => {
+                    exit_code = data.first().map(|c| *c as i32);
+                    break;
+                }
+                _ => continue,
+            };
+            if events.send(SessionEvent::Output(output)).is_err() {
+                return;
+            }
+        }
+        let _ = events.send(SessionEvent::Exited(exit_code));
+    });
+
+    tokio::spawn(async move {
+        while let Some(message) = input.recv().await {
+            let result = match (message, shell_v2) {
+                (SessionInput::Data(data), true) => write_shell_packet(&mut writer, SHELL_STDIN, &data).await,
+                (SessionInput::Data(data), false) => writer.write_all(&data).await.map_err(AdbError::from),
+                (SessionInput::Resize { rows, cols }, true) => {
+                    let size = format!("{}x{},0x0\0", rows, cols);
+                    write_shell_packet(&mut writer, SHELL_WINDOW_SIZE, size.as_bytes()).await
+                }
+                (SessionInput::Resize { .. }, false) => Ok(()),
+            };
+            if result.is_err() {
+                break;
+            }
+        }
+        reader_task.abort();
+    });
+
+    Ok(shell_v2)
+}
+
+fn open_executable_shell(
+    settings: &AdbStudioSettings,
+    serial: &str,
+    mut input: UnboundedReceiver<SessionInput>,
+    events: UnboundedSender<SessionEvent>,
+) -> Result<bool, AdbError> {
+    // `-t -t` forces a remote pty even though our stdin is a pipe.
+    let mut child = Command::new(settings.adb_program())
+        .args(["-s", serial, "shell", "-t", "-t"])
+        .env("TERM", TERM)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| AdbError::from_spawn(settings.adb_program(), e))?;
+    let mut stdin = child.stdin.take().ok_or_else(|| AdbError::Spawn("no stdin".to_string()))?;
+    let mut stdout = child.stdout.take().ok_or_else(|| AdbError::Spawn("no stdout".to_string()))?;
+    let mut stderr = child.stderr.take().ok_or_else(|| AdbError::Spawn("no stderr".to_string()))?;
+
+    let stderr_events = events.clone();
+    tokio::spawn(async move { pump_raw(&mut stderr, &stderr_events).await });
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                message = input.recv() => match message {
+                    Some(SessionInput::Data(data)) => {
+                        if stdin.write_all(&data).await.is_err() || stdin.flush().await.is_err() {
+                            break;
+                        }
+                    }
+                    Some(SessionInput::Resize { .. }) => {}
+                    // Every handle is gone: dropping `child` kills the adb process.
+                    None => return,
+                },
+                _ = pump_raw(&mut stdout, &events) => break,
+            }
+        }
+        let status = child.wait().await.ok().and_then(|s| s.code());
+        let _ = events.send(SessionEvent::Exited(status));
+    });
+
+    Ok(false)
+}
+
+async fn pump_raw<R: AsyncRead + Unpin>(reader: &mut R, events: &UnboundedSender<SessionEvent>) {
+    let mut buf = vec![0u8; 8192];
+    loop {
+        match reader.read(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => {
+                if events.send(SessionEvent::Output(buf[..n].to_vec())).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+}
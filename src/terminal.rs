@@ -1,42 +1,391 @@
-use crate::adb::adb_shell;
+use crate::adb::{adb_shell, cancellable};
 use dioxus::prelude::*;
+use dioxus::core::spawn_forever;
+use crate::app::AppState;
 use crate::settings::AdbStudioSettings;
+use crate::shell_session::{SessionEvent, ShellSession};
+use crate::ui::terminal::{render_window, TerminalScreen, TerminalView};
+use tokio_util::sync::CancellationToken;
+
+const SCROLLBACK_LINES: usize = 2000;
+
+pub struct ShellTab {
+    pub id: usize,
+    pub serial: String,
+    pub label: String,
+    pub screen: TerminalScreen,
+    pub session: Option<ShellSession>,
+}
+
+/// Interactive shells live in app-wide state so they survive switching views.
+#[derive(Default)]
+pub struct ShellTabs {
+    pub tabs: Vec<ShellTab>,
+    next_id: usize,
+}
+
+impl ShellTabs {
+    fn add(&mut self, serial: &str, model: &str) -> usize {
+        self.next_id += 1;
+        let count = self.tabs.iter().filter(|t| t.serial == serial).count() + 1;
+        let name = if model.is_empty() { serial } else { model };
+        self.tabs.push(ShellTab {
+            id: self.next_id,
+            serial: serial.to_string(),
+            label: format!("{} #{}", name, count),
+            screen: TerminalScreen::new(24, 80, SCROLLBACK_LINES),
+            session: None,
+        });
+        self.next_id
+    }
+
+    fn get_mut(&mut self, id: usize) -> Option<&mut ShellTab> {
+        self.tabs.iter_mut().find(|t| t.id == id)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ConsoleTab {
+    Commands,
+    Shell(usize),
+}
 
 #[component]
 pub fn Terminal() -> Element {
+    let mut shell_tabs = use_context::<Signal<ShellTabs>>();
+    let mut active = use_signal(|| ConsoleTab::Commands);
+    let app_state = use_context::<Signal<AppState>>();
+    let settings = use_context::<Signal<AdbStudioSettings>>();
+
+    let open_shell = move |_| {
+        let Some(device) = app_state.read().selected_device.clone() else {
+            return;
+        };
+        let settings_clone = settings.read().clone();
+        let id = shell_tabs.write().add(&device.serial, device.name());
+        active.set(ConsoleTab::Shell(id));
+        // Not tied to this view: the shell keeps running while other views are open.
+        spawn_forever(async move {
+            let (rows, cols) = shell_tabs.read().tabs.iter().find(|t| t.id == id).map(|t| t.screen.size()).unwrap_or((24, 80));
+            match ShellSession::open(&settings_clone, &device.serial, rows as u16, cols as u16).await {
+                Ok((session, mut events)) => {
+                    match shell_tabs.write().get_mut(id) {
+                        Some(tab) => tab.session = Some(session),
+                        None => return,
+                    }
+                    while let Some(event) = events.recv().await {
+                        let mut tabs = shell_tabs.write();
+                        let Some(tab) = tabs.get_mut(id) else {
+                            break;
+                        };
+                        match event {
+                            SessionEvent::Output(bytes) => {
+                                tab.screen.feed(&bytes);
+                                let responses = tab.screen.take_responses();
+                                if let (false, Some(session)) = (responses.is_empty(), &tab.session) {
+                                    session.write(responses);
+                                }
+                            }
+                            SessionEvent::Exited(code) => {
+                                let code = code.map(|c| c.to_string()).unwrap_or_else(|| "?".to_string());
+                                tab.screen.feed(format!("\r\n[process exited with code {}]\r\n", code).as_bytes());
+                                tab.session = None;
+                            }
+                        }
+                    }
+                }
+                Err(e) => {
+                    if let Some(tab) = shell_tabs.write().get_mut(id) {
+                        tab.screen.feed(format!("\x1b[31m{}\x1b[0m\r\n{}\r\n", e, e.remediation()).as_bytes());
+                    }
+                }
+            }
+        });
+    };
+
+    let has_device = app_state.read().selected_device.is_some();
+    let tab_list = shell_tabs.read().tabs.iter().map(|t| (t.id, t.label.clone(), t.session.is_some())).collect::<Vec<_>>();
+    let current = match active() {
+        ConsoleTab::Shell(id) if tab_list.iter().any(|(tab_id, _, _)| *tab_id == id) => ConsoleTab::Shell(id),
+        _ => ConsoleTab::Commands,
+    };
+
+    rsx! {
+        div {
+            h2 { "Terminal" }
+            div { style: "display: flex; flex-wrap: wrap; align-items: center; gap: 6px; margin-bottom: 12px;",
+                button {
+                    class: if current == ConsoleTab::Commands { "btn" } else { "btn btn-secondary" },
+                    onclick: move |_| active.set(ConsoleTab::Commands),
+                    i { class: "fas fa-list" }
+                    "Commands"
+                }
+                for (id, label, running) in tab_list {
+                    div {
+                        class: if current == ConsoleTab::Shell(id) { "btn" } else { "btn btn-secondary" },
+                        onclick: move |_| active.set(ConsoleTab::Shell(id)),
+                        i { class: if running { "fas fa-terminal" } else { "fas fa-ban" } }
+                        "{label}"
+                        i {
+                            class: "fas fa-times",
+                            title: "Close shell",
+                            onclick: move |event| {
+                                event.stop_propagation();
+                                shell_tabs.write().tabs.retain(|t| t.id != id);
+                            },
+                        }
+                    }
+                }
+                button {
+                    class: "btn btn-secondary",
+                    disabled: !has_device,
+                    onclick: open_shell,
+                    i { class: "fas fa-plus" }
+                    "New Shell"
+                }
+            }
+            match current {
+                ConsoleTab::Commands => rsx! { CommandConsole {} },
+                ConsoleTab::Shell(id) => rsx! { ShellTabView { key: "{id}", id } },
+            }
+        }
+    }
+}
+
+#[component]
+fn ShellTabView(id: usize) -> Element {
+    let mut shell_tabs = use_context::<Signal<ShellTabs>>();
+    let mut scrolled_to = use_signal(|| None::<usize>);
+    let tabs = shell_tabs.read();
+    let Some(tab) = tabs.tabs.iter().find(|t| t.id == id) else {
+        return rsx! {};
+    };
+    let total_rows = tab.screen.line_count();
+    let window = render_window(total_rows, tab.screen.size().0, scrolled_to());
+    let first_row = window.start;
+    let rows = tab.screen.render_range(window, tab.session.is_some());
+    let app_cursor_keys = tab.screen.app_cursor_keys;
+    let resizable = tab.session.as_ref().map(|s| s.is_resizable()).unwrap_or(true);
+    let serial = tab.serial.clone();
+    let title = tab.screen.title.clone();
+    drop(tabs);
+
+    rsx! {
+        div { style: "display: flex; align-items: center; gap: 12px; font-size: 0.875rem; color: var(--secondary-text);",
+            span { i { class: "fas fa-mobile-alt" } " {serial}" }
+            if !title.is_empty() {
+                span { "{title}" }
+            }
+            if !resizable {
+                span { style: "margin-left: auto;", "Window size changes are not forwarded with this connection" }
+            }
+        }
+        TerminalView {
+            id: "shell-{id}",
+            total_rows,
+            first_row,
+            rows,
+            app_cursor_keys,
+            on_input: move |bytes: Vec<u8>| {
+                if let Some(session) = shell_tabs.read().tabs.iter().find(|t| t.id == id).and_then(|t| t.session.as_ref()) {
+                    session.write(bytes);
+                }
+            },
+            on_resize: move |(rows, cols): (u16, u16)| {
+                let mut tabs = shell_tabs.write();
+                if let Some(tab) = tabs.get_mut(id) {
+                    if tab.screen.size() != (rows as usize, cols as usize) {
+                        tab.screen.resize(rows as usize, cols as usize);
+                        if let Some(session) = &tab.session {
+                            session.resize(rows, cols);
+                        }
+                    }
+                }
+            },
+            on_scroll: move |first_visible: Option<usize>| {
+                if *scrolled_to.peek() != first_visible {
+                    scrolled_to.set(first_visible);
+                }
+            },
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+struct DeviceOutput {
+    serial: String,
+    output: String,
+    failed: bool,
+    running: bool,
+}
+
+#[component]
+fn CommandConsole() -> Element {
     let mut command = use_signal(String::new);
     let mut output = use_signal(String::new);
+    let mut broadcast = use_signal(|| false);
+    let mut broadcast_serials = use_signal(Vec::<String>::new);
+    let mut broadcast_outputs = use_signal(Vec::<DeviceOutput>::new);
+    let mut is_running = use_signal(|| false);
+    let mut cancel = use_signal(CancellationToken::new);
+    let app_state = use_context::<Signal<AppState>>();
     let settings = use_context::<Signal<AdbStudioSettings>>();
 
+    let execute = move |_| {
+        let cmd = command.read().clone();
+        let settings_clone = settings.read().clone();
+        let token = CancellationToken::new();
+        cancel.set(token.clone());
+        if broadcast() {
+            let serials = broadcast_serials.read().clone();
+            broadcast_outputs.set(serials.iter().map(|serial| DeviceOutput {
+                serial: serial.clone(),
+                output: String::new(),
+                failed: false,
+                running: true,
+            }).collect());
+            for serial in serials {
+                let cmd = cmd.clone();
+                let settings_clone = settings_clone.clone();
+                let token = token.clone();
+                spawn(async move {
+                    let result = cancellable(&token, adb_shell(&settings_clone, &serial, &cmd)).await;
+                    if let Some(entry) = broadcast_outputs.write().iter_mut().find(|o| o.serial == serial) {
+                        entry.running = false;
+                        match result {
+                            Ok(out) => entry.output = out,
+                            Err(e) => {
+                                entry.failed = true;
+                                entry.output = e.to_string();
+                            }
+                        }
+                    }
+                });
+            }
+        } else if let Some(device) = app_state.read().selected_device.clone() {
+            is_running.set(true);
+            spawn(async move {
+                match cancellable(&token, adb_shell(&settings_clone, &device.serial, &cmd)).await {
+                    Ok(result) => output.set(result),
+                    Err(e) => output.set(e.to_string()),
+                }
+                is_running.set(false);
+            });
+        } else {
+            output.set("No device selected.".to_string());
+        }
+    };
+
+    let selected = app_state.read().selected_device.clone();
+    let online_devices = app_state.read().devices.iter().filter(|d| d.status == "device").cloned().collect::<Vec<_>>();
+    let can_execute = if broadcast() { !broadcast_serials.read().is_empty() } else { selected.is_some() };
+    let running = if broadcast() { broadcast_outputs.read().iter().any(|o| o.running) } else { is_running() };
+
     rsx! {
         div {
-            h2 { "Command Console" }
+            div { style: "display: flex; align-items: center; gap: 12px; margin-bottom: 12px; font-size: 0.875rem; color: var(--secondary-text);",
+                if broadcast() {
+                    span { i { class: "fas fa-broadcast-tower" } " Broadcasting to {broadcast_serials.read().len()} device(s)" }
+                } else if let Some(dev) = &selected {
+                    span { i { class: "fas fa-mobile-alt" } " Running on {dev.label()}" }
+                }
+                label { style: "margin-left: auto; display: flex; align-items: center;",
+                    "Broadcast mode"
+                    input {
+                        r#type: "checkbox",
+                        checked: "{broadcast}",
+                        oninput: move |event| {
+                            let enabled = event.checked();
+                            if enabled && broadcast_serials.read().is_empty() {
+                                if let Some(dev) = app_state.read().selected_device.as_ref() {
+                                    broadcast_serials.write().push(dev.serial.clone());
+                                }
+                            }
+                            broadcast.set(enabled);
+                        },
+                    }
+                }
+            }
+            if broadcast() {
+                div { style: "display: flex; flex-wrap: wrap; gap: 8px; margin-bottom: 12px;",
+                    for dev in online_devices {
+                        {
+                            let serial = dev.serial.clone();
+                            let is_checked = broadcast_serials.read().contains(&dev.serial);
+                            rsx! {
+                                label { class: "btn btn-secondary", style: "margin: 0;",
+                                    input {
+                                        r#type: "checkbox",
+                                        checked: "{is_checked}",
+                                        style: "margin: 0 6px 0 0;",
+                                        oninput: move |event| {
+                                            let mut serials = broadcast_serials.write();
+                                            if event.checked() {
+                                                if !serials.contains(&serial) {
+                                                    serials.push(serial.clone());
+                                                }
+                                            } else {
+                                                serials.retain(|s| s != &serial);
+                                            }
+                                        },
+                                    }
+                                    "{dev.label()}"
+                                }
+                            }
+                        }
+                    }
+                }
+            }
             div { style: "display: flex; gap: 8px; margin-bottom: 12px;",
-                input { 
-                    r#type: "text", 
+                input {
+                    r#type: "text",
                     placeholder: "Enter ADB command",
                     oninput: move |event| command.set(event.value().clone()),
                     style: "flex: 1;",
                 }
-                button { 
+                button {
                     class: "btn",
-                    onclick: move |_| {
-                        spawn(async move {
-                            let cmd = command.read().clone();
-                            let settings_clone = settings.read().clone();
-                            match adb_shell(&settings_clone, &cmd).await {
-                                Ok(result) => output.set(result),
-                                Err(e) => output.set(e),
-                            }
-                        });
-                    },
+                    disabled: !can_execute,
+                    onclick: execute,
                     i { class: "fas fa-play" }
-                    "Execute" 
+                    "Execute"
+                }
+                if running {
+                    button {
+                        class: "btn btn-secondary",
+                        onclick: move |_| cancel.read().cancel(),
+                        i { class: "fas fa-stop" }
+                        "Cancel"
+                    }
                 }
             }
-            div {
-                class: "terminal-output",
-                pre { {output} }
+            if broadcast() {
+                div { style: "display: grid; grid-template-columns: repeat(auto-fit, minmax(280px, 1fr)); gap: 12px;",
+                    for result in broadcast_outputs.read().iter().cloned() {
+                        div {
+                            div { style: "display: flex; align-items: center; gap: 6px; font-size: 0.875rem; font-weight: 600;",
+                                if result.running {
+                                    i { class: "fas fa-spinner fa-spin" }
+                                } else if result.failed {
+                                    i { class: "fas fa-times-circle", style: "color: var(--error-color);" }
+                                } else {
+                                    i { class: "fas fa-check-circle", style: "color: var(--success-color);" }
+                                }
+                                "{result.serial}"
+                            }
+                            div {
+                                class: "terminal-output",
+                                style: "margin-top: 6px;",
+                                pre { "{result.output}" }
+                            }
+                        }
+                    }
+                }
+            } else {
+                div {
+                    class: "terminal-output",
+                    pre { {output} }
+                }
             }
         }
     }
@@ -0,0 +1,303 @@
+use std::time::{Duration, Instant};
+use dioxus::prelude::*;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use crate::adb::{adb_pull, adb_push, adb_shell, cancellable, shell_quote};
+use crate::adb_client::TransferProgress;
+use crate::error::AdbError;
+use crate::remote_fs::format_size;
+use crate::settings::AdbStudioSettings;
+use crate::ui::ErrorNotice;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransferDirection {
+    Push,
+    Pull,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TransferStatus {
+    Running,
+    Completed(String),
+    Failed(AdbError),
+    Cancelled,
+}
+
+#[derive(Debug, Clone)]
+pub struct Transfer {
+    pub id: u64,
+    pub direction: TransferDirection,
+    pub serial: String,
+    pub source: String,
+    pub destination: String,
+    pub progress: TransferProgress,
+    pub status: TransferStatus,
+    pub cancel: CancellationToken,
+    started: Instant,
+    finished: Option<Instant>,
+}
+
+// Tokens have no identity to compare, so equality only covers what the UI shows.
+impl PartialEq for Transfer {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+            && self.progress == other.progress
+            && self.status == other.status
+            && self.finished == other.finished
+    }
+}
+
+impl Transfer {
+    pub fn is_running(&self) -> bool {
+        self.status == TransferStatus::Running
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.finished.unwrap_or_else(Instant::now).duration_since(self.started)
+    }
+
+    pub fn percent(&self) -> f64 {
+        match (&self.status, self.progress.total_bytes) {
+            (TransferStatus::Completed(_), _) => 100.0,
+            (_, 0) => 0.0,
+            (_, total) => (self.progress.bytes_done as f64 / total as f64 * 100.0).min(100.0),
+        }
+    }
+
+    /// Average bytes per second since the transfer started.
+    pub fn throughput(&self) -> f64 {
+        let secs = self.elapsed().as_secs_f64();
+        if secs > 0.0 { self.progress.bytes_done as f64 / secs } else { 0.0 }
+    }
+
+    pub fn eta(&self) -> Option<Duration> {
+        let rate = self.throughput();
+        let remaining = self.progress.total_bytes.saturating_sub(self.progress.bytes_done);
+        (self.is_running() && rate > 0.0).then(|| Duration::from_secs_f64(remaining as f64 / rate))
+    }
+}
+
+/// Every push and pull started from the UI, shared through context so any view can show them.
+#[derive(Clone, Default)]
+pub struct TransferQueue {
+    pub transfers: Vec<Transfer>,
+    next_id: u64,
+}
+
+impl TransferQueue {
+    pub fn enqueue(
+        &mut self,
+        direction: TransferDirection,
+        serial: &str,
+        source: &str,
+        destination: &str,
+        cancel: CancellationToken,
+    ) -> u64 {
+        self.next_id += 1;
+        self.transfers.push(Transfer {
+            id: self.next_id,
+            direction,
+            serial: serial.to_string(),
+            source: source.to_string(),
+            destination: destination.to_string(),
+            progress: TransferProgress::default(),
+            status: TransferStatus::Running,
+            cancel,
+            started: Instant::now(),
+            finished: None,
+        });
+        self.next_id
+    }
+
+    pub fn get(&self, id: u64) -> Option<&Transfer> {
+        self.transfers.iter().find(|t| t.id == id)
+    }
+
+    fn get_mut(&mut self, id: u64) -> Option<&mut Transfer> {
+        self.transfers.iter_mut().find(|t| t.id == id)
+    }
+
+    pub fn active_count(&self) -> usize {
+        self.transfers.iter().filter(|t| t.is_running()).count()
+    }
+
+    pub fn clear_finished(&mut self) {
+        self.transfers.retain(|t| t.is_running());
+    }
+}
+
+/// Runs a transfer previously added with [`TransferQueue::enqueue`], streaming its progress into
+/// the queue until it completes.
+pub async fn run_transfer(mut queue: Signal<TransferQueue>, id: u64, settings: &AdbStudioSettings) -> Result<String, AdbError> {
+    let Some(transfer) = queue.read().get(id).cloned() else {
+        return Err(AdbError::Protocol(format!("unknown transfer {}", id)));
+    };
+    let (progress_tx, mut progress_rx) = mpsc::unbounded_channel();
+    let operation = cancellable(&transfer.cancel, async {
+        match transfer.direction {
+            TransferDirection::Push => {
+                adb_push(settings, &transfer.serial, &transfer.source, &transfer.destination, Some(&progress_tx)).await
+            }
+            TransferDirection::Pull => {
+                adb_pull(settings, &transfer.serial, &transfer.source, &transfer.destination, Some(&progress_tx)).await
+            }
+        }
+    });
+    tokio::pin!(operation);
+
+    let result = loop {
+        tokio::select! {
+            result = &mut operation => break result,
+            Some(progress) = progress_rx.recv() => {
+                if let Some(t) = queue.write().get_mut(id) {
+                    t.progress = progress;
+                }
+            }
+        }
+    };
+
+    if let Some(t) = queue.write().get_mut(id) {
+        while let Ok(progress) = progress_rx.try_recv() {
+            t.progress = progress;
+        }
+        t.finished = Some(Instant::now());
+        t.status = match &result {
+            Ok(output) => TransferStatus::Completed(output.trim().to_string()),
+            Err(AdbError::Cancelled) => TransferStatus::Cancelled,
+            Err(e) => TransferStatus::Failed(e.clone()),
+        };
+    }
+    // A cancelled push leaves the file it was writing truncated on the device.
+    if result == Err(AdbError::Cancelled) && transfer.direction == TransferDirection::Push {
+        let partial = queue.peek().get(id).map(|t| t.progress.current_file.clone()).unwrap_or_default();
+        if !partial.is_empty() {
+            let _ = adb_shell(settings, &transfer.serial, &format!("rm -f {}", shell_quote(&partial))).await;
+        }
+    }
+    result
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}h {:02}m", secs / 3600, secs % 3600 / 60)
+    } else if secs >= 60 {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+#[component]
+pub fn TransferProgressBar(transfer: Transfer) -> Element {
+    let color = match transfer.status {
+        TransferStatus::Running => "var(--accent-color)",
+        TransferStatus::Completed(_) => "var(--success-color)",
+        TransferStatus::Failed(_) => "var(--error-color)",
+        TransferStatus::Cancelled => "var(--warning-color)",
+    };
+    let detail = match &transfer.status {
+        TransferStatus::Running => format!(
+            "{} / {} · {}/s · ETA {}",
+            format_size(transfer.progress.bytes_done),
+            format_size(transfer.progress.total_bytes),
+            format_size(transfer.throughput() as u64),
+            transfer.eta().map(format_duration).unwrap_or_else(|| "--".to_string())
+        ),
+        TransferStatus::Completed(_) => format!(
+            "{} in {} · {}/s",
+            format_size(transfer.progress.bytes_done),
+            format_duration(transfer.elapsed()),
+            format_size(transfer.throughput() as u64)
+        ),
+        TransferStatus::Failed(_) => "Failed".to_string(),
+        TransferStatus::Cancelled => format!("Cancelled after {}", format_size(transfer.progress.bytes_done)),
+    };
+
+    rsx! {
+        div {
+            style: "background: var(--tertiary-bg); border-radius: 6px; height: 8px; overflow: hidden;",
+            div {
+                style: format!("background: {}; height: 100%; width: {:.1}%; transition: width 0.3s ease;", color, transfer.percent()),
+            }
+        }
+        div {
+            style: "display: flex; justify-content: space-between; margin-top: 4px; font-size: 0.75rem; color: var(--secondary-text);",
+            span { "{transfer.percent():.0}%" }
+            span { "{detail}" }
+        }
+    }
+}
+
+#[component]
+pub fn TransferPanel() -> Element {
+    let mut queue = use_context::<Signal<TransferQueue>>();
+    let transfers = queue.read().transfers.iter().rev().cloned().collect::<Vec<_>>();
+    let has_finished = transfers.iter().any(|t| !t.is_running());
+
+    // Re-render once a second while something is running so elapsed time and ETA stay current.
+    let mut tick = use_signal(|| 0u64);
+    use_future(move || async move {
+        loop {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            if queue.peek().active_count() > 0 {
+                tick += 1;
+            }
+        }
+    });
+    let _ = tick();
+
+    rsx! {
+        div {
+            h2 { "Transfers" }
+            button {
+                class: "btn btn-secondary",
+                disabled: !has_finished,
+                onclick: move |_| queue.write().clear_finished(),
+                i { class: "fas fa-broom" }
+                "Clear Finished"
+            }
+            if transfers.is_empty() {
+                p { style: "color: var(--secondary-text);", "No transfers yet." }
+            }
+            for transfer in transfers {
+                div {
+                    key: "{transfer.id}",
+                    class: "device-info",
+                    style: "margin-top: 12px;",
+                    div {
+                        style: "display: flex; align-items: center; gap: 8px; margin-bottom: 8px;",
+                        i { class: if transfer.direction == TransferDirection::Push { "fas fa-upload" } else { "fas fa-download" } }
+                        span { style: "font-weight: 500; overflow: hidden; text-overflow: ellipsis; white-space: nowrap;",
+                            "{transfer.source} → {transfer.destination}"
+                        }
+                        span { style: "margin-left: auto; color: var(--secondary-text); font-size: 0.8rem;", "{transfer.serial}" }
+                        if transfer.is_running() {
+                            {
+                                let cancel = transfer.cancel.clone();
+                                rsx! {
+                                    button {
+                                        class: "btn btn-secondary",
+                                        style: "margin: 0; padding: 4px 10px;",
+                                        onclick: move |_| cancel.cancel(),
+                                        i { class: "fas fa-times" }
+                                        "Cancel"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    TransferProgressBar { transfer: transfer.clone() }
+                    if transfer.is_running() && !transfer.progress.current_file.is_empty() {
+                        div { style: "font-size: 0.75rem; color: var(--secondary-text); margin-top: 4px;",
+                            "{transfer.progress.current_file}"
+                        }
+                    }
+                    if let TransferStatus::Failed(e) = &transfer.status {
+                        ErrorNotice { error: e.clone() }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1 +1,22 @@
-pub mod terminal;
\ No newline at end of file
+pub mod terminal;
+
+use dioxus::prelude::*;
+use crate::error::AdbError;
+
+#[component]
+pub fn ErrorNotice(error: AdbError) -> Element {
+    rsx! {
+        div {
+            style: "margin: 12px 0; padding: 12px 16px; background: var(--card-bg); border: 1px solid var(--error-color); border-left: 4px solid var(--error-color); border-radius: 6px;",
+            div {
+                style: "display: flex; align-items: center; gap: 8px; color: var(--error-color); font-weight: 600;",
+                i { class: "fas fa-exclamation-triangle" }
+                "{error}"
+            }
+            div {
+                style: "margin-top: 4px; font-size: 0.875rem; color: var(--secondary-text);",
+                "{error.remediation()}"
+            }
+        }
+    }
+}
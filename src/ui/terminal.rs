@@ -0,0 +1,876 @@
+use std::collections::VecDeque;
+use std::ops::Range;
+use dioxus::prelude::*;
+
+const TAB_WIDTH: usize = 8;
+pub const CELL_WIDTH: f64 = 7.8;
+pub const CELL_HEIGHT: f64 = 18.0;
+/// Rows rendered above and below the viewport so short scrolls never show blank space.
+pub const RENDER_MARGIN: usize = 20;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Color {
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    fn css(&self) -> String {
+        match *self {
+            Color::Indexed(index) => {
+                let (r, g, b) = xterm_rgb(index);
+                format!("#{:02x}{:02x}{:02x}", r, g, b)
+            }
+            Color::Rgb(r, g, b) => format!("#{:02x}{:02x}{:02x}", r, g, b),
+        }
+    }
+}
+
+fn xterm_rgb(index: u8) -> (u8, u8, u8) {
+    const BASE: [(u8, u8, u8); 16] = [
+        (0x00, 0x00, 0x00), (0xcd, 0x00, 0x00), (0x00, 0xcd, 0x00), (0xcd, 0xcd, 0x00),
+        (0x00, 0x00, 0xee), (0xcd, 0x00, 0xcd), (0x00, 0xcd, 0xcd), (0xe5, 0xe5, 0xe5),
+        (0x7f, 0x7f, 0x7f), (0xff, 0x00, 0x00), (0x00, 0xff, 0x00), (0xff, 0xff, 0x00),
+        (0x5c, 0x5c, 0xff), (0xff, 0x00, 0xff), (0x00, 0xff, 0xff), (0xff, 0xff, 0xff),
+    ];
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    match index {
+        0..=15 => BASE[index as usize],
+        16..=231 => {
+            let i = index - 16;
+            (LEVELS[(i / 36) as usize], LEVELS[((i / 6) % 6) as usize], LEVELS[(i % 6) as usize])
+        }
+        _ => {
+            let level = 8 + (index - 232) * 10;
+            (level, level, level)
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct CellStyle {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub underline: bool,
+    pub inverse: bool,
+}
+
+impl CellStyle {
+    fn css(&self) -> String {
+        let (fg, bg) = if self.inverse {
+            (
+                self.bg.map(|c| c.css()).unwrap_or_else(|| "var(--card-bg)".to_string()),
+                Some(self.fg.map(|c| c.css()).unwrap_or_else(|| "var(--primary-text)".to_string())),
+            )
+        } else {
+            (self.fg.map(|c| c.css()).unwrap_or_default(), self.bg.map(|c| c.css()))
+        };
+        let mut css = String::new();
+        if !fg.is_empty() {
+            css.push_str(&format!("color:{};", fg));
+        }
+        if let Some(bg) = bg {
+            css.push_str(&format!("background:{};", bg));
+        }
+        if self.bold {
+            css.push_str("font-weight:bold;");
+        }
+        if self.underline {
+            css.push_str("text-decoration:underline;");
+        }
+        css
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Cell {
+    ch: char,
+    style: CellStyle,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Cell { ch: ' ', style: CellStyle::default() }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct TerminalRun {
+    pub text: String,
+    pub style: String,
+}
+
+pub type TerminalRow = Vec<TerminalRun>;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum ParserState {
+    Ground,
+    Escape,
+    EscapeIntermediate,
+    Csi,
+    Osc,
+    OscEscape,
+}
+
+/// A VT100/xterm screen model: feed it the raw bytes a pty produces and read back styled rows.
+/// Covers the cursor movement, erase, scroll-region, SGR color and alternate-screen sequences
+/// used by the Android shell, toybox tools, `top` and `vi`.
+pub struct TerminalScreen {
+    rows: usize,
+    cols: usize,
+    grid: Vec<Vec<Cell>>,
+    scrollback: VecDeque<Vec<Cell>>,
+    scrollback_limit: usize,
+    cursor_row: usize,
+    cursor_col: usize,
+    wrap_pending: bool,
+    style: CellStyle,
+    saved_cursor: (usize, usize, CellStyle),
+    scroll_top: usize,
+    scroll_bottom: usize,
+    alternate: Option<(Vec<Vec<Cell>>, usize, usize)>,
+    pub cursor_visible: bool,
+    pub app_cursor_keys: bool,
+    pub title: String,
+    state: ParserState,
+    params: String,
+    osc: String,
+    utf8: Vec<u8>,
+    responses: Vec<u8>,
+}
+
+impl TerminalScreen {
+    pub fn new(rows: usize, cols: usize, scrollback_limit: usize) -> Self {
+        let rows = rows.max(1);
+        let cols = cols.max(1);
+        Self {
+            rows,
+            cols,
+            grid: vec![vec![Cell::default(); cols]; rows],
+            scrollback: VecDeque::new(),
+            scrollback_limit,
+            cursor_row: 0,
+            cursor_col: 0,
+            wrap_pending: false,
+            style: CellStyle::default(),
+            saved_cursor: (0, 0, CellStyle::default()),
+            scroll_top: 0,
+            scroll_bottom: rows - 1,
+            alternate: None,
+            cursor_visible: true,
+            app_cursor_keys: false,
+            title: String::new(),
+            state: ParserState::Ground,
+            params: String::new(),
+            osc: String::new(),
+            utf8: Vec::new(),
+            responses: Vec::new(),
+        }
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (self.rows, self.cols)
+    }
+
+    /// Bytes the terminal has to send back to the program (cursor position and device
+    /// attribute reports).
+    pub fn take_responses(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.responses)
+    }
+
+    pub fn feed(&mut self, bytes: &[u8]) {
+        let mut pending = std::mem::take(&mut self.utf8);
+        pending.extend_from_slice(bytes);
+        let mut rest = pending.as_slice();
+        loop {
+            match std::str::from_utf8(rest) {
+                Ok(text) => {
+                    text.chars().for_each(|c| self.process(c));
+                    break;
+                }
+                Err(e) => {
+                    let (valid, invalid) = rest.split_at(e.valid_up_to());
+                    std::str::from_utf8(valid).unwrap_or_default().chars().for_each(|c| self.process(c));
+                    match e.error_len() {
+                        Some(len) => {
+                            self.process(char::REPLACEMENT_CHARACTER);
+                            rest = &invalid[len..];
+                        }
+                        None => {
+                            self.utf8 = invalid.to_vec();
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn resize(&mut self, rows: usize, cols: usize) {
+        let rows = rows.max(1);
+        let cols = cols.max(1);
+        if rows == self.rows && cols == self.cols {
+            return;
+        }
+        for line in self.grid.iter_mut() {
+            line.resize(cols, Cell::default());
+        }
+        while self.grid.len() > rows {
+            if self.cursor_row > 0 {
+                let line = self.grid.remove(0);
+                self.push_scrollback(line);
+                self.cursor_row -= 1;
+            } else {
+                self.grid.pop();
+            }
+        }
+        while self.grid.len() < rows {
+            self.grid.push(vec![Cell::default(); cols]);
+        }
+        self.rows = rows;
+        self.cols = cols;
+        self.scroll_top = 0;
+        self.scroll_bottom = rows - 1;
+        self.cursor_row = self.cursor_row.min(rows - 1);
+        self.cursor_col = self.cursor_col.min(cols - 1);
+        self.wrap_pending = false;
+    }
+
+    /// Rows available to `render_range`: the scrollback (hidden on the alternate screen) plus the screen.
+    pub fn line_count(&self) -> usize {
+        self.visible_scrollback() + self.rows
+    }
+
+    /// Rows `range` of the scrollback followed by the visible screen, with the cursor drawn in
+    /// when `show_cursor`.
+    pub fn render_range(&self, range: Range<usize>, show_cursor: bool) -> Vec<TerminalRow> {
+        let scrollback = self.visible_scrollback();
+        let end = range.end.min(self.line_count());
+        (range.start..end)
+            .map(|index| match index.checked_sub(scrollback) {
+                None => render_line(&self.scrollback[index], None),
+                Some(row) => {
+                    let cursor = (show_cursor && self.cursor_visible && row == self.cursor_row).then_some(self.cursor_col);
+                    render_line(&self.grid[row], cursor)
+                }
+            })
+            .collect()
+    }
+
+    fn visible_scrollback(&self) -> usize {
+        if self.alternate.is_some() { 0 } else { self.scrollback.len() }
+    }
+
+    fn process(&mut self, c: char) {
+        match self.state {
+            ParserState::Ground => match c {
+                '\x1b' => self.state = ParserState::Escape,
+                '\r' => {
+                    self.cursor_col = 0;
+                    self.wrap_pending = false;
+                }
+                '\n' | '\x0b' | '\x0c' => self.linefeed(),
+                '\x08' => {
+                    self.cursor_col = self.cursor_col.saturating_sub(1);
+                    self.wrap_pending = false;
+                }
+                '\t' => self.cursor_col = ((self.cursor_col / TAB_WIDTH + 1) * TAB_WIDTH).min(self.cols - 1),
+                c if (c as u32) < 0x20 || c == '\x7f' => {}
+                c => self.put_char(c),
+            },
+            ParserState::Escape => {
+                self.state = ParserState::Ground;
+                match c {
+                    '[' => {
+                        self.params.clear();
+                        self.state = ParserState::Csi;
+                    }
+                    ']' => {
+                        self.osc.clear();
+                        self.state = ParserState::Osc;
+                    }
+                    '(' | ')' | '*' | '+' | '#' | '%' => self.state = ParserState::EscapeIntermediate,
+                    '7' => self.save_cursor(),
+                    '8' => self.restore_cursor(),
+                    'D' => self.linefeed(),
+                    'E' => {
+                        self.cursor_col = 0;
+                        self.linefeed();
+                    }
+                    'M' => self.reverse_index(),
+                    'c' => self.reset(),
+                    _ => {}
+                }
+            }
+            ParserState::EscapeIntermediate => self.state = ParserState::Ground,
+            ParserState::Csi => match c {
+                '0'..='9' | ';' | ':' | '?' | '>' | '<' | '=' | '!' | ' ' | '"' | '\'' => self.params.push(c),
+                '@'..='~' => {
+                    self.state = ParserState::Ground;
+                    self.execute_csi(c);
+                }
+                '\x1b' => self.state = ParserState::Escape,
+                _ => {}
+            },
+            ParserState::Osc => match c {
+                '\x07' => self.finish_osc(),
+                '\x1b' => self.state = ParserState::OscEscape,
+                c => self.osc.push(c),
+            },
+            ParserState::OscEscape => {
+                if c == '\\' {
+                    self.finish_osc();
+                } else {
+                    self.state = ParserState::Ground;
+                }
+            }
+        }
+    }
+
+    fn finish_osc(&mut self) {
+        self.state = ParserState::Ground;
+        if let Some(title) = self.osc.strip_prefix("0;").or_else(|| self.osc.strip_prefix("2;")) {
+            self.title = title.to_string();
+        }
+    }
+
+    fn blank_line(&self) -> Vec<Cell> {
+        vec![Cell::default(); self.cols]
+    }
+
+    fn push_scrollback(&mut self, line: Vec<Cell>) {
+        if self.alternate.is_some() || self.scrollback_limit == 0 {
+            return;
+        }
+        if self.scrollback.len() == self.scrollback_limit {
+            self.scrollback.pop_front();
+        }
+        self.scrollback.push_back(line);
+    }
+
+    fn put_char(&mut self, c: char) {
+        if self.wrap_pending {
+            self.cursor_col = 0;
+            self.linefeed();
+        }
+        self.grid[self.cursor_row][self.cursor_col] = Cell { ch: c, style: self.style };
+        if self.cursor_col + 1 == self.cols {
+            self.wrap_pending = true;
+        } else {
+            self.cursor_col += 1;
+        }
+    }
+
+    fn linefeed(&mut self) {
+        self.wrap_pending = false;
+        if self.cursor_row == self.scroll_bottom {
+            self.scroll_up(1);
+        } else if self.cursor_row + 1 < self.rows {
+            self.cursor_row += 1;
+        }
+    }
+
+    fn reverse_index(&mut self) {
+        self.wrap_pending = false;
+        if self.cursor_row == self.scroll_top {
+            self.scroll_down(1);
+        } else {
+            self.cursor_row = self.cursor_row.saturating_sub(1);
+        }
+    }
+
+    fn scroll_up(&mut self, count: usize) {
+        for _ in 0..count.min(self.scroll_bottom - self.scroll_top + 1) {
+            let line = self.grid.remove(self.scroll_top);
+            if self.scroll_top == 0 {
+                self.push_scrollback(line);
+            }
+            self.grid.insert(self.scroll_bottom, self.blank_line());
+        }
+    }
+
+    fn scroll_down(&mut self, count: usize) {
+        for _ in 0..count.min(self.scroll_bottom - self.scroll_top + 1) {
+            self.grid.remove(self.scroll_bottom);
+            self.grid.insert(self.scroll_top, self.blank_line());
+        }
+    }
+
+    fn save_cursor(&mut self) {
+        self.saved_cursor = (self.cursor_row, self.cursor_col, self.style);
+    }
+
+    fn restore_cursor(&mut self) {
+        let (row, col, style) = self.saved_cursor;
+        self.cursor_row = row.min(self.rows - 1);
+        self.cursor_col = col.min(self.cols - 1);
+        self.style = style;
+        self.wrap_pending = false;
+    }
+
+    fn reset(&mut self) {
+        let scrollback_limit = self.scrollback_limit;
+        let scrollback = std::mem::take(&mut self.scrollback);
+        *self = TerminalScreen::new(self.rows, self.cols, scrollback_limit);
+        self.scrollback = scrollback;
+    }
+
+    fn set_alternate_screen(&mut self, enabled: bool) {
+        if enabled && self.alternate.is_none() {
+            let main = std::mem::replace(&mut self.grid, vec![vec![Cell::default(); self.cols]; self.rows]);
+            self.alternate = Some((main, self.cursor_row, self.cursor_col));
+        } else if !enabled {
+            if let Some((mut main, row, col)) = self.alternate.take() {
+                main.resize(self.rows, self.blank_line());
+                for line in main.iter_mut() {
+                    line.resize(self.cols, Cell::default());
+                }
+                self.grid = main;
+                self.cursor_row = row.min(self.rows - 1);
+                self.cursor_col = col.min(self.cols - 1);
+            }
+        }
+        self.wrap_pending = false;
+    }
+
+    fn erase(&mut self, row: usize, from: usize, to: usize) {
+        let to = to.min(self.cols);
+        for cell in &mut self.grid[row][from.min(to)..to] {
+            *cell = Cell::default();
+        }
+    }
+
+    fn execute_csi(&mut self, action: char) {
+        let private = self.params.starts_with('?');
+        let secondary = self.params.starts_with('>');
+        let params: Vec<usize> = self
+            .params
+            .trim_start_matches(['?', '>', '<', '='])
+            .split(';')
+            .map(|p| p.split(':').next().unwrap_or_default().parse().unwrap_or(0))
+            .collect();
+        let param = |index: usize, default: usize| params.get(index).copied().filter(|v| *v != 0).unwrap_or(default);
+        let last_row = self.rows - 1;
+        let last_col = self.cols - 1;
+        self.wrap_pending = false;
+
+        match action {
+            'A' => self.cursor_row = self.cursor_row.saturating_sub(param(0, 1)),
+            'B' | 'e' => self.cursor_row = (self.cursor_row + param(0, 1)).min(last_row),
+            'C' | 'a' => self.cursor_col = (self.cursor_col + param(0, 1)).min(last_col),
+            'D' => self.cursor_col = self.cursor_col.saturating_sub(param(0, 1)),
+            'E' => {
+                self.cursor_row = (self.cursor_row + param(0, 1)).min(last_row);
+                self.cursor_col = 0;
+            }
+            'F' => {
+                self.cursor_row = self.cursor_row.saturating_sub(param(0, 1));
+                self.cursor_col = 0;
+            }
+            'G' | '`' => self.cursor_col = (param(0, 1) - 1).min(last_col),
+            'd' => self.cursor_row = (param(0, 1) - 1).min(last_row),
+            'H' | 'f' => {
+                self.cursor_row = (param(0, 1) - 1).min(last_row);
+                self.cursor_col = (param(1, 1) - 1).min(last_col);
+            }
+            'J' => match params.first().copied().unwrap_or(0) {
+                0 => {
+                    self.erase(self.cursor_row, self.cursor_col, self.cols);
+                    for row in self.cursor_row + 1..self.rows {
+                        self.erase(row, 0, self.cols);
+                    }
+                }
+                1 => {
+                    for row in 0..self.cursor_row {
+                        self.erase(row, 0, self.cols);
+                    }
+                    self.erase(self.cursor_row, 0, self.cursor_col + 1);
+                }
+                mode => {
+                    for row in 0..self.rows {
+                        self.erase(row, 0, self.cols);
+                    }
+                    if mode == 3 {
+                        self.scrollback.clear();
+                    }
+                }
+            },
+            'K' => match params.first().copied().unwrap_or(0) {
+                0 => self.erase(self.cursor_row, self.cursor_col, self.cols),
+                1 => self.erase(self.cursor_row, 0, self.cursor_col + 1),
+                _ => self.erase(self.cursor_row, 0, self.cols),
+            },
+            'L' | 'M' if (self.scroll_top..=self.scroll_bottom).contains(&self.cursor_row) => {
+                for _ in 0..param(0, 1).min(self.scroll_bottom - self.cursor_row + 1) {
+                    if action == 'L' {
+                        self.grid.remove(self.scroll_bottom);
+                        self.grid.insert(self.cursor_row, self.blank_line());
+                    } else {
+                        self.grid.remove(self.cursor_row);
+                        self.grid.insert(self.scroll_bottom, self.blank_line());
+                    }
+                }
+                self.cursor_col = 0;
+            }
+            'P' => {
+                let count = param(0, 1).min(self.cols - self.cursor_col);
+                let line = &mut self.grid[self.cursor_row];
+                line.drain(self.cursor_col..self.cursor_col + count);
+                line.resize(self.cols, Cell::default());
+            }
+            '@' => {
+                let count = param(0, 1).min(self.cols - self.cursor_col);
+                let line = &mut self.grid[self.cursor_row];
+                for _ in 0..count {
+                    line.insert(self.cursor_col, Cell::default());
+                }
+                line.truncate(self.cols);
+            }
+            'X' => self.erase(self.cursor_row, self.cursor_col, self.cursor_col + param(0, 1)),
+            'S' => self.scroll_up(param(0, 1)),
+            'T' if !secondary => self.scroll_down(param(0, 1)),
+            'r' => {
+                let top = param(0, 1) - 1;
+                let bottom = param(1, self.rows).min(self.rows) - 1;
+                if top < bottom {
+                    self.scroll_top = top;
+                    self.scroll_bottom = bottom;
+                    self.cursor_row = 0;
+                    self.cursor_col = 0;
+                }
+            }
+            'm' => self.select_graphic_rendition(&params),
+            'h' | 'l' if private => {
+                let enabled = action == 'h';
+                for mode in &params {
+                    match mode {
+                        1 => self.app_cursor_keys = enabled,
+                        25 => self.cursor_visible = enabled,
+                        47 | 1047 => self.set_alternate_screen(enabled),
+                        1049 => {
+                            if enabled {
+                                self.save_cursor();
+                                self.set_alternate_screen(true);
+                            } else {
+                                self.set_alternate_screen(false);
+                                self.restore_cursor();
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            's' => self.save_cursor(),
+            'u' => self.restore_cursor(),
+            'n' => match params.first().copied().unwrap_or(0) {
+                5 => self.responses.extend_from_slice(b"\x1b[0n"),
+                6 => self.responses.extend_from_slice(format!("\x1b[{};{}R", self.cursor_row + 1, self.cursor_col + 1).as_bytes()),
+                _ => {}
+            },
+            'c' if !secondary => self.responses.extend_from_slice(b"\x1b[?1;2c"),
+            _ => {}
+        }
+    }
+
+    fn select_graphic_rendition(&mut self, params: &[usize]) {
+        let mut i = 0;
+        while i < params.len() {
+            match params[i] {
+                0 => self.style = CellStyle::default(),
+                1 => self.style.bold = true,
+                4 => self.style.underline = true,
+                7 => self.style.inverse = true,
+                22 => self.style.bold = false,
+                24 => self.style.underline = false,
+                27 => self.style.inverse = false,
+                code @ 30..=37 => self.style.fg = Some(Color::Indexed((code - 30) as u8)),
+                code @ 40..=47 => self.style.bg = Some(Color::Indexed((code - 40) as u8)),
+                code @ 90..=97 => self.style.fg = Some(Color::Indexed((code - 90 + 8) as u8)),
+                code @ 100..=107 => self.style.bg = Some(Color::Indexed((code - 100 + 8) as u8)),
+                39 => self.style.fg = None,
+                49 => self.style.bg = None,
+                code @ (38 | 48) => {
+                    let color = match params.get(i + 1) {
+                        Some(5) => {
+                            i += 2;
+                            params.get(i).map(|n| Color::Indexed(*n as u8))
+                        }
+                        Some(2) => {
+                            i += 4;
+                            match (params.get(i - 2), params.get(i - 1), params.get(i)) {
+                                (Some(r), Some(g), Some(b)) => Some(Color::Rgb(*r as u8, *g as u8, *b as u8)),
+                                _ => None,
+                            }
+                        }
+                        _ => None,
+                    };
+                    if code == 38 {
+                        self.style.fg = color;
+                    } else {
+                        self.style.bg = color;
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+}
+
+/// The rows to render for a viewport `visible` rows tall that starts at `first_visible`, or at
+/// the bottom when `None`.
+pub fn render_window(total: usize, visible: usize, first_visible: Option<usize>) -> Range<usize> {
+    let first = first_visible.unwrap_or(usize::MAX).min(total.saturating_sub(visible));
+    first.saturating_sub(RENDER_MARGIN)..(first + visible + RENDER_MARGIN).min(total)
+}
+
+fn render_line(line: &[Cell], cursor: Option<usize>) -> TerminalRow {
+    let end = line
+        .iter()
+        .rposition(|cell| cell.ch != ' ' || cell.style.bg.is_some() || cell.style.inverse)
+        .map(|i| i + 1)
+        .unwrap_or(0)
+        .max(cursor.map(|c| c + 1).unwrap_or(0))
+        .min(line.len());
+    let mut runs: Vec<(CellStyle, String)> = Vec::new();
+    for (index, cell) in line[..end].iter().enumerate() {
+        let mut style = cell.style;
+        if cursor == Some(index) {
+            style.inverse = !style.inverse;
+        }
+        match runs.last_mut() {
+            Some((run_style, text)) if *run_style == style => text.push(cell.ch),
+            _ => runs.push((style, cell.ch.to_string())),
+        }
+    }
+    runs.into_iter().map(|(style, text)| TerminalRun { text, style: style.css() }).collect()
+}
+
+/// Translates a key press into the bytes an xterm would send.
+pub fn key_to_bytes(event: &KeyboardData, app_cursor_keys: bool) -> Option<Vec<u8>> {
+    let modifiers = event.modifiers();
+    if modifiers.meta() {
+        return None;
+    }
+    let cursor = |code: u8| {
+        if app_cursor_keys {
+            vec![0x1b, b'O', code]
+        } else {
+            vec![0x1b, b'[', code]
+        }
+    };
+    let bytes = match event.key() {
+        Key::Character(text) => {
+            let mut bytes = if modifiers.ctrl() {
+                match text.chars().next()? {
+                    c @ ('a'..='z' | 'A'..='Z') => vec![(c.to_ascii_uppercase() as u8) & 0x1f],
+                    '[' => vec![0x1b],
+                    '\\' => vec![0x1c],
+                    ']' => vec![0x1d],
+                    ' ' | '@' | '2' => vec![0x00],
+                    _ => return None,
+                }
+            } else {
+                text.into_bytes()
+            };
+            if modifiers.alt() {
+                bytes.insert(0, 0x1b);
+            }
+            bytes
+        }
+        Key::Enter => vec![b'\r'],
+        Key::Backspace => vec![0x7f],
+        Key::Tab if modifiers.shift() => b"\x1b[Z".to_vec(),
+        Key::Tab => vec![b'\t'],
+        Key::Escape => vec![0x1b],
+        Key::ArrowUp => cursor(b'A'),
+        Key::ArrowDown => cursor(b'B'),
+        Key::ArrowRight => cursor(b'C'),
+        Key::ArrowLeft => cursor(b'D'),
+        Key::Home => cursor(b'H'),
+        Key::End => cursor(b'F'),
+        Key::Insert => b"\x1b[2~".to_vec(),
+        Key::Delete => b"\x1b[3~".to_vec(),
+        Key::PageUp => b"\x1b[5~".to_vec(),
+        Key::PageDown => b"\x1b[6~".to_vec(),
+        Key::F1 => b"\x1bOP".to_vec(),
+        Key::F2 => b"\x1bOQ".to_vec(),
+        Key::F3 => b"\x1bOR".to_vec(),
+        Key::F4 => b"\x1bOS".to_vec(),
+        Key::F5 => b"\x1b[15~".to_vec(),
+        Key::F6 => b"\x1b[17~".to_vec(),
+        Key::F7 => b"\x1b[18~".to_vec(),
+        Key::F8 => b"\x1b[19~".to_vec(),
+        Key::F9 => b"\x1b[20~".to_vec(),
+        Key::F10 => b"\x1b[21~".to_vec(),
+        Key::F11 => b"\x1b[23~".to_vec(),
+        Key::F12 => b"\x1b[24~".to_vec(),
+        _ => return None,
+    };
+    Some(bytes)
+}
+
+/// Renders a window of terminal rows, forwards key presses as pty input and reports the size in
+/// cells whenever the element is resized. `rows` start at `first_row` out of `total_rows`; the
+/// rest are spacers, and `on_scroll` reports the first row in view (`None` at the bottom) so the
+/// caller can move the window. Sticks to the bottom unless the user scrolled up.
+#[component]
+pub fn TerminalView(
+    id: String,
+    total_rows: usize,
+    first_row: usize,
+    rows: Vec<TerminalRow>,
+    app_cursor_keys: bool,
+    on_input: EventHandler<Vec<u8>>,
+    on_resize: EventHandler<(u16, u16)>,
+    on_scroll: EventHandler<Option<usize>>,
+) -> Element {
+    let mut follow = use_signal(|| true);
+    let element_id = id.clone();
+    let top_spacer = first_row as f64 * CELL_HEIGHT;
+    let bottom_spacer = total_rows.saturating_sub(first_row + rows.len()) as f64 * CELL_HEIGHT;
+
+    use_effect(use_reactive!(|total_rows| {
+        let _ = total_rows;
+        if *follow.peek() {
+            document::eval(&format!(
+                "let el = document.getElementById('{}'); if (el) {{ el.scrollTop = el.scrollHeight; }}",
+                element_id
+            ));
+        }
+    }));
+
+    rsx! {
+        div {
+            id: "{id}",
+            class: "terminal-output",
+            tabindex: "0",
+            style: "outline: none; font-size: 13px; line-height: {CELL_HEIGHT}px; white-space: pre; color: var(--primary-text); cursor: text;",
+            onkeydown: move |event| {
+                if let Some(bytes) = key_to_bytes(&event.data(), app_cursor_keys) {
+                    event.prevent_default();
+                    follow.set(true);
+                    on_input.call(bytes);
+                }
+            },
+            onscroll: move |event| {
+                let at_bottom = event.scroll_top() + event.client_height() as f64 >= event.scroll_height() as f64 - CELL_HEIGHT;
+                if *follow.peek() != at_bottom {
+                    follow.set(at_bottom);
+                }
+                let first_visible = (event.scroll_top() / CELL_HEIGHT).floor().max(0.0) as usize;
+                on_scroll.call((!at_bottom).then_some(first_visible));
+            },
+            onresize: move |event| {
+                if let Ok(size) = event.get_content_box_size() {
+                    let cols = (size.width / CELL_WIDTH).floor().max(20.0) as u16;
+                    let rows = (size.height / CELL_HEIGHT).floor().max(5.0) as u16;
+                    on_resize.call((rows, cols));
+                }
+            },
+            div { style: "height: {top_spacer}px;" }
+            for row in rows.iter() {
+                div { style: "height: {CELL_HEIGHT}px;",
+                    for run in row.iter() {
+                        span { style: "{run.style}", "{run.text}" }
+                    }
+                }
+            }
+            div { style: "height: {bottom_spacer}px;" }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(screen: &TerminalScreen) -> Vec<String> {
+        screen.grid.iter().map(|line| line.iter().map(|cell| cell.ch).collect::<String>().trim_end().to_string()).collect()
+    }
+
+    fn cursor(screen: &TerminalScreen) -> (usize, usize) {
+        (screen.cursor_row, screen.cursor_col)
+    }
+
+    #[test]
+    fn writes_text_and_moves_the_cursor() {
+        let mut screen = TerminalScreen::new(3, 10, 100);
+        screen.feed(b"hello\r\nworld");
+        assert_eq!(lines(&screen), ["hello", "world", ""]);
+        assert_eq!(cursor(&screen), (1, 5));
+
+        screen.feed(b"\x1b[1;2Hx\x1b[K");
+        assert_eq!(lines(&screen), ["hx", "world", ""]);
+        assert_eq!(cursor(&screen), (0, 2));
+
+        screen.feed(b"\x1b[3;4H\x1b[31mred\x1b[0m");
+        assert_eq!(lines(&screen)[2], "   red");
+        assert_eq!(screen.grid[2][3].style.fg, Some(Color::Indexed(1)));
+        assert_eq!(screen.grid[2][6].style, CellStyle::default());
+    }
+
+    #[test]
+    fn wraps_and_scrolls_into_scrollback() {
+        let mut screen = TerminalScreen::new(2, 5, 100);
+        screen.feed(b"0123456789\r\nnext");
+        assert_eq!(lines(&screen), ["56789", "next"]);
+        assert_eq!(screen.line_count(), 3);
+        let rows = screen.render_range(0..1, false);
+        assert_eq!(rows[0][0].text, "01234");
+    }
+
+    #[test]
+    fn chunk_ending_mid_escape_sequence() {
+        let mut screen = TerminalScreen::new(3, 10, 100);
+        screen.feed(b"abc\x1b[");
+        screen.feed(b"2");
+        screen.feed(b"D!");
+        assert_eq!(lines(&screen)[0], "a!c");
+        assert_eq!(cursor(&screen), (0, 2));
+    }
+
+    #[test]
+    fn chunk_ending_mid_utf8_character() {
+        let mut screen = TerminalScreen::new(3, 10, 100);
+        let bytes = "ab\u{e9}\u{4e2d}".as_bytes();
+        screen.feed(&bytes[..3]);
+        assert_eq!(lines(&screen)[0], "ab");
+        screen.feed(&bytes[3..5]);
+        screen.feed(&bytes[5..]);
+        assert_eq!(lines(&screen)[0], "ab\u{e9}\u{4e2d}");
+        assert_eq!(cursor(&screen), (0, 4));
+
+        screen.feed(b"\xff!");
+        assert_eq!(lines(&screen)[0], "ab\u{e9}\u{4e2d}\u{fffd}!");
+    }
+
+    #[test]
+    fn alternate_screen_restores_the_main_screen() {
+        let mut screen = TerminalScreen::new(3, 10, 100);
+        screen.feed(b"shell$ ");
+        screen.feed(b"\x1b[?1049h\x1b[2J\x1b[HALT");
+        assert_eq!(lines(&screen), ["ALT", "", ""]);
+        screen.feed(b"\x1b[?1049l");
+        assert_eq!(lines(&screen), ["shell$", "", ""]);
+        assert_eq!(cursor(&screen), (0, 7));
+    }
+
+    #[test]
+    fn answers_cursor_position_reports() {
+        let mut screen = TerminalScreen::new(3, 10, 100);
+        screen.feed(b"\r\nab\x1b[6n");
+        assert_eq!(screen.take_responses(), b"\x1b[2;3R");
+        assert!(screen.take_responses().is_empty());
+    }
+
+    #[test]
+    fn render_window_follows_the_bottom_or_the_scroll_position() {
+        assert_eq!(render_window(10, 24, None), 0..10);
+        assert_eq!(render_window(2000, 24, None), 2000 - 24 - RENDER_MARGIN..2000);
+        assert_eq!(render_window(2000, 24, Some(500)), 500 - RENDER_MARGIN..524 + RENDER_MARGIN);
+        assert_eq!(render_window(2000, 24, Some(5)), 0..29 + RENDER_MARGIN);
+        assert_eq!(render_window(2000, 24, Some(1990)), 1976 - RENDER_MARGIN..2000);
+    }
+}
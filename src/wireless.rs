@@ -0,0 +1,414 @@
+use std::time::{Duration, Instant};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use confy::store;
+use dioxus::prelude::*;
+use qrcode::render::svg;
+use qrcode::QrCode;
+use tokio_util::sync::CancellationToken;
+use crate::adb::{adb_connect, adb_pair, adb_shell, adb_tcpip, cancellable};
+use crate::app::AppState;
+use crate::error::AdbError;
+use crate::mdns::{discover, CONNECT_SERVICE, PAIRING_SERVICE};
+use crate::settings::AdbStudioSettings;
+use crate::ui::ErrorNotice;
+
+pub const DEFAULT_TCPIP_PORT: u16 = 5555;
+/// How long to wait for the phone to scan the QR code before giving up.
+const QR_PAIRING_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Finds the device's Wi-Fi IPv4 address, for connecting after `adb tcpip`.
+pub async fn device_wifi_address(settings: &AdbStudioSettings, serial: &str) -> Result<String, AdbError> {
+    let output = adb_shell(settings, serial, "ip -f inet addr show wlan0").await.unwrap_or_default();
+    // inet 192.168.1.23/24 brd 192.168.1.255 scope global wlan0
+    let from_addr = output.lines().find_map(|line| {
+        let rest = line.trim().strip_prefix("inet ")?;
+        rest.split('/').next().map(str::to_string)
+    });
+    if let Some(address) = from_addr {
+        return Ok(address);
+    }
+    // 192.168.1.0/24 dev wlan0 proto kernel scope link src 192.168.1.23
+    let routes = adb_shell(settings, serial, "ip route").await?;
+    routes
+        .lines()
+        .find_map(|line| line.split_once(" src ").and_then(|(_, src)| src.split_whitespace().next()).map(str::to_string))
+        .ok_or_else(|| AdbError::CommandFailed {
+            code: None,
+            message: "the device has no Wi-Fi address; connect it to the same network first".to_string(),
+        })
+}
+
+/// Random characters from the OS generator; the password is what keeps other devices on the
+/// network from pairing in our place.
+fn random_string(len: usize) -> Result<String, AdbError> {
+    // 32 characters, so each random byte maps onto one without bias.
+    const ALPHABET: &[u8; 32] = b"abcdefghijkmnpqrstuvwxyz23456789";
+    let mut bytes = vec![0u8; len];
+    getrandom::getrandom(&mut bytes).map_err(|e| AdbError::Io(format!("could not generate a pairing code: {}", e)))?;
+    Ok(bytes.iter().map(|b| ALPHABET[*b as usize % ALPHABET.len()] as char).collect())
+}
+
+/// The service name and password a phone learns by scanning the pairing QR code. It then
+/// advertises `_adb-tls-pairing._tcp` under that name, which tells us where to pair.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QrPairing {
+    pub service_name: String,
+    pub password: String,
+}
+
+impl QrPairing {
+    pub fn generate() -> Result<Self, AdbError> {
+        Ok(QrPairing { service_name: format!("adbstudio-{}", random_string(8)?), password: random_string(10)? })
+    }
+
+    pub fn payload(&self) -> String {
+        format!("WIFI:T:ADB;S:{};P:{};;", self.service_name, self.password)
+    }
+
+    pub fn svg_data_url(&self) -> Result<String, AdbError> {
+        let code = QrCode::new(self.payload().as_bytes()).map_err(|e| AdbError::Io(e.to_string()))?;
+        let image = code
+            .render::<svg::Color>()
+            .min_dimensions(220, 220)
+            .dark_color(svg::Color("#000000"))
+            .light_color(svg::Color("#ffffff"))
+            .build();
+        Ok(format!("data:image/svg+xml;base64,{}", STANDARD.encode(image)))
+    }
+
+    /// Waits for the phone to advertise the pairing service, pairs with it, then connects to the
+    /// device's connect service on the same host. Returns the connect address, or `None` when
+    /// pairing worked but the connect service never showed up; the pairing port only accepts
+    /// the one pairing, so it is no use for connecting.
+    pub async fn pair(&self, settings: &AdbStudioSettings) -> Result<Option<String>, AdbError> {
+        let started = Instant::now();
+        let pairing = loop {
+            let services = discover(settings).await?;
+            if let Some(service) = services.into_iter().find(|s| s.service_type == PAIRING_SERVICE && s.name == self.service_name) {
+                break service;
+            }
+            if started.elapsed() > QR_PAIRING_TIMEOUT {
+                return Err(AdbError::CommandFailed {
+                    code: None,
+                    message: "no device scanned the QR code; check that both are on the same network".to_string(),
+                });
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        };
+        adb_pair(settings, &pairing.address, &self.password).await?;
+
+        // The server usually connects on its own once paired; look for the connect service so
+        // the device can be remembered either way.
+        let host = pairing.address.rsplit_once(':').map(|(host, _)| host).unwrap_or(&pairing.address).to_string();
+        for _ in 0..10 {
+            let services = discover(settings).await.unwrap_or_default();
+            if let Some(service) = services.iter().find(|s| s.service_type == CONNECT_SERVICE && s.address.starts_with(&format!("{}:", host))) {
+                adb_connect(settings, &service.address).await?;
+                return Ok(Some(service.address.clone()));
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+        Ok(None)
+    }
+}
+
+/// Connects to `address` and saves it to the known device list.
+pub async fn connect_and_remember(mut settings: Signal<AdbStudioSettings>, address: &str, name: &str) -> Result<String, AdbError> {
+    let settings_clone = settings.read().clone();
+    let message = adb_connect(&settings_clone, address).await?;
+    settings.write().remember_device(address, name);
+    let _ = store("adb-studio", None, settings.read().clone());
+    Ok(message)
+}
+
+#[component]
+pub fn WirelessPanel() -> Element {
+    let mut settings = use_context::<Signal<AdbStudioSettings>>();
+    let app_state = use_context::<Signal<AppState>>();
+    let mut connect_address = use_signal(String::new);
+    let mut pair_address = use_signal(String::new);
+    let mut pair_code = use_signal(String::new);
+    let mut tcpip_port = use_signal(|| DEFAULT_TCPIP_PORT.to_string());
+    let mut qr = use_signal(|| None::<(QrPairing, String)>);
+    let mut qr_cancel = use_signal(CancellationToken::new);
+    let mut busy = use_signal(|| false);
+    let mut status = use_signal(String::new);
+    let mut error = use_signal(|| None::<AdbError>);
+
+    use_drop(move || qr_cancel.peek().cancel());
+
+    let connect = move |address: String, name: String| {
+        if address.trim().is_empty() {
+            return;
+        }
+        spawn(async move {
+            busy.set(true);
+            error.set(None);
+            status.set(format!("Connecting to {}...", address));
+            match connect_and_remember(settings, address.trim(), &name).await {
+                Ok(message) => status.set(message),
+                Err(e) => {
+                    status.set(String::new());
+                    error.set(Some(e));
+                }
+            }
+            busy.set(false);
+        });
+    };
+
+    let selected = app_state.read().selected_device.clone();
+    // tcpip only makes sense for a device that isn't already on the network.
+    let usb_device = selected.filter(|d| d.status == "device" && d.connection() != "Wi-Fi");
+    let known_devices = settings.read().known_devices.clone();
+
+    rsx! {
+        div {
+            class: "device-info",
+            h3 { "Wireless Debugging" }
+            if !status.read().is_empty() {
+                p { style: "font-size: 0.875rem; color: var(--secondary-text); margin-bottom: 8px;", "{status}" }
+            }
+            if let Some(e) = error() {
+                ErrorNotice { error: e }
+            }
+            div { class: "form-group",
+                label { "Connect" }
+                div { style: "display: flex; gap: 8px; align-items: center;",
+                    input {
+                        r#type: "text",
+                        placeholder: "192.168.1.23:5555",
+                        value: "{connect_address}",
+                        oninput: move |e| connect_address.set(e.value()),
+                    }
+                    button {
+                        class: "btn",
+                        style: "margin: 0;",
+                        disabled: busy() || connect_address.read().trim().is_empty(),
+                        onclick: move |_| connect(connect_address(), String::new()),
+                        i { class: "fas fa-link" }
+                        "Connect"
+                    }
+                }
+            }
+            div { class: "form-group",
+                label { "Pair with code (Android 11+)" }
+                div { style: "display: flex; gap: 8px; align-items: center;",
+                    input {
+                        r#type: "text",
+                        placeholder: "Pairing address, e.g. 192.168.1.23:37099",
+                        value: "{pair_address}",
+                        oninput: move |e| pair_address.set(e.value()),
+                    }
+                    input {
+                        r#type: "text",
+                        placeholder: "Code",
+                        style: "max-width: 120px;",
+                        value: "{pair_code}",
+                        oninput: move |e| pair_code.set(e.value()),
+                    }
+                    button {
+                        class: "btn",
+                        style: "margin: 0;",
+                        disabled: busy() || pair_address.read().trim().is_empty() || pair_code.read().trim().is_empty(),
+                        onclick: move |_| {
+                            spawn(async move {
+                                busy.set(true);
+                                error.set(None);
+                                let settings_clone = settings.read().clone();
+                                match adb_pair(&settings_clone, pair_address().trim(), pair_code().trim()).await {
+                                    Ok(message) => status.set(format!("{}. Connect using the address shown under Wireless debugging.", message)),
+                                    Err(e) => error.set(Some(e)),
+                                }
+                                busy.set(false);
+                            });
+                        },
+                        i { class: "fas fa-key" }
+                        "Pair"
+                    }
+                }
+            }
+            div { class: "form-group",
+                label { "Pair with QR code (Android 11+)" }
+                if let Some((pairing, image)) = qr() {
+                    div { style: "display: flex; gap: 16px; align-items: center;",
+                        img { src: "{image}", style: "width: 220px; height: 220px; border-radius: 6px;" }
+                        div {
+                            p { style: "font-size: 0.875rem; margin-bottom: 8px;",
+                                "On the device, open Developer options › Wireless debugging › Pair device with QR code and scan this code."
+                            }
+                            p { style: "font-size: 0.75rem; color: var(--secondary-text); margin-bottom: 8px;", "Service {pairing.service_name}" }
+                            button {
+                                class: "btn btn-secondary",
+                                onclick: move |_| {
+                                    qr_cancel.peek().cancel();
+                                    qr.set(None);
+                                    status.set(String::new());
+                                },
+                                i { class: "fas fa-times" }
+                                "Cancel"
+                            }
+                        }
+                    }
+                } else {
+                    button {
+                        class: "btn btn-secondary",
+                        disabled: busy(),
+                        onclick: move |_| {
+                            let image = QrPairing::generate().and_then(|pairing| Ok((pairing.svg_data_url()?, pairing)));
+                            let (image, pairing) = match image {
+                                Ok(image) => image,
+                                Err(e) => {
+                                    error.set(Some(e));
+                                    return;
+                                }
+                            };
+                            let token = CancellationToken::new();
+                            qr_cancel.set(token.clone());
+                            qr.set(Some((pairing.clone(), image)));
+                            error.set(None);
+                            status.set("Waiting for the device to scan the code...".to_string());
+                            spawn(async move {
+                                let settings_clone = settings.read().clone();
+                                match cancellable(&token, pairing.pair(&settings_clone)).await {
+                                    Ok(Some(address)) => {
+                                        settings.write().remember_device(&address, "");
+                                        let _ = store("adb-studio", None, settings.read().clone());
+                                        status.set(format!("Paired and connected to {}", address));
+                                    }
+                                    Ok(None) => {
+                                        status.set(
+                                            "Paired, but the device didn't advertise its connect address. Enter the IP address and port shown under Wireless debugging to connect."
+                                                .to_string(),
+                                        );
+                                    }
+                                    Err(AdbError::Cancelled) => return,
+                                    Err(e) => {
+                                        status.set(String::new());
+                                        error.set(Some(e));
+                                    }
+                                }
+                                qr.set(None);
+                            });
+                        },
+                        i { class: "fas fa-qrcode" }
+                        "Show QR Code"
+                    }
+                }
+            }
+            if let Some(device) = usb_device {
+                div { class: "form-group",
+                    label { "Switch {device.name()} to Wi-Fi (adb tcpip)" }
+                    div { style: "display: flex; gap: 8px; align-items: center;",
+                        input {
+                            r#type: "number",
+                            min: "1",
+                            max: "65535",
+                            style: "max-width: 120px;",
+                            value: "{tcpip_port}",
+                            oninput: move |e| tcpip_port.set(e.value()),
+                        }
+                        button {
+                            class: "btn",
+                            style: "margin: 0;",
+                            disabled: busy(),
+                            onclick: move |_| {
+                                let device = device.clone();
+                                spawn(async move {
+                                    let Ok(port) = tcpip_port().trim().parse::<u16>() else {
+                                        error.set(Some(AdbError::CommandFailed { code: None, message: "invalid port".to_string() }));
+                                        return;
+                                    };
+                                    busy.set(true);
+                                    error.set(None);
+                                    let settings_clone = settings.read().clone();
+                                    let result = async {
+                                        // Look up the address first; the USB connection drops once adbd restarts.
+                                        let host = device_wifi_address(&settings_clone, &device.serial).await?;
+                                        status.set(format!("Restarting adbd on {} in TCP mode...", device.name()));
+                                        adb_tcpip(&settings_clone, &device.serial, port).await?;
+                                        let address = format!("{}:{}", host, port);
+                                        let mut last_error = None;
+                                        for _ in 0..5 {
+                                            tokio::time::sleep(Duration::from_secs(1)).await;
+                                            match connect_and_remember(settings, &address, device.name()).await {
+                                                Ok(message) => return Ok(message),
+                                                Err(e) => last_error = Some(e),
+                                            }
+                                        }
+                                        Err(last_error.unwrap_or(AdbError::Offline))
+                                    }
+                                    .await;
+                                    match result {
+                                        Ok(message) => status.set(message),
+                                        Err(e) => {
+                                            status.set(String::new());
+                                            error.set(Some(e));
+                                        }
+                                    }
+                                    busy.set(false);
+                                });
+                            },
+                            i { class: "fas fa-wifi" }
+                            "Enable Wi-Fi Debugging"
+                        }
+                    }
+                }
+            }
+            if !known_devices.is_empty() {
+                h3 { "Known Network Devices" }
+                for known in known_devices {
+                    div {
+                        key: "{known.address}",
+                        class: "device-info-item",
+                        span { class: "label",
+                            if known.name.is_empty() { "{known.address}" } else { "{known.name} ({known.address})" }
+                        }
+                        span {
+                            {
+                                let address = known.address.clone();
+                                let name = known.name.clone();
+                                let forget = known.address.clone();
+                                rsx! {
+                                    button {
+                                        class: "btn btn-secondary",
+                                        style: "margin: 0 4px 0 0; padding: 4px 10px;",
+                                        disabled: busy(),
+                                        onclick: move |_| connect(address.clone(), name.clone()),
+                                        i { class: "fas fa-link" }
+                                        "Connect"
+                                    }
+                                    button {
+                                        class: "btn btn-secondary",
+                                        style: "margin: 0; padding: 4px 10px;",
+                                        onclick: move |_| {
+                                            settings.write().forget_device(&forget);
+                                            let _ = store("adb-studio", None, settings.read().clone());
+                                        },
+                                        i { class: "fas fa-trash" }
+                                        "Forget"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pairing_codes_are_random() {
+        let first = QrPairing::generate().unwrap();
+        let second = QrPairing::generate().unwrap();
+        assert_ne!(first.password, second.password);
+        assert_eq!(first.password.len(), 10);
+        assert!(first.service_name.starts_with("adbstudio-"));
+        assert!(first.password.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit()));
+        assert_eq!(first.payload(), format!("WIFI:T:ADB;S:{};P:{};;", first.service_name, first.password));
+    }
+}